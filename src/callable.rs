@@ -3,18 +3,97 @@ use std::fmt;
 use std::rc::Rc;
 
 use crate::error::*;
+use crate::expr::Expr;
 use crate::interpreter::*;
 use crate::lox_class::*;
 use crate::object::*;
+use crate::token::Token;
 
 pub trait LoxCallable {
-    fn arity(&self) -> usize;
+    fn arity(&self) -> Arity;
     fn call(
         &self,
         interpreter: &Interpreter,
         arguments: Vec<Object>,
         klass: Option<Rc<LoxClass>>,
     ) -> Result<Object, LoxResult>;
+
+    // Names of the parameters that named arguments are matched against.
+    // Callables that don't name them only take positional arguments.
+    fn param_names(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    // Value of the parameter at 'index' when a call with named arguments
+    // leaves it out, or None if it is required. 'arguments' holds the
+    // values of the parameters before it.
+    fn default_argument(
+        &self,
+        _interpreter: &Interpreter,
+        _index: usize,
+        _arguments: &[Object],
+    ) -> Result<Option<Object>, LoxResult> {
+        Ok(None)
+    }
+}
+
+// 'name: value' in the arguments of a call
+#[derive(Clone)]
+pub struct NamedArgument {
+    pub name: Token,
+    pub value: Rc<Expr>,
+}
+
+// Put named arguments in the position of the parameters they name. The
+// parameters left out take their default value.
+pub fn arrange_arguments(
+    callable: &dyn LoxCallable,
+    interpreter: &Interpreter,
+    mut positional: Vec<Object>,
+    named: Vec<(Token, Object)>,
+    paren: &Token,
+) -> Result<Vec<Object>, LoxResult> {
+    let names = callable.param_names();
+    let extra = positional.split_off(positional.len().min(names.len()));
+    let mut slots: Vec<Option<Object>> = positional.into_iter().map(Some).collect();
+    slots.resize(names.len(), None);
+    for (name, value) in named {
+        let index = match names.iter().position(|param| *param == name.lexeme) {
+            Some(index) => index,
+            None => {
+                return Err(LoxResult::error_runtime(
+                    &name,
+                    &format!("Unknown argument '{}'", name.lexeme),
+                ))
+            }
+        };
+        if slots[index].is_some() {
+            return Err(LoxResult::error_runtime(
+                &name,
+                &format!("Argument '{}' is given more than once", name.lexeme),
+            ));
+        }
+        slots[index] = Some(value);
+    }
+
+    let mut arguments = Vec::new();
+    for (slot, name) in slots.into_iter().zip(names.iter()) {
+        let value = match slot {
+            Some(value) => value,
+            None => match callable.default_argument(interpreter, arguments.len(), &arguments)? {
+                Some(value) => value,
+                None => {
+                    return Err(LoxResult::error_runtime(
+                        paren,
+                        &format!("Missing argument '{}'", name),
+                    ))
+                }
+            },
+        };
+        arguments.push(value);
+    }
+    arguments.extend(extra);
+    Ok(arguments)
 }
 
 #[derive(Clone)]
@@ -43,3 +122,34 @@ impl PartialEq for Callable {
         )
     }
 }
+
+// Range of the number of arguments a callable accepts. There is no upper
+// bound for functions with a rest parameter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Arity {
+    pub min: usize,
+    pub max: Option<usize>,
+}
+
+impl Arity {
+    pub fn exact(count: usize) -> Self {
+        Self {
+            min: count,
+            max: Some(count),
+        }
+    }
+
+    pub fn accepts(&self, count: usize) -> bool {
+        count >= self.min && self.max.is_none_or(|max| count <= max)
+    }
+}
+
+impl fmt::Display for Arity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.max {
+            Some(max) if max == self.min => write!(f, "{}", max),
+            Some(max) => write!(f, "{} to {}", self.min, max),
+            None => write!(f, "at least {}", self.min),
+        }
+    }
+}
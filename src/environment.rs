@@ -6,9 +6,21 @@ use std::collections::hash_map;
 use std::collections::HashMap;
 use std::rc::Rc;
 
+// How a variable was declared. A 'let' is a 'var' that can't be declared
+// again in the same scope, not even at the top level. A 'const' is a 'let'
+// that can't be assigned to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VarKind {
+    Var,
+    Let,
+    Const,
+}
+
 #[derive(Debug)]
 pub struct Environment {
     pub values: HashMap<String, Object>,
+    // Kinds of the variables not declared with 'var'
+    kinds: HashMap<String, VarKind>,
     pub enclosing: Option<Rc<RefCell<Environment>>>,
 }
 
@@ -16,6 +28,7 @@ impl Environment {
     pub fn new() -> Environment {
         Environment {
             values: HashMap::new(),
+            kinds: HashMap::new(),
             enclosing: None,
         }
     }
@@ -23,6 +36,7 @@ impl Environment {
     pub fn new_enclosing(enclosing: Rc<RefCell<Environment>>) -> Environment {
         Environment {
             values: HashMap::new(),
+            kinds: HashMap::new(),
             enclosing: Some(enclosing),
         }
     }
@@ -31,6 +45,23 @@ impl Environment {
         self.values.insert(name.to_string(), value);
     }
 
+    // Define a variable from a declaration. The resolver rejects duplicate
+    // locals, so the checks here only matter for globals.
+    pub fn declare(&mut self, name: &Token, kind: VarKind, value: Object) -> Result<(), LoxResult> {
+        let previous = self.kinds.get(&name.lexeme).copied();
+        if self.values.contains_key(&name.lexeme) && (kind != VarKind::Var || previous.is_some()) {
+            return Err(LoxResult::error_runtime(
+                name,
+                &format!("Variable '{}' is already declared", name.lexeme),
+            ));
+        }
+        if kind != VarKind::Var {
+            self.kinds.insert(name.lexeme.clone(), kind);
+        }
+        self.define(&name.lexeme, value);
+        Ok(())
+    }
+
     pub fn get(&self, name: &Token) -> Result<Object, LoxResult> {
         if let Some(obj) = self.values.get(&name.lexeme.to_string()) {
             Ok(obj.clone())
@@ -63,6 +94,12 @@ impl Environment {
 
     pub fn assign(&mut self, name: &Token, value: Object) -> Result<Object, LoxResult> {
         if let hash_map::Entry::Occupied(mut entry) = self.values.entry(name.lexeme.clone()) {
+            if self.kinds.get(&name.lexeme) == Some(&VarKind::Const) {
+                return Err(LoxResult::error_runtime(
+                    name,
+                    &format!("Can't assign to constant '{}'", name.lexeme),
+                ));
+            }
             entry.insert(value.clone());
             return Ok(value);
         } else if let Some(enclosing) = &self.enclosing {
@@ -0,0 +1,145 @@
+use crate::callable::*;
+use crate::error::*;
+use crate::interpreter::*;
+use crate::object::*;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::thread;
+use std::time::{Duration, Instant};
+
+struct Timer {
+    id: i64,
+    // Milliseconds on the loop's clock at which the timer fires
+    due: u64,
+    // Set for timers created by 'setInterval'
+    interval: Option<u64>,
+    callback: Object,
+}
+
+// Work scheduled by a script. The loop runs once the script has finished:
+// deferred callbacks (microtasks) run first, then timers fire in the order
+// they are due. Timers due at the same time fire in the order they were
+// created. With the virtual clock the loop never sleeps. Time jumps
+// straight to the next timer instead, which makes scheduling deterministic.
+pub struct EventLoop {
+    virtual_clock: Cell<bool>,
+    start: Instant,
+    virtual_now: Cell<u64>,
+    next_id: Cell<i64>,
+    timers: RefCell<Vec<Timer>>,
+    microtasks: RefCell<VecDeque<Object>>,
+}
+
+impl EventLoop {
+    pub fn new() -> Self {
+        Self {
+            virtual_clock: Cell::new(false),
+            start: Instant::now(),
+            virtual_now: Cell::new(0),
+            next_id: Cell::new(1),
+            timers: RefCell::new(Vec::new()),
+            microtasks: RefCell::new(VecDeque::new()),
+        }
+    }
+
+    pub fn use_virtual_clock(&self) {
+        self.virtual_clock.set(true);
+    }
+
+    pub fn is_virtual(&self) -> bool {
+        self.virtual_clock.get()
+    }
+
+    // Milliseconds since the loop was created, or virtual time
+    pub fn now(&self) -> u64 {
+        if self.is_virtual() {
+            self.virtual_now.get()
+        } else {
+            self.start.elapsed().as_millis() as u64
+        }
+    }
+
+    // Returns the id used to cancel the timer with 'clear_timer'
+    pub fn set_timer(&self, callback: Object, delay: u64, repeat: bool) -> i64 {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        self.timers.borrow_mut().push(Timer {
+            id,
+            due: self.now() + delay,
+            // An interval of zero would never let time move on
+            interval: repeat.then_some(delay.max(1)),
+            callback,
+        });
+        id
+    }
+
+    pub fn clear_timer(&self, id: i64) {
+        self.timers.borrow_mut().retain(|timer| timer.id != id);
+    }
+
+    pub fn defer(&self, callback: Object) {
+        self.microtasks.borrow_mut().push_back(callback);
+    }
+
+    // Run until there is no work left. An error in a callback stops the loop.
+    pub fn run(&self, interpreter: &Interpreter) -> Result<(), LoxResult> {
+        loop {
+            self.run_microtasks(interpreter)?;
+            let callback = match self.next_timer() {
+                Some(callback) => callback,
+                None => return Ok(()),
+            };
+            Self::call(interpreter, callback)?;
+        }
+    }
+
+    // Microtasks deferred by other microtasks run in the same pass
+    fn run_microtasks(&self, interpreter: &Interpreter) -> Result<(), LoxResult> {
+        loop {
+            let callback = self.microtasks.borrow_mut().pop_front();
+            match callback {
+                Some(callback) => Self::call(interpreter, callback)?,
+                None => return Ok(()),
+            }
+        }
+    }
+
+    // Wait for the earliest timer and return its callback. Intervals are
+    // rescheduled before their callback runs so that it can clear them.
+    fn next_timer(&self) -> Option<Object> {
+        let mut timers = self.timers.borrow_mut();
+        let (index, _) = timers
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, timer)| (timer.due, timer.id))?;
+        let due = timers[index].due;
+        if self.is_virtual() {
+            self.virtual_now.set(self.virtual_now.get().max(due));
+        } else {
+            let now = self.now();
+            if due > now {
+                thread::sleep(Duration::from_millis(due - now));
+            }
+        }
+        match timers[index].interval {
+            Some(interval) => {
+                timers[index].due = due + interval;
+                Some(timers[index].callback.clone())
+            }
+            None => Some(timers.remove(index).callback),
+        }
+    }
+
+    fn call(interpreter: &Interpreter, callback: Object) -> Result<(), LoxResult> {
+        match callback {
+            Object::Func(func) => func.call(interpreter, Vec::new(), None)?,
+            Object::Native(native) => native.func.call(interpreter, Vec::new(), None)?,
+            _ => {
+                return Err(LoxResult::system_error(
+                    "Scheduled callback is not a function",
+                ))
+            }
+        };
+        Ok(())
+    }
+}
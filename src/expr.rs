@@ -1,6 +1,7 @@
 // This is an autogenerated file. Do not edit manually. Use gen-ast package.
 // Use gen-ast package to generate this file.
 
+use crate::callable::NamedArgument;
 use crate::error::*;
 use crate::object::*;
 use crate::token::*;
@@ -14,13 +15,20 @@ pub enum Expr {
     Call(Rc<CallExpr>),
     Get(Rc<GetExpr>),
     Grouping(Rc<GroupingExpr>),
+    Index(Rc<IndexExpr>),
+    List(Rc<ListExpr>),
     Literal(Rc<LiteralExpr>),
     Logical(Rc<LogicalExpr>),
+    Range(Rc<RangeExpr>),
     Set(Rc<SetExpr>),
+    SetIndex(Rc<SetIndexExpr>),
+    Spread(Rc<SpreadExpr>),
     Super(Rc<SuperExpr>),
     This(Rc<ThisExpr>),
+    Tuple(Rc<TupleExpr>),
     Unary(Rc<UnaryExpr>),
     Variable(Rc<VariableExpr>),
+    Yield(Rc<YieldExpr>),
 }
 
 impl PartialEq for Expr {
@@ -31,13 +39,20 @@ impl PartialEq for Expr {
             (Expr::Call(a), Expr::Call(b)) => Rc::ptr_eq(a, b),
             (Expr::Get(a), Expr::Get(b)) => Rc::ptr_eq(a, b),
             (Expr::Grouping(a), Expr::Grouping(b)) => Rc::ptr_eq(a, b),
+            (Expr::Index(a), Expr::Index(b)) => Rc::ptr_eq(a, b),
+            (Expr::List(a), Expr::List(b)) => Rc::ptr_eq(a, b),
             (Expr::Literal(a), Expr::Literal(b)) => Rc::ptr_eq(a, b),
             (Expr::Logical(a), Expr::Logical(b)) => Rc::ptr_eq(a, b),
+            (Expr::Range(a), Expr::Range(b)) => Rc::ptr_eq(a, b),
             (Expr::Set(a), Expr::Set(b)) => Rc::ptr_eq(a, b),
+            (Expr::SetIndex(a), Expr::SetIndex(b)) => Rc::ptr_eq(a, b),
+            (Expr::Spread(a), Expr::Spread(b)) => Rc::ptr_eq(a, b),
             (Expr::Super(a), Expr::Super(b)) => Rc::ptr_eq(a, b),
             (Expr::This(a), Expr::This(b)) => Rc::ptr_eq(a, b),
+            (Expr::Tuple(a), Expr::Tuple(b)) => Rc::ptr_eq(a, b),
             (Expr::Unary(a), Expr::Unary(b)) => Rc::ptr_eq(a, b),
             (Expr::Variable(a), Expr::Variable(b)) => Rc::ptr_eq(a, b),
+            (Expr::Yield(a), Expr::Yield(b)) => Rc::ptr_eq(a, b),
             _ => false,
         }
     }
@@ -63,27 +78,48 @@ impl Hash for Expr {
             Expr::Grouping(a) => {
                 hasher.write_usize(Rc::as_ptr(a) as usize);
             }
+            Expr::Index(a) => {
+                hasher.write_usize(Rc::as_ptr(a) as usize);
+            }
+            Expr::List(a) => {
+                hasher.write_usize(Rc::as_ptr(a) as usize);
+            }
             Expr::Literal(a) => {
                 hasher.write_usize(Rc::as_ptr(a) as usize);
             }
             Expr::Logical(a) => {
                 hasher.write_usize(Rc::as_ptr(a) as usize);
             }
+            Expr::Range(a) => {
+                hasher.write_usize(Rc::as_ptr(a) as usize);
+            }
             Expr::Set(a) => {
                 hasher.write_usize(Rc::as_ptr(a) as usize);
             }
+            Expr::SetIndex(a) => {
+                hasher.write_usize(Rc::as_ptr(a) as usize);
+            }
+            Expr::Spread(a) => {
+                hasher.write_usize(Rc::as_ptr(a) as usize);
+            }
             Expr::Super(a) => {
                 hasher.write_usize(Rc::as_ptr(a) as usize);
             }
             Expr::This(a) => {
                 hasher.write_usize(Rc::as_ptr(a) as usize);
             }
+            Expr::Tuple(a) => {
+                hasher.write_usize(Rc::as_ptr(a) as usize);
+            }
             Expr::Unary(a) => {
                 hasher.write_usize(Rc::as_ptr(a) as usize);
             }
             Expr::Variable(a) => {
                 hasher.write_usize(Rc::as_ptr(a) as usize);
             }
+            Expr::Yield(a) => {
+                hasher.write_usize(Rc::as_ptr(a) as usize);
+            }
         }
     }
 }
@@ -96,13 +132,20 @@ impl Expr {
             Expr::Call(v) => visitor.visit_call_expr(base, v),
             Expr::Get(v) => visitor.visit_get_expr(base, v),
             Expr::Grouping(v) => visitor.visit_grouping_expr(base, v),
+            Expr::Index(v) => visitor.visit_index_expr(base, v),
+            Expr::List(v) => visitor.visit_list_expr(base, v),
             Expr::Literal(v) => visitor.visit_literal_expr(base, v),
             Expr::Logical(v) => visitor.visit_logical_expr(base, v),
+            Expr::Range(v) => visitor.visit_range_expr(base, v),
             Expr::Set(v) => visitor.visit_set_expr(base, v),
+            Expr::SetIndex(v) => visitor.visit_setindex_expr(base, v),
+            Expr::Spread(v) => visitor.visit_spread_expr(base, v),
             Expr::Super(v) => visitor.visit_super_expr(base, v),
             Expr::This(v) => visitor.visit_this_expr(base, v),
+            Expr::Tuple(v) => visitor.visit_tuple_expr(base, v),
             Expr::Unary(v) => visitor.visit_unary_expr(base, v),
             Expr::Variable(v) => visitor.visit_variable_expr(base, v),
+            Expr::Yield(v) => visitor.visit_yield_expr(base, v),
         }
     }
 }
@@ -125,6 +168,7 @@ pub struct CallExpr {
     pub callee: Rc<Expr>,
     pub paren: Token,
     pub arguments: Vec<Rc<Expr>>,
+    pub named: Vec<NamedArgument>,
 }
 
 #[derive(Clone)]
@@ -138,6 +182,18 @@ pub struct GroupingExpr {
     pub expression: Rc<Expr>,
 }
 
+#[derive(Clone)]
+pub struct IndexExpr {
+    pub object: Rc<Expr>,
+    pub bracket: Token,
+    pub index: Rc<Expr>,
+}
+
+#[derive(Clone)]
+pub struct ListExpr {
+    pub elements: Vec<Rc<Expr>>,
+}
+
 #[derive(Clone)]
 pub struct LiteralExpr {
     pub value: Option<Object>,
@@ -150,6 +206,14 @@ pub struct LogicalExpr {
     pub right: Rc<Expr>,
 }
 
+#[derive(Clone)]
+pub struct RangeExpr {
+    pub start: Rc<Expr>,
+    pub operator: Token,
+    pub end: Rc<Expr>,
+    pub step: Option<Rc<Expr>>,
+}
+
 #[derive(Clone)]
 pub struct SetExpr {
     pub object: Rc<Expr>,
@@ -157,6 +221,20 @@ pub struct SetExpr {
     pub value: Rc<Expr>,
 }
 
+#[derive(Clone)]
+pub struct SetIndexExpr {
+    pub object: Rc<Expr>,
+    pub bracket: Token,
+    pub index: Rc<Expr>,
+    pub value: Rc<Expr>,
+}
+
+#[derive(Clone)]
+pub struct SpreadExpr {
+    pub ellipsis: Token,
+    pub value: Rc<Expr>,
+}
+
 #[derive(Clone)]
 pub struct SuperExpr {
     pub keyword: Token,
@@ -168,6 +246,11 @@ pub struct ThisExpr {
     pub keyword: Token,
 }
 
+#[derive(Clone)]
+pub struct TupleExpr {
+    pub elements: Vec<Rc<Expr>>,
+}
+
 #[derive(Clone)]
 pub struct UnaryExpr {
     pub operator: Token,
@@ -179,17 +262,30 @@ pub struct VariableExpr {
     pub name: Token,
 }
 
+#[derive(Clone)]
+pub struct YieldExpr {
+    pub keyword: Token,
+    pub value: Option<Rc<Expr>>,
+}
+
 pub trait ExprVisitor<T> {
     fn visit_assign_expr(&self, base: Rc<Expr>, expr: &AssignExpr) -> Result<T, LoxResult>;
     fn visit_binary_expr(&self, base: Rc<Expr>, expr: &BinaryExpr) -> Result<T, LoxResult>;
     fn visit_call_expr(&self, base: Rc<Expr>, expr: &CallExpr) -> Result<T, LoxResult>;
     fn visit_get_expr(&self, base: Rc<Expr>, expr: &GetExpr) -> Result<T, LoxResult>;
     fn visit_grouping_expr(&self, base: Rc<Expr>, expr: &GroupingExpr) -> Result<T, LoxResult>;
+    fn visit_index_expr(&self, base: Rc<Expr>, expr: &IndexExpr) -> Result<T, LoxResult>;
+    fn visit_list_expr(&self, base: Rc<Expr>, expr: &ListExpr) -> Result<T, LoxResult>;
     fn visit_literal_expr(&self, base: Rc<Expr>, expr: &LiteralExpr) -> Result<T, LoxResult>;
     fn visit_logical_expr(&self, base: Rc<Expr>, expr: &LogicalExpr) -> Result<T, LoxResult>;
+    fn visit_range_expr(&self, base: Rc<Expr>, expr: &RangeExpr) -> Result<T, LoxResult>;
     fn visit_set_expr(&self, base: Rc<Expr>, expr: &SetExpr) -> Result<T, LoxResult>;
+    fn visit_setindex_expr(&self, base: Rc<Expr>, expr: &SetIndexExpr) -> Result<T, LoxResult>;
+    fn visit_spread_expr(&self, base: Rc<Expr>, expr: &SpreadExpr) -> Result<T, LoxResult>;
     fn visit_super_expr(&self, base: Rc<Expr>, expr: &SuperExpr) -> Result<T, LoxResult>;
     fn visit_this_expr(&self, base: Rc<Expr>, expr: &ThisExpr) -> Result<T, LoxResult>;
+    fn visit_tuple_expr(&self, base: Rc<Expr>, expr: &TupleExpr) -> Result<T, LoxResult>;
     fn visit_unary_expr(&self, base: Rc<Expr>, expr: &UnaryExpr) -> Result<T, LoxResult>;
     fn visit_variable_expr(&self, base: Rc<Expr>, expr: &VariableExpr) -> Result<T, LoxResult>;
+    fn visit_yield_expr(&self, base: Rc<Expr>, expr: &YieldExpr) -> Result<T, LoxResult>;
 }
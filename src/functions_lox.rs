@@ -5,18 +5,41 @@ use std::rc::Rc;
 use crate::callable::*;
 use crate::environment::*;
 use crate::error::*;
+use crate::expr::Expr;
 use crate::interpreter::*;
 use crate::lox_class::*;
+use crate::lox_generator::{contains_yield, LoxGenerator};
+use crate::lox_list::LoxList;
 use crate::object::*;
 use crate::stmt::*;
 use crate::token::*;
 
+// Methods declared without a parameter list are getters that run when the
+// property is read. Setters ('name=(value)') run when the property is assigned.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FunctionKind {
+    Function,
+    Getter,
+    Setter,
+}
+
 pub struct LoxFunction {
     name: Token,
+    kind: FunctionKind,
     is_initializer: bool,
+    // Functions containing 'yield' return a generator when called
+    is_generator: bool,
     params: Rc<Vec<Token>>,
+    // Default values of the trailing parameters
+    defaults: Rc<Vec<Rc<Expr>>>,
+    // Receives a list of the arguments left over
+    rest: Option<Token>,
     body: Rc<Vec<Rc<Stmt>>>,
     closure: Rc<RefCell<Environment>>,
+    // Methods added with 'define' take the instance as their first parameter
+    // instead of 'this'. Binding them fills it in as 'receiver'.
+    takes_receiver: bool,
+    receiver: Option<Object>,
 }
 
 impl LoxFunction {
@@ -27,40 +50,99 @@ impl LoxFunction {
     ) -> Self {
         Self {
             name: declaration.name.clone(),
+            kind: declaration.kind,
             is_initializer,
+            is_generator: declaration.body.iter().any(|stmt| contains_yield(stmt)),
             params: Rc::clone(&declaration.params),
+            defaults: Rc::clone(&declaration.defaults),
+            rest: declaration.rest.clone(),
             body: Rc::clone(&declaration.body),
             closure: Rc::clone(closure),
+            takes_receiver: false,
+            receiver: None,
         }
     }
+
+    // This function as a method taking the instance as its first parameter.
+    // None if it has no parameter to take it.
+    pub fn with_receiver_param(&self) -> Option<Self> {
+        (!self.params.is_empty()).then(|| Self {
+            takes_receiver: true,
+            ..self.clone()
+        })
+    }
     // Create a new environment nestled inside the method's original closure
     // Like a closure within a closure. When the method is called, that will
     // become the parent of the methods body's environment
     pub fn bind(&self, instance: &Object) -> Object {
+        if self.takes_receiver {
+            return Object::Func(Rc::new(Self {
+                takes_receiver: false,
+                receiver: Some(instance.clone()),
+                ..self.clone()
+            }));
+        }
         let env = RefCell::new(Environment::new_enclosing(Rc::clone(&self.closure)));
         env.borrow_mut().define("this", instance.clone());
         Object::Func(Rc::new(Self {
             name: self.name.clone(),
+            kind: self.kind,
             is_initializer: self.is_initializer,
+            is_generator: self.is_generator,
             params: Rc::clone(&self.params),
+            defaults: Rc::clone(&self.defaults),
+            rest: self.rest.clone(),
             body: Rc::clone(&self.body),
             closure: Rc::new(env),
+            takes_receiver: false,
+            receiver: None,
         }))
     }
-}
 
-impl LoxCallable for LoxFunction {
-    fn call(
+    pub fn is_getter(&self) -> bool {
+        self.kind == FunctionKind::Getter
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name.lexeme
+    }
+
+    // The parameters the receiver, if bound, leaves to the arguments
+    fn skipped_params(&self) -> usize {
+        usize::from(self.receiver.is_some())
+    }
+
+    // Run the body as a coroutine that suspends at each 'yield'
+    pub fn coroutine(
+        &self,
+        interpreter: &Interpreter,
+        arguments: Vec<Object>,
+    ) -> Result<LoxGenerator, LoxResult> {
+        let environment = self.environment(interpreter, arguments)?;
+        Ok(LoxGenerator::new(
+            &self.name.lexeme,
+            &self.body,
+            environment,
+        ))
+    }
+
+    // Run the body to its end and return the result. Unlike 'call', this runs
+    // the body of a generator function too, as fibers do.
+    pub fn run(
         &self,
         interpreter: &Interpreter,
         arguments: Vec<Object>,
-        _klass: Option<Rc<LoxClass>>,
     ) -> Result<Object, LoxResult> {
-        let mut e = Environment::new_enclosing(Rc::clone(&self.closure));
-        for (param, arg) in self.params.iter().zip(arguments.iter()) {
-            e.define(&param.lexeme, arg.clone());
+        if let Some(fiber) = interpreter.fibers.borrow().last() {
+            fiber.check_stack(&self.name)?;
         }
-        match interpreter.execute_block(&self.body, e) {
+        let environment = self.environment(interpreter, arguments)?;
+        let result = interpreter.with_environment(&environment, || {
+            self.body
+                .iter()
+                .try_for_each(|stmt| interpreter.execute(Rc::clone(stmt)))
+        });
+        match result {
             Err(LoxResult::ReturnValue { value: v }) => Ok(v),
             Err(e) => Err(e),
             Ok(_) => {
@@ -74,8 +156,89 @@ impl LoxCallable for LoxFunction {
         }
     }
 
-    fn arity(&self) -> usize {
-        self.params.len()
+    // The environment of a call, holding the arguments. Defaults of the
+    // parameters left out are evaluated in it so that they can refer to the
+    // parameters before them.
+    fn environment(
+        &self,
+        interpreter: &Interpreter,
+        arguments: Vec<Object>,
+    ) -> Result<Rc<RefCell<Environment>>, LoxResult> {
+        let e = Rc::new(RefCell::new(Environment::new_enclosing(Rc::clone(
+            &self.closure,
+        ))));
+        let first_default = self.params.len() - self.defaults.len();
+        let mut arguments = self.receiver.iter().cloned().chain(arguments);
+        for (i, param) in self.params.iter().enumerate() {
+            let value = match arguments.next() {
+                Some(value) => value,
+                None if i >= first_default => {
+                    let default = Rc::clone(&self.defaults[i - first_default]);
+                    interpreter.with_environment(&e, || interpreter.evaluate(default))?
+                }
+                None => Object::Nil,
+            };
+            e.borrow_mut().define(&param.lexeme, value);
+        }
+        if let Some(rest) = &self.rest {
+            let rest_list = LoxList::new(arguments.collect());
+            e.borrow_mut()
+                .define(&rest.lexeme, Object::List(Rc::new(rest_list)));
+        }
+        Ok(e)
+    }
+}
+
+impl LoxCallable for LoxFunction {
+    fn call(
+        &self,
+        interpreter: &Interpreter,
+        arguments: Vec<Object>,
+        _klass: Option<Rc<LoxClass>>,
+    ) -> Result<Object, LoxResult> {
+        if self.is_generator {
+            let generator = self.coroutine(interpreter, arguments)?;
+            return Ok(Object::Generator(Rc::new(generator)));
+        }
+        self.run(interpreter, arguments)
+    }
+
+    fn param_names(&self) -> Vec<String> {
+        self.params
+            .iter()
+            .skip(self.skipped_params())
+            .map(|param| param.lexeme.clone())
+            .collect()
+    }
+
+    fn default_argument(
+        &self,
+        interpreter: &Interpreter,
+        index: usize,
+        arguments: &[Object],
+    ) -> Result<Option<Object>, LoxResult> {
+        let index = index + self.skipped_params();
+        let first_default = self.params.len() - self.defaults.len();
+        if index < first_default {
+            return Ok(None);
+        }
+        let mut e = Environment::new_enclosing(Rc::clone(&self.closure));
+        let arguments = self.receiver.iter().chain(arguments);
+        for (param, value) in self.params.iter().zip(arguments) {
+            e.define(&param.lexeme, value.clone());
+        }
+        let default = Rc::clone(&self.defaults[index - first_default]);
+        let e = Rc::new(RefCell::new(e));
+        let value = interpreter.with_environment(&e, || interpreter.evaluate(default))?;
+        Ok(Some(value))
+    }
+
+    fn arity(&self) -> Arity {
+        let skipped = self.skipped_params();
+        Arity {
+            min: (self.params.len() - self.defaults.len()).saturating_sub(skipped),
+            max: self.rest.is_none().then_some(self.params.len() - skipped),
+        }
     }
 }
 
@@ -85,11 +248,12 @@ impl fmt::Display for LoxFunction {
             .params
             .iter()
             .map(|p| p.lexeme.clone())
+            .chain(self.rest.iter().map(|rest| format!("...{}", rest.lexeme)))
             .collect::<Vec<String>>()
             .join(", ");
 
         // <fun foo(a, b, c)>
-        write!(f, "<fun {}({param_list})>", self.name)
+        write!(f, "<fun {}({param_list})>", self.name.lexeme)
     }
 }
 
@@ -103,10 +267,16 @@ impl Clone for LoxFunction {
     fn clone(&self) -> Self {
         Self {
             name: self.name.clone(),
+            kind: self.kind,
             is_initializer: self.is_initializer,
+            is_generator: self.is_generator,
             params: Rc::clone(&self.params),
+            defaults: Rc::clone(&self.defaults),
+            rest: self.rest.clone(),
             body: Rc::clone(&self.body),
             closure: Rc::clone(&self.closure),
+            takes_receiver: self.takes_receiver,
+            receiver: self.receiver.clone(),
         }
     }
 }
@@ -117,5 +287,6 @@ impl PartialEq for LoxFunction {
             && Rc::ptr_eq(&self.params, &other.params)
             && Rc::ptr_eq(&self.body, &other.body)
             && Rc::ptr_eq(&self.closure, &other.closure)
+            && self.receiver == other.receiver
     }
 }
@@ -6,6 +6,11 @@ use crate::callable::*;
 use crate::error::*;
 use crate::interpreter::*;
 use crate::lox_class::*;
+use crate::lox_fiber::LoxFiber;
+use crate::lox_instance::LoxInstance;
+use crate::lox_list::LoxList;
+use crate::lox_range::LoxRange;
+use crate::numeric;
 use crate::object::*;
 
 pub struct LoxNative {
@@ -17,10 +22,14 @@ pub struct NativeClock {}
 impl LoxCallable for NativeClock {
     fn call(
         &self,
-        _: &Interpreter,
+        interpreter: &Interpreter,
         _: Vec<Object>,
         _klass: Option<Rc<LoxClass>>,
     ) -> Result<Object, LoxResult> {
+        // Scheduled behavior is tested against the event loop's virtual time
+        if interpreter.event_loop.is_virtual() {
+            return Ok(Object::Number(interpreter.event_loop.now() as f64));
+        }
         match SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
             Ok(n) => Ok(Object::Number(n.as_millis() as f64)),
             Err(e) => Err(LoxResult::system_error(&format!(
@@ -30,8 +39,645 @@ impl LoxCallable for NativeClock {
         }
     }
 
-    fn arity(&self) -> usize {
-        0
+    fn arity(&self) -> Arity {
+        Arity::exact(0)
+    }
+}
+
+// Convert any value to a string the same way 'print' does
+pub struct NativeStr {}
+
+impl LoxCallable for NativeStr {
+    fn call(
+        &self,
+        interpreter: &Interpreter,
+        arguments: Vec<Object>,
+        _klass: Option<Rc<LoxClass>>,
+    ) -> Result<Object, LoxResult> {
+        Ok(Object::Str(interpreter.stringify(&arguments[0])?))
+    }
+
+    fn arity(&self) -> Arity {
+        Arity::exact(1)
+    }
+}
+
+// Argument checks shared by the reflection natives. Natives have no token
+// to point at, so a bad argument is reported as a system error.
+fn expect_instance(native: &str, value: &Object) -> Result<Rc<LoxInstance>, LoxResult> {
+    match value {
+        Object::Instance(inst) => Ok(Rc::clone(inst)),
+        _ => Err(LoxResult::system_error(&format!(
+            "'{native}' expects an instance but got a {}",
+            value.type_name()
+        ))),
+    }
+}
+
+fn expect_class(native: &str, value: &Object) -> Result<Rc<LoxClass>, LoxResult> {
+    match value {
+        Object::Class(klass) => Ok(Rc::clone(klass)),
+        _ => Err(LoxResult::system_error(&format!(
+            "'{native}' expects a class but got a {}",
+            value.type_name()
+        ))),
+    }
+}
+
+fn expect_string<'a>(native: &str, value: &'a Object) -> Result<&'a str, LoxResult> {
+    match value {
+        Object::Str(s) => Ok(s),
+        _ => Err(LoxResult::system_error(&format!(
+            "'{native}' expects a string but got a {}",
+            value.type_name()
+        ))),
+    }
+}
+
+// Reflection never reaches private fields
+fn expect_field_name<'a>(native: &str, value: &'a Object) -> Result<&'a str, LoxResult> {
+    let name = expect_string(native, value)?;
+    if is_private(name) {
+        return Err(LoxResult::system_error(&format!(
+            "'{native}' can't access private field '{name}'"
+        )));
+    }
+    Ok(name)
+}
+
+pub fn string_list(names: Vec<String>) -> Object {
+    Object::List(Rc::new(LoxList::new(
+        names.into_iter().map(Object::Str).collect(),
+    )))
+}
+
+// Number of characters in a string or items in a list
+pub struct NativeLen {}
+
+impl LoxCallable for NativeLen {
+    fn call(
+        &self,
+        _: &Interpreter,
+        arguments: Vec<Object>,
+        _klass: Option<Rc<LoxClass>>,
+    ) -> Result<Object, LoxResult> {
+        match &arguments[0] {
+            Object::Str(s) => Ok(Object::Int(s.chars().count() as i64)),
+            Object::List(list) => Ok(Object::Int(list.len() as i64)),
+            Object::Tuple(tuple) => Ok(Object::Int(tuple.len() as i64)),
+            Object::Range(range) => match range.len() {
+                Some(len) => Ok(Object::Int(len)),
+                None => Err(LoxResult::system_error(&format!(
+                    "The length of {} is too large",
+                    range
+                ))),
+            },
+            value => Err(LoxResult::system_error(&format!(
+                "'len' expects a string, a list, a tuple or a range but got a {}",
+                value.type_name()
+            ))),
+        }
+    }
+
+    fn arity(&self) -> Arity {
+        Arity::exact(1)
+    }
+}
+
+// The integers from 'start' up to but not including 'end', counting in
+// steps of 'step' which defaults to one
+pub struct NativeRange {}
+
+impl LoxCallable for NativeRange {
+    fn call(
+        &self,
+        _: &Interpreter,
+        arguments: Vec<Object>,
+        _klass: Option<Rc<LoxClass>>,
+    ) -> Result<Object, LoxResult> {
+        let step = arguments.get(2).unwrap_or(&Object::Int(1));
+        let range = match (&arguments[0], &arguments[1], step) {
+            (Object::Int(start), Object::Int(end), Object::Int(step)) => {
+                LoxRange::new(*start, *end, *step)
+            }
+            _ => {
+                return Err(LoxResult::system_error(
+                    "'range' expects integers as its bounds and step",
+                ))
+            }
+        };
+        match range {
+            Some(range) => Ok(Object::Range(Rc::new(range))),
+            None => Err(LoxResult::system_error("The step of a range can't be zero")),
+        }
+    }
+
+    fn param_names(&self) -> Vec<String> {
+        param_names(&["start", "end", "step"])
+    }
+
+    fn default_argument(
+        &self,
+        _: &Interpreter,
+        index: usize,
+        _arguments: &[Object],
+    ) -> Result<Option<Object>, LoxResult> {
+        Ok((index == 2).then_some(Object::Int(1)))
+    }
+
+    fn arity(&self) -> Arity {
+        Arity {
+            min: 2,
+            max: Some(3),
+        }
+    }
+}
+
+// Create a fiber running a function of at most one parameter
+pub struct NativeFiber {}
+
+impl LoxCallable for NativeFiber {
+    fn call(
+        &self,
+        _: &Interpreter,
+        arguments: Vec<Object>,
+        _klass: Option<Rc<LoxClass>>,
+    ) -> Result<Object, LoxResult> {
+        match &arguments[0] {
+            Object::Func(function) if function.arity().min <= 1 => {
+                Ok(Object::Fiber(Rc::new(LoxFiber::new(Rc::clone(function)))))
+            }
+            _ => Err(LoxResult::system_error(
+                "'Fiber' expects a function taking at most one argument",
+            )),
+        }
+    }
+
+    fn arity(&self) -> Arity {
+        Arity::exact(1)
+    }
+}
+
+// Suspend the running fiber from any function it calls. See 'LoxFiber'.
+pub struct NativeSuspend {}
+
+impl LoxCallable for NativeSuspend {
+    fn call(
+        &self,
+        interpreter: &Interpreter,
+        arguments: Vec<Object>,
+        _klass: Option<Rc<LoxClass>>,
+    ) -> Result<Object, LoxResult> {
+        let value = arguments.into_iter().next().unwrap_or(Object::Nil);
+        interpreter
+            .suspend_fiber(value)
+            .ok_or_else(|| LoxResult::system_error("Can't suspend outside of a fiber"))
+    }
+
+    fn arity(&self) -> Arity {
+        Arity::exact(1)
+    }
+}
+
+fn param_names(names: &[&str]) -> Vec<String> {
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+// Callbacks given to the event loop are called without arguments
+fn expect_callback(native: &str, value: &Object) -> Result<Object, LoxResult> {
+    let arity = match value {
+        Object::Func(func) => func.arity(),
+        Object::Native(native) => native.func.arity(),
+        _ => Arity::exact(1),
+    };
+    if arity.accepts(0) {
+        Ok(value.clone())
+    } else {
+        Err(LoxResult::system_error(&format!(
+            "'{native}' expects a function that takes no arguments"
+        )))
+    }
+}
+
+fn expect_delay(native: &str, value: &Object) -> Result<u64, LoxResult> {
+    match numeric::to_f64(value) {
+        Some(ms) => Ok(ms.max(0.) as u64),
+        None => Err(LoxResult::system_error(&format!(
+            "'{native}' expects the delay in milliseconds as a number"
+        ))),
+    }
+}
+
+// Call a function once after a delay in milliseconds. Returns the id of
+// the timer for 'clearTimeout'.
+pub struct NativeSetTimeout {}
+
+impl LoxCallable for NativeSetTimeout {
+    fn call(
+        &self,
+        interpreter: &Interpreter,
+        arguments: Vec<Object>,
+        _klass: Option<Rc<LoxClass>>,
+    ) -> Result<Object, LoxResult> {
+        let callback = expect_callback("setTimeout", &arguments[0])?;
+        let delay = expect_delay("setTimeout", &arguments[1])?;
+        let id = interpreter.event_loop.set_timer(callback, delay, false);
+        Ok(Object::Int(id))
+    }
+
+    fn param_names(&self) -> Vec<String> {
+        param_names(&["callback", "delay"])
+    }
+
+    fn arity(&self) -> Arity {
+        Arity::exact(2)
+    }
+}
+
+// Call a function repeatedly, every given number of milliseconds
+pub struct NativeSetInterval {}
+
+impl LoxCallable for NativeSetInterval {
+    fn call(
+        &self,
+        interpreter: &Interpreter,
+        arguments: Vec<Object>,
+        _klass: Option<Rc<LoxClass>>,
+    ) -> Result<Object, LoxResult> {
+        let callback = expect_callback("setInterval", &arguments[0])?;
+        let delay = expect_delay("setInterval", &arguments[1])?;
+        let id = interpreter.event_loop.set_timer(callback, delay, true);
+        Ok(Object::Int(id))
+    }
+
+    fn param_names(&self) -> Vec<String> {
+        param_names(&["callback", "delay"])
+    }
+
+    fn arity(&self) -> Arity {
+        Arity::exact(2)
+    }
+}
+
+// Cancel a timer created by 'setTimeout' or 'setInterval'. Unknown ids are
+// ignored.
+pub struct NativeClearTimeout {}
+
+impl LoxCallable for NativeClearTimeout {
+    fn call(
+        &self,
+        interpreter: &Interpreter,
+        arguments: Vec<Object>,
+        _klass: Option<Rc<LoxClass>>,
+    ) -> Result<Object, LoxResult> {
+        if let Object::Int(id) = arguments[0] {
+            interpreter.event_loop.clear_timer(id);
+        }
+        Ok(Object::Nil)
+    }
+
+    fn arity(&self) -> Arity {
+        Arity::exact(1)
+    }
+}
+
+// Call a function as soon as the current script or callback finishes,
+// before any timer fires
+pub struct NativeDefer {}
+
+impl LoxCallable for NativeDefer {
+    fn call(
+        &self,
+        interpreter: &Interpreter,
+        arguments: Vec<Object>,
+        _klass: Option<Rc<LoxClass>>,
+    ) -> Result<Object, LoxResult> {
+        let callback = expect_callback("defer", &arguments[0])?;
+        interpreter.event_loop.defer(callback);
+        Ok(Object::Nil)
+    }
+
+    fn arity(&self) -> Arity {
+        Arity::exact(1)
+    }
+}
+
+// Name of the type of a value: "number", "string", "instance", ...
+pub struct NativeType {}
+
+impl LoxCallable for NativeType {
+    fn call(
+        &self,
+        _: &Interpreter,
+        arguments: Vec<Object>,
+        _klass: Option<Rc<LoxClass>>,
+    ) -> Result<Object, LoxResult> {
+        Ok(Object::Str(arguments[0].type_name().to_string()))
+    }
+
+    fn arity(&self) -> Arity {
+        Arity::exact(1)
+    }
+}
+
+// The class of an instance, the metaclass of a class, or nil for any other
+// value (metaclasses included)
+pub struct NativeClassOf {}
+
+impl LoxCallable for NativeClassOf {
+    fn call(
+        &self,
+        _: &Interpreter,
+        arguments: Vec<Object>,
+        _klass: Option<Rc<LoxClass>>,
+    ) -> Result<Object, LoxResult> {
+        match &arguments[0] {
+            Object::Instance(inst) => Ok(Object::Class(Rc::clone(&inst.klass))),
+            Object::Class(klass) => Ok(klass.metaclass().map_or(Object::Nil, Object::Class)),
+            _ => Ok(Object::Nil),
+        }
+    }
+
+    fn arity(&self) -> Arity {
+        Arity::exact(1)
+    }
+}
+
+// Whether a value is an instance of a class or of one of its subclasses.
+// A class is an instance of its metaclass.
+pub struct NativeIsInstance {}
+
+impl LoxCallable for NativeIsInstance {
+    fn call(
+        &self,
+        _: &Interpreter,
+        arguments: Vec<Object>,
+        _klass: Option<Rc<LoxClass>>,
+    ) -> Result<Object, LoxResult> {
+        let klass = expect_class("isInstance", &arguments[1])?;
+        match &arguments[0] {
+            Object::Instance(inst) => Ok(Object::Bool(inst.klass.is_subclass_of(&klass))),
+            Object::Class(c) => Ok(Object::Bool(
+                c.metaclass()
+                    .is_some_and(|metaclass| metaclass.is_subclass_of(&klass)),
+            )),
+            _ => Ok(Object::Bool(false)),
+        }
+    }
+
+    fn param_names(&self) -> Vec<String> {
+        param_names(&["value", "klass"])
+    }
+
+    fn arity(&self) -> Arity {
+        Arity::exact(2)
+    }
+}
+
+// Whether a class, or the class of an instance, includes a trait
+pub struct NativeIncludes {}
+
+impl LoxCallable for NativeIncludes {
+    fn call(
+        &self,
+        _: &Interpreter,
+        arguments: Vec<Object>,
+        _klass: Option<Rc<LoxClass>>,
+    ) -> Result<Object, LoxResult> {
+        let Object::Trait(tr) = &arguments[1] else {
+            return Err(LoxResult::system_error(&format!(
+                "'includes' expects a trait but got a {}",
+                arguments[1].type_name()
+            )));
+        };
+        match &arguments[0] {
+            Object::Class(klass) => Ok(Object::Bool(klass.includes(tr))),
+            Object::Instance(inst) => Ok(Object::Bool(inst.klass.includes(tr))),
+            _ => Ok(Object::Bool(false)),
+        }
+    }
+
+    fn param_names(&self) -> Vec<String> {
+        param_names(&["value", "mixin"])
+    }
+
+    fn arity(&self) -> Arity {
+        Arity::exact(2)
+    }
+}
+
+// Names of the fields of an instance in order
+pub struct NativeFields {}
+
+impl LoxCallable for NativeFields {
+    fn call(
+        &self,
+        _: &Interpreter,
+        arguments: Vec<Object>,
+        _klass: Option<Rc<LoxClass>>,
+    ) -> Result<Object, LoxResult> {
+        let inst = expect_instance("fields", &arguments[0])?;
+        let names = inst.sorted_fields().into_iter().map(|(name, _)| name);
+        Ok(string_list(names.collect()))
+    }
+
+    fn arity(&self) -> Arity {
+        Arity::exact(1)
+    }
+}
+
+pub struct NativeHasField {}
+
+impl LoxCallable for NativeHasField {
+    fn call(
+        &self,
+        _: &Interpreter,
+        arguments: Vec<Object>,
+        _klass: Option<Rc<LoxClass>>,
+    ) -> Result<Object, LoxResult> {
+        let name = expect_string("hasField", &arguments[1])?;
+        match &arguments[0] {
+            Object::Instance(inst) if !is_private(name) => {
+                Ok(Object::Bool(inst.get_field(name).is_some()))
+            }
+            _ => Ok(Object::Bool(false)),
+        }
+    }
+
+    fn param_names(&self) -> Vec<String> {
+        param_names(&["instance", "name"])
+    }
+
+    fn arity(&self) -> Arity {
+        Arity::exact(2)
+    }
+}
+
+// Read a field by name. Unlike property access this never finds methods.
+pub struct NativeGetField {}
+
+impl LoxCallable for NativeGetField {
+    fn call(
+        &self,
+        _: &Interpreter,
+        arguments: Vec<Object>,
+        _klass: Option<Rc<LoxClass>>,
+    ) -> Result<Object, LoxResult> {
+        let inst = expect_instance("getField", &arguments[0])?;
+        let name = expect_field_name("getField", &arguments[1])?;
+        inst.get_field(name)
+            .ok_or_else(|| LoxResult::system_error(&format!("Undefined field '{name}' on {inst}")))
+    }
+
+    fn param_names(&self) -> Vec<String> {
+        param_names(&["instance", "name"])
+    }
+
+    fn arity(&self) -> Arity {
+        Arity::exact(2)
+    }
+}
+
+// Write a field by name and return the value written
+pub struct NativeSetField {}
+
+impl LoxCallable for NativeSetField {
+    fn call(
+        &self,
+        _: &Interpreter,
+        arguments: Vec<Object>,
+        _klass: Option<Rc<LoxClass>>,
+    ) -> Result<Object, LoxResult> {
+        let inst = expect_instance("setField", &arguments[0])?;
+        let name = expect_field_name("setField", &arguments[1])?;
+        if inst.klass.is_strict() && !inst.klass.declares_field(name) {
+            return Err(LoxResult::system_error(&format!(
+                "'setField' can't add field '{name}' to strict class {}",
+                inst.klass.name
+            )));
+        }
+        inst.set_field(name, arguments[2].clone());
+        Ok(arguments[2].clone())
+    }
+
+    fn param_names(&self) -> Vec<String> {
+        param_names(&["instance", "name", "value"])
+    }
+
+    fn arity(&self) -> Arity {
+        Arity::exact(3)
+    }
+}
+
+// Names of the methods of a class (or of an instance's class) in order,
+// including inherited methods
+pub struct NativeMethods {}
+
+impl LoxCallable for NativeMethods {
+    fn call(
+        &self,
+        _: &Interpreter,
+        arguments: Vec<Object>,
+        _klass: Option<Rc<LoxClass>>,
+    ) -> Result<Object, LoxResult> {
+        let klass = match &arguments[0] {
+            Object::Instance(inst) => Rc::clone(&inst.klass),
+            value => expect_class("methods", value)?,
+        };
+        Ok(string_list(klass.method_names()))
+    }
+
+    fn arity(&self) -> Arity {
+        Arity::exact(1)
+    }
+}
+
+pub struct NativeSuperclass {}
+
+impl LoxCallable for NativeSuperclass {
+    fn call(
+        &self,
+        _: &Interpreter,
+        arguments: Vec<Object>,
+        _klass: Option<Rc<LoxClass>>,
+    ) -> Result<Object, LoxResult> {
+        match &expect_class("superclass", &arguments[0])?.superclass {
+            Some(superclass) => Ok(Object::Class(Rc::clone(superclass))),
+            None => Ok(Object::Nil),
+        }
+    }
+
+    fn arity(&self) -> Arity {
+        Arity::exact(1)
+    }
+}
+
+// Number of arguments a function, native or class requires
+pub struct NativeArity {}
+
+impl LoxCallable for NativeArity {
+    fn call(
+        &self,
+        _: &Interpreter,
+        arguments: Vec<Object>,
+        _klass: Option<Rc<LoxClass>>,
+    ) -> Result<Object, LoxResult> {
+        let arity = match &arguments[0] {
+            Object::Func(func) => func.arity(),
+            Object::Native(native) => native.func.arity(),
+            Object::Variant(variant) => variant.arity(),
+            Object::Class(klass) => klass.arity(),
+            value => {
+                return Err(LoxResult::system_error(&format!(
+                    "'arity' expects a function or a class but got a {}",
+                    value.type_name()
+                )))
+            }
+        };
+        Ok(Object::Int(arity.min as i64))
+    }
+
+    fn arity(&self) -> Arity {
+        Arity::exact(1)
+    }
+}
+
+// A method of a built-in object such as a generator, bound to the object
+// it was read from
+pub struct NativeMethod<T> {
+    receiver: Rc<T>,
+    arity: usize,
+    method: fn(&T, &Interpreter, Vec<Object>) -> Result<Object, LoxResult>,
+}
+
+impl<T: 'static> NativeMethod<T> {
+    pub fn bind(
+        receiver: Rc<T>,
+        arity: usize,
+        method: fn(&T, &Interpreter, Vec<Object>) -> Result<Object, LoxResult>,
+    ) -> Object {
+        Object::Native(Rc::new(LoxNative {
+            func: Rc::new(NativeMethod {
+                receiver,
+                arity,
+                method,
+            }),
+        }))
+    }
+}
+
+impl<T> LoxCallable for NativeMethod<T> {
+    fn call(
+        &self,
+        interpreter: &Interpreter,
+        arguments: Vec<Object>,
+        _klass: Option<Rc<LoxClass>>,
+    ) -> Result<Object, LoxResult> {
+        (self.method)(&self.receiver, interpreter, arguments)
+    }
+
+    fn arity(&self) -> Arity {
+        Arity::exact(self.arity)
     }
 }
 
@@ -21,6 +21,7 @@ fn main() -> io::Result<()> {
         outdir,
         "Expr",
         &[
+            "crate::callable::NamedArgument",
             "crate::error::*",
             "crate::object::*",
             "crate::token::*",
@@ -31,16 +32,23 @@ fn main() -> io::Result<()> {
         &[
             "Assign       : Token name, Rc<Expr> value",
             "Binary       : Rc<Expr> left, Token operator, Rc<Expr> right",
-            "Call         : Rc<Expr> callee, Token paren, Vec<Rc<Expr>> arguments",
+            "Call         : Rc<Expr> callee, Token paren, Vec<Rc<Expr>> arguments, Vec<NamedArgument> named",
             "Get          : Rc<Expr> object, Token name",
             "Grouping     : Rc<Expr> expression",
+            "Index        : Rc<Expr> object, Token bracket, Rc<Expr> index",
+            "List         : Vec<Rc<Expr>> elements",
             "Literal      : Option<Object> value",
             "Logical      : Rc<Expr> left, Token operator, Rc<Expr> right",
+            "Range        : Rc<Expr> start, Token operator, Rc<Expr> end, Option<Rc<Expr>> step",
             "Set          : Rc<Expr> object, Token name, Rc<Expr> value",
+            "SetIndex     : Rc<Expr> object, Token bracket, Rc<Expr> index, Rc<Expr> value",
+            "Spread       : Token ellipsis, Rc<Expr> value",
             "Super        : Token keyword, Token method",
             "This         : Token keyword",
+            "Tuple        : Vec<Rc<Expr>> elements",
             "Unary        : Token operator, Rc<Expr> right",
             "Variable     : Token name",
+            "Yield        : Token keyword, Option<Rc<Expr>> value",
         ],
     )?;
 
@@ -49,8 +57,12 @@ fn main() -> io::Result<()> {
         outdir,
         "Stmt",
         &[
+            "crate::environment::VarKind",
             "crate::error::*",
             "crate::expr::Expr",
+            "crate::functions_lox::FunctionKind",
+            "crate::lox_enum::VariantDecl",
+            "crate::pattern::{DestructureKind, MatchCase}",
             "crate::token::Token",
             "std::hash::Hash",
             "std::hash::Hasher",
@@ -58,13 +70,19 @@ fn main() -> io::Result<()> {
         ],
         &[
             "Block        : Rc<Vec<Rc<Stmt>>> statements",
-            "Class        : Token name, Option<Rc<Expr>> superclass, Rc<Vec<Rc<Stmt>>> methods",
+            "Class        : Token name, Option<Rc<Expr>> superclass, Rc<Vec<Rc<Stmt>>> methods, Rc<Vec<Rc<Stmt>>> static_methods, Rc<Vec<Rc<Expr>>> traits, bool is_abstract, Rc<Vec<Token>> abstract_methods, Rc<Vec<Rc<Stmt>>> fields, bool strict, Option<Rc<Vec<Token>>> record_fields",
+            "Destructure  : Token bracket, DestructureKind shape, VarKind kind, Rc<Vec<Token>> names, Rc<Expr> initializer",
+            "Enum         : Token name, Rc<Vec<VariantDecl>> variants",
             "Expression   : Rc<Expr> expression",
-            "Function     : Token name, Rc<Vec<Token>> params, Rc<Vec<Rc<Stmt>>> body",
+            "ForIn        : Token name, Rc<Expr> iterable, Rc<Stmt> body",
+            "Function     : Token name, FunctionKind kind, Rc<Vec<Token>> params, Rc<Vec<Rc<Expr>>> defaults, Option<Token> rest, Rc<Vec<Rc<Stmt>>> body",
             "If           : Rc<Expr> condition, Rc<Stmt> then_branch, Option<Rc<Stmt>> else_branch",
+            "Match        : Token keyword, Rc<Expr> subject, Rc<Vec<MatchCase>> cases, Option<Rc<Stmt>> default",
+            "MultiAssign  : Rc<Vec<Rc<Expr>>> targets, Token equals, Rc<Vec<Rc<Expr>>> values",
             "Print        : Rc<Expr> expression",
             "Return       : Token keyword, Option<Rc<Expr>> value",
-            "Var          : Token name, Option<Rc<Expr>> initializer",
+            "Trait        : Token name, Rc<Vec<Rc<Stmt>>> methods",
+            "Var          : Token name, VarKind kind, Option<Rc<Expr>> initializer",
             "While        : Rc<Expr> condition, Rc<Stmt> body",
             "Break        : Token token",
         ],
@@ -1,15 +1,28 @@
 // The AST Tree-walk Interpreter
-use crate::callable::LoxCallable;
+use crate::callable::{arrange_arguments, LoxCallable};
 use crate::environment::*;
 use crate::error::*;
+use crate::event_loop::EventLoop;
 use crate::expr::*;
-use crate::functions_lox::LoxFunction;
+use crate::functions_lox::{FunctionKind, LoxFunction};
 use crate::functions_native::*;
-use crate::lox_class::LoxClass;
+use crate::lox_class::{is_private, private_key, FieldDeclarations, LoxClass};
+use crate::lox_enum::LoxEnum;
+use crate::lox_fiber::LoxFiber;
+use crate::lox_instance::LoxInstance;
+use crate::lox_iterator::LoxIterator;
+use crate::lox_list::LoxList;
+use crate::lox_range::LoxRange;
+use crate::lox_trait::LoxTrait;
+use crate::lox_tuple::LoxTuple;
+use crate::numeric;
 use crate::object::*;
+use crate::pattern::{DestructureKind, Pattern};
 use crate::stmt::*;
 use crate::token::*;
+use std::borrow::Cow;
 use std::cell::RefCell;
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::ops::Deref;
 use std::rc::Rc;
@@ -19,35 +32,73 @@ pub struct Interpreter {
     environment: RefCell<Rc<RefCell<Environment>>>,
     pub globals: Rc<RefCell<Environment>>,
     pub locals: RefCell<HashMap<Rc<Expr>, usize>>,
+    // Class or trait declaring each private property access, by its id
+    privates: RefCell<HashMap<Rc<Expr>, usize>>,
+    // Ids of the class and trait declarations. Private members are keyed on
+    // them rather than on the name, which other classes may share.
+    private_owners: RefCell<HashMap<Rc<Stmt>, usize>>,
+    pub event_loop: EventLoop,
+    // Fibers being resumed, the one running last
+    pub fibers: RefCell<Vec<Rc<LoxFiber>>>,
+    // Instances and lists being stringified, to cut reference cycles short
+    stringifying: RefCell<Vec<*const ()>>,
 }
 
 impl Interpreter {
     pub fn new() -> Interpreter {
         let globals = Rc::new(RefCell::new(Environment::new()));
 
-        globals.borrow_mut().define(
-            "clock",
-            Object::Native(Rc::new(LoxNative {
-                func: Rc::new(NativeClock {}),
-            })),
-        );
+        let natives: Vec<(&str, Rc<dyn LoxCallable>)> = vec![
+            ("clock", Rc::new(NativeClock {})),
+            ("str", Rc::new(NativeStr {})),
+            ("len", Rc::new(NativeLen {})),
+            ("range", Rc::new(NativeRange {})),
+            ("Fiber", Rc::new(NativeFiber {})),
+            ("suspend", Rc::new(NativeSuspend {})),
+            // Event loop
+            ("setTimeout", Rc::new(NativeSetTimeout {})),
+            ("setInterval", Rc::new(NativeSetInterval {})),
+            ("clearTimeout", Rc::new(NativeClearTimeout {})),
+            ("clearInterval", Rc::new(NativeClearTimeout {})),
+            ("defer", Rc::new(NativeDefer {})),
+            // Reflection
+            ("type", Rc::new(NativeType {})),
+            ("classOf", Rc::new(NativeClassOf {})),
+            ("isInstance", Rc::new(NativeIsInstance {})),
+            ("includes", Rc::new(NativeIncludes {})),
+            ("fields", Rc::new(NativeFields {})),
+            ("hasField", Rc::new(NativeHasField {})),
+            ("getField", Rc::new(NativeGetField {})),
+            ("setField", Rc::new(NativeSetField {})),
+            ("methods", Rc::new(NativeMethods {})),
+            ("superclass", Rc::new(NativeSuperclass {})),
+            ("arity", Rc::new(NativeArity {})),
+        ];
+        for (name, func) in natives {
+            globals
+                .borrow_mut()
+                .define(name, Object::Native(Rc::new(LoxNative { func })));
+        }
 
         Interpreter {
             globals: Rc::clone(&globals),
             environment: RefCell::new(Rc::clone(&globals)),
             locals: RefCell::new(HashMap::new()),
+            privates: RefCell::new(HashMap::new()),
+            private_owners: RefCell::new(HashMap::new()),
+            event_loop: EventLoop::new(),
+            fibers: RefCell::new(Vec::new()),
+            stringifying: RefCell::new(Vec::new()),
         }
     }
     pub fn interpret(&self, stmts: &[Rc<Stmt>]) -> Result<(), LoxResult> {
         for s in stmts {
-            if let Err(e) = self.execute(s.clone()) {
-                return Err(e);
-            }
+            self.execute(s.clone())?;
         }
         Ok(())
     }
 
-    fn execute(&self, stmt: Rc<Stmt>) -> Result<(), LoxResult> {
+    pub fn execute(&self, stmt: Rc<Stmt>) -> Result<(), LoxResult> {
         stmt.accept(stmt.clone(), self)
     }
 
@@ -64,13 +115,92 @@ impl Interpreter {
         result
     }
 
-    fn evaluate(&self, expr: Rc<Expr>) -> Result<Object, LoxResult> {
+    pub fn evaluate(&self, expr: Rc<Expr>) -> Result<Object, LoxResult> {
         expr.accept(expr.clone(), self)
     }
 
+    // The environment of the code being run. Fibers switch it along with
+    // their call stack.
+    pub fn environment(&self) -> Rc<RefCell<Environment>> {
+        Rc::clone(&self.environment.borrow())
+    }
+
+    pub fn set_environment(&self, environment: Rc<RefCell<Environment>>) {
+        self.environment.replace(environment);
+    }
+
+    // Suspend the running fiber, handing 'value' to its resumer. Returns the
+    // value it is resumed with, or None outside of a fiber.
+    pub fn suspend_fiber(&self, value: Object) -> Option<Object> {
+        let fiber = self.fibers.borrow().last().cloned()?;
+        Some(LoxFiber::suspend(fiber, self, value))
+    }
+
+    // Run 'f' with 'environment' as the current environment. Generators use
+    // this to run statements in their own environment when resumed.
+    pub fn with_environment<T>(
+        &self,
+        environment: &Rc<RefCell<Environment>>,
+        f: impl FnOnce() -> T,
+    ) -> T {
+        let previous = self.environment.replace(Rc::clone(environment));
+        let result = f();
+        self.environment.replace(previous);
+        result
+    }
+
+    // Assign to the variable resolved for the assignment expression 'expr'
+    pub fn assign_variable(
+        &self,
+        expr: Rc<Expr>,
+        name: &Token,
+        value: Object,
+    ) -> Result<(), LoxResult> {
+        if let Some(distance) = self.locals.borrow().get(&expr) {
+            self.environment
+                .borrow()
+                .borrow_mut()
+                .assign_at(*distance, name, value)?;
+        } else {
+            self.globals.borrow_mut().assign(name, value)?;
+        }
+        Ok(())
+    }
+
     pub fn resolve(&self, expr: Rc<Expr>, depth: usize) {
         self.locals.borrow_mut().insert(expr, depth);
     }
+    pub fn resolve_private(&self, expr: Rc<Expr>, owner: usize) {
+        self.privates.borrow_mut().insert(expr, owner);
+    }
+    // The id of a class or trait declaration, the same each time it runs
+    pub fn private_owner(&self, declaration: &Rc<Stmt>) -> usize {
+        let mut owners = self.private_owners.borrow_mut();
+        let id = owners.len();
+        *owners.entry(Rc::clone(declaration)).or_insert(id)
+    }
+    // The key a property is stored under. Private names are qualified with
+    // the class whose methods access them.
+    fn member_name<'a>(
+        &self,
+        expr: &Rc<Expr>,
+        name: &'a Token,
+    ) -> Result<Cow<'a, Token>, LoxResult> {
+        if name.ttype != TokenType::PrivateName {
+            return Ok(Cow::Borrowed(name));
+        }
+        match self.privates.borrow().get(expr) {
+            Some(owner) => {
+                let mut key = name.clone();
+                key.lexeme = private_key(&name.lexeme, *owner);
+                Ok(Cow::Owned(key))
+            }
+            None => Err(LoxResult::error_runtime(
+                name,
+                &format!("Private member '{}' is not accessible here", name.lexeme),
+            )),
+        }
+    }
     fn lookup_variable(&self, name: &Token, expr: Rc<Expr>) -> Result<Object, LoxResult> {
         if let Some(distance) = self.locals.borrow().get(&expr) {
             self.environment
@@ -85,794 +215,3781 @@ impl Interpreter {
     // Being a dynamically typed language, perform implicit type conversions
     // for all types for the purposes of determining truthiness. false and
     // nil are falsey, and everything else is truthy
-    fn is_truthy(value: &Object) -> bool {
+    pub fn is_truthy(value: &Object) -> bool {
         if let Object::Bool(b) = value {
             *b
         } else {
             !matches!(value, Object::Nil)
         }
     }
-    pub fn print_env(&self) {
-        println!("{:?}", self.environment);
-    }
-}
-
-impl StmtVisitor<()> for Interpreter {
-    fn visit_block_stmt(&self, _: Rc<Stmt>, stmt: &BlockStmt) -> Result<(), LoxResult> {
-        let e = Environment::new_enclosing(self.environment.borrow().clone());
-        self.execute_block(&stmt.statements, e)
-    }
-    fn visit_class_stmt(&self, _base: Rc<Stmt>, stmt: &ClassStmt) -> Result<(), LoxResult> {
-        let superclass = if let Some(superclass_expr) = &stmt.superclass {
-            let superclass = self.evaluate(superclass_expr.clone())?;
-            if let Object::Class(c) = superclass {
-                Some(c)
-            } else if let Expr::Variable(v) = superclass_expr.deref() {
-                return Err(LoxResult::error_runtime(
-                    &v.name,
-                    "superclass must be a class",
-                ));
-            } else {
-                return Err(LoxResult::system_error("Failed to extract variable from"));
-            }
-        } else {
-            None
-        };
-
-        self.environment
-            .borrow()
-            .borrow_mut()
-            .define(&stmt.name.lexeme, Object::Nil);
-
-        // While evaluating a subclass definition, create a new environment
-        // that contains the 'super' keyword definition
-        let enclosing = if let Some(ref s) = superclass {
-            let mut e = Environment::new_enclosing(self.environment.borrow().clone());
-            e.define("super", Object::Class(s.clone()));
-            Some(self.environment.replace(Rc::new(RefCell::new(e))))
-        } else {
-            None
-        };
 
+    // Create the functions for the methods of a class declaration. Setters
+    // are stored as 'name=' so that they do not clash with a getter or a
+    // method of the same name.
+    fn class_methods(
+        &self,
+        owner: &Token,
+        owner_id: usize,
+        declarations: &Rc<Vec<Rc<Stmt>>>,
+        allow_initializer: bool,
+    ) -> Result<HashMap<String, Object>, LoxResult> {
         let mut methods = HashMap::new();
-        for meth in stmt.methods.deref() {
+        for meth in declarations.deref() {
             if let Stmt::Function(method) = meth.deref() {
-                let is_initializer = method.name.lexeme == "init";
+                let is_initializer = allow_initializer && method.name.lexeme == "init";
                 let function = Object::Func(Rc::new(LoxFunction::new(
                     method.deref(),
                     &self.environment.borrow(),
                     is_initializer,
                 )));
-                methods.insert(method.name.lexeme.clone(), function);
+                let mut name = method.name.lexeme.clone();
+                if is_private(&name) {
+                    name = private_key(&name, owner_id);
+                }
+                if method.kind == FunctionKind::Setter {
+                    name.push('=');
+                }
+                methods.insert(name, function);
             } else {
-                return Err(LoxResult::error_runtime(
-                    &stmt.name,
-                    "non-function in class",
-                ));
+                return Err(LoxResult::error_runtime(owner, "non-function in class"));
             }
         }
-        let klass = Object::Class(Rc::new(LoxClass::new(
-            &stmt.name.lexeme,
-            superclass,
-            methods,
-        )));
-
-        // Pop the environment (or scope) that contains the 'super' keyword
-        if let Some(previous) = enclosing {
-            self.environment.replace(previous);
-        }
-        self.environment
-            .borrow()
-            .borrow_mut()
-            .assign(&stmt.name, klass)?;
-        Ok(())
-    }
-    fn visit_expression_stmt(&self, _: Rc<Stmt>, stmt: &ExpressionStmt) -> Result<(), LoxResult> {
-        self.evaluate(stmt.expression.clone())?;
-        Ok(())
-    }
-    fn visit_function_stmt(&self, _: Rc<Stmt>, stmt: &FunctionStmt) -> Result<(), LoxResult> {
-        // Closure holds on to the surrounding variables when a function is declared.
-        // Save the current environment in 'closure' which is the environment
-        // that is active when a function is declared, not when it is called.
-        // For actual function declarations, the initializer is always false
-        let function = LoxFunction::new(stmt, self.environment.borrow().deref(), false);
-        self.environment
-            .borrow()
-            .borrow_mut()
-            .define(&stmt.name.lexeme, Object::Func(Rc::new(function)));
-        Ok(())
-    }
-    fn visit_if_stmt(&self, _: Rc<Stmt>, stmt: &IfStmt) -> Result<(), LoxResult> {
-        if Self::is_truthy(&self.evaluate(stmt.condition.clone())?) {
-            self.execute(stmt.then_branch.clone())
-        } else if let Some(else_branch) = stmt.else_branch.clone() {
-            self.execute(else_branch)
-        } else {
-            Ok(())
-        }
-    }
-    fn visit_print_stmt(&self, _: Rc<Stmt>, stmt: &PrintStmt) -> Result<(), LoxResult> {
-        let value = self.evaluate(stmt.expression.clone())?;
-        println!("{}", value);
-        Ok(())
+        Ok(methods)
     }
-    fn visit_return_stmt(&self, _base: Rc<Stmt>, stmt: &ReturnStmt) -> Result<(), LoxResult> {
-        if let Some(value) = stmt.value.clone() {
-            Err(LoxResult::return_value(self.evaluate(value)?))
-        } else {
-            Err(LoxResult::return_value(Object::Nil))
+
+    // The fields of a record come first. Private fields are stored under the
+    // class's private key like private methods. See 'class_methods'.
+    fn field_declarations(
+        &self,
+        stmt: &ClassStmt,
+        owner_id: usize,
+    ) -> Result<FieldDeclarations, LoxResult> {
+        let mut fields = Vec::new();
+        let record = stmt.record_fields.as_ref().map(|record_fields| {
+            record_fields
+                .iter()
+                .map(|field| field.lexeme.clone())
+                .collect::<Vec<String>>()
+        });
+        for name in record.iter().flatten() {
+            fields.push((name.clone(), None));
         }
-    }
-    fn visit_var_stmt(&self, _: Rc<Stmt>, stmt: &VarStmt) -> Result<(), LoxResult> {
-        let value = if let Some(initilalizer) = stmt.initializer.clone() {
-            self.evaluate(initilalizer)?
-        } else {
-            Object::Nil
-        };
-        self.environment
-            .borrow()
-            .borrow_mut()
-            .define(&stmt.name.lexeme, value);
-        Ok(())
-    }
-    fn visit_while_stmt(&self, _: Rc<Stmt>, stmt: &WhileStmt) -> Result<(), LoxResult> {
-        while Self::is_truthy(&self.evaluate(stmt.condition.clone())?) {
-            match self.execute(stmt.body.clone()) {
-                Err(LoxResult::Break) => break,
-                Err(e) => return Err(e),
-                Ok(_) => {}
+        for field in stmt.fields.iter() {
+            if let Stmt::Var(field) = field.deref() {
+                let mut name = field.name.lexeme.clone();
+                if is_private(&name) {
+                    name = private_key(&name, owner_id);
+                }
+                fields.push((name, field.initializer.clone()));
+            } else {
+                return Err(LoxResult::error_runtime(&stmt.name, "non-field in class"));
             }
         }
-        Ok(())
-    }
-    fn visit_break_stmt(&self, _: Rc<Stmt>, _stmt: &BreakStmt) -> Result<(), LoxResult> {
-        Err(LoxResult::Break)
-    }
-}
-
-impl ExprVisitor<Object> for Interpreter {
-    fn visit_assign_expr(&self, base: Rc<Expr>, expr: &AssignExpr) -> Result<Object, LoxResult> {
-        let value = self.evaluate(expr.value.clone())?;
-        if let Some(distance) = self.locals.borrow().get(&base) {
-            self.environment.borrow().borrow_mut().assign_at(
-                *distance,
-                &expr.name,
-                value.clone(),
-            )?;
-        } else {
-            self.globals
-                .borrow_mut()
-                .assign(&expr.name, value.clone())?;
-        }
-        Ok(value)
-    }
-
-    // Simplest all expression. Just convert the literal to a 'value'
-    // Do not call this when an identifier is encountered.
-    fn visit_literal_expr(
-        &self,
-        _: Rc<Expr>,
-        expr: &LiteralExpr,
-    ) -> result::Result<Object, LoxResult> {
-        Ok(match &expr.value {
-            Some(v) => v.clone(),
-            None => Object::Nil,
+        Ok(FieldDeclarations {
+            fields,
+            closure: self.environment.borrow().clone(),
+            strict: stmt.strict,
+            record,
         })
     }
 
-    // Evaluate left and right subexpressions first and then perform arithmetic,
-    // logical or equality operations. The arithmetic operation produces result
-    // whose type is same as  the operands. However, the logical and equality
-    // operators produce a boolean result.
-    fn visit_binary_expr(&self, _: Rc<Expr>, expr: &BinaryExpr) -> Result<Object, LoxResult> {
-        let left = self.evaluate(expr.left.clone())?;
-        let right = self.evaluate(expr.right.clone())?;
-        let ttype = expr.operator.ttype;
-
-        let result = match (left, right) {
-            (Object::Number(left), Object::Number(right)) => match ttype {
-                TokenType::Minus => Object::Number(left - right),
-                TokenType::Slash => Object::Number(left / right),
-                TokenType::Star => Object::Number(left * right),
-                TokenType::Plus => Object::Number(left + right),
-                TokenType::Greater => Object::Bool(left > right),
-                TokenType::GreaterEqual => Object::Bool(left >= right),
-                TokenType::Less => Object::Bool(left < right),
-                TokenType::LessEqual => Object::Bool(left <= right),
-                TokenType::BangEqual => Object::Bool(left != right),
-                TokenType::EqualEqual => Object::Bool(left == right),
-                _ => Object::IllegalOperation,
-            },
-            (Object::Number(left), Object::Str(right)) => match ttype {
-                TokenType::Plus => Object::Str(format!("{left}{right}")),
-                TokenType::Star => Object::Str(right.repeat(left as usize)),
-                _ => Object::IllegalOperation,
-            },
-            (Object::Str(left), Object::Number(right)) => match ttype {
-                TokenType::Plus => Object::Str(format!("{left}{right}")),
-                TokenType::Star => Object::Str(left.repeat(right as usize)),
-                _ => Object::IllegalOperation,
-            },
-            (Object::Str(left), Object::Str(right)) => match ttype {
-                TokenType::Plus => Object::Str(format!("{left}{right}")),
-                TokenType::BangEqual => Object::Bool(left != right),
-                TokenType::EqualEqual => Object::Bool(left == right),
-                _ => Object::IllegalOperation,
-            },
-            (Object::Bool(left), Object::Bool(right)) => match ttype {
-                TokenType::BangEqual => Object::Bool(left != right),
-                TokenType::EqualEqual => Object::Bool(left == right),
-                _ => Object::IllegalOperation,
-            },
-            (Object::Nil, Object::Nil) => match ttype {
-                TokenType::BangEqual => Object::Bool(false),
-                TokenType::EqualEqual => Object::Bool(true),
-                _ => Object::IllegalOperation,
-            },
-            (Object::Nil, _) => match ttype {
-                TokenType::Equal => Object::Bool(false),
-                TokenType::BangEqual => Object::Bool(true),
-                _ => Object::IllegalOperation,
-            },
-            _ => Object::IllegalOperation,
-        };
-
-        if result == Object::IllegalOperation {
-            Err(LoxResult::error_runtime(
-                &expr.operator,
-                "Illegal operation",
-            ))
+    fn call_setter(
+        &self,
+        setter: &LoxFunction,
+        this: Object,
+        value: Object,
+    ) -> Result<Object, LoxResult> {
+        if let Object::Func(func) = setter.bind(&this) {
+            func.call(self, vec![value], None)
         } else {
-            Ok(result)
+            Err(LoxResult::system_error("Failed to bind setter"))
         }
     }
 
-    fn visit_call_expr(&self, _base: Rc<Expr>, expr: &CallExpr) -> Result<Object, LoxResult> {
-        let callee = self.evaluate(expr.callee.clone())?;
-        let mut arguments = Vec::new();
-        for arg in expr.arguments.clone() {
-            arguments.push(self.evaluate(arg)?);
-        }
-
+    // Call a function, native or class. Instances are callable too when
+    // their class defines a '__call__' method.
+    pub fn call_object(
+        &self,
+        callee: Object,
+        arguments: Vec<Object>,
+        paren: &Token,
+    ) -> Result<Object, LoxResult> {
         let (callfunc, klass): (Option<Rc<dyn LoxCallable>>, Option<Rc<LoxClass>>) = match callee {
             Object::Func(f) => (Some(f), None),
             Object::Native(n) => (Some(n.func.clone()), None),
-            Object::Class(c) => (Some(c.clone()), Some(c)),
+            Object::Variant(v) => (Some(v.clone()), None),
+            Object::Class(c) => {
+                c.check_instantiable(paren)?;
+                (Some(c.clone()), Some(c))
+            }
+            Object::Instance(inst) => {
+                return match Self::bind_method(&inst, "__call__") {
+                    Some(method) => self.call_object(method, arguments, paren),
+                    None => Err(LoxResult::error_runtime(
+                        paren,
+                        &format!("{} is not callable without a '__call__' method", inst),
+                    )),
+                };
+            }
             _ => (None, None),
         };
 
         if let Some(callfunc) = callfunc {
-            if arguments.len() != callfunc.arity() {
+            let arity = callfunc.arity();
+            if !arity.accepts(arguments.len()) {
                 return Err(LoxResult::error_runtime(
-                    &expr.paren,
-                    &format!(
-                        "Expected {} arguments but got {}",
-                        callfunc.arity(),
-                        arguments.len()
-                    ),
+                    paren,
+                    &format!("Expected {} arguments but got {}", arity, arguments.len()),
                 ));
             }
             callfunc.call(self, arguments, klass)
         } else {
             Err(LoxResult::error_runtime(
-                &expr.paren,
+                paren,
                 "Can only call functions and classes",
             ))
         }
     }
 
-    fn visit_get_expr(&self, _base: Rc<Expr>, expr: &GetExpr) -> Result<Object, LoxResult> {
-        // First evaluate the expression whose property is being accessed
-        let object = self.evaluate(expr.object.clone())?;
-        // Only allow get expressions on instance types
-        if let Object::Instance(inst) = object {
-            // If object is an instance, then look up the property
-            inst.get(&expr.name, &inst)
-        } else {
-            Err(LoxResult::error_runtime(
-                &expr.name,
-                "Only instances have properties.",
-            ))
-        }
-    }
-
-    fn visit_grouping_expr(
+    // Call with named arguments, which are matched against the names of the
+    // parameters of the callee
+    fn call_named(
         &self,
-        _base: Rc<Expr>,
-        expr: &GroupingExpr,
+        callee: Object,
+        positional: Vec<Object>,
+        named: Vec<(Token, Object)>,
+        paren: &Token,
     ) -> Result<Object, LoxResult> {
-        self.evaluate(expr.expression.clone())
-    }
-
-    fn visit_logical_expr(&self, _base: Rc<Expr>, expr: &LogicalExpr) -> Result<Object, LoxResult> {
-        let left = self.evaluate(expr.left.clone())?;
-        if expr.operator.ttype == TokenType::Or {
-            // If lhs of logical or is true, do not evaluate rhs
-            if Self::is_truthy(&left) {
-                return Ok(left);
+        let callable: Rc<dyn LoxCallable> = match &callee {
+            Object::Func(f) => f.clone(),
+            Object::Native(n) => n.func.clone(),
+            Object::Variant(v) => v.clone(),
+            // The arguments go to the initializer bound to the new instance
+            Object::Class(c) if c.initializing_class().is_some() => {
+                c.check_instantiable(paren)?;
+                let (instance, initializer) = c.allocate(self, c)?;
+                if let Some(initializer) = initializer {
+                    self.call_named(initializer, positional, named, paren)?;
+                }
+                return Ok(Object::Instance(instance));
             }
-        } else {
-            // If lhs of logical and is true, do not evaluate rhs
-            if !Self::is_truthy(&left) {
-                return Ok(left);
+            Object::Class(c) => c.clone(),
+            Object::Instance(inst) => {
+                if let Some(method) = Self::bind_method(inst, "__call__") {
+                    return self.call_named(method, positional, named, paren);
+                }
+                return self.call_object(callee, positional, paren);
             }
-        }
-        // evaluate rhs only if the result wasn't enough to determine the truthiness
-        self.evaluate(expr.right.clone())
+            _ => return self.call_object(callee, positional, paren),
+        };
+        let arguments = arrange_arguments(callable.as_ref(), self, positional, named, paren)?;
+        self.call_object(callee, arguments, paren)
     }
 
-    fn visit_set_expr(&self, _base: Rc<Expr>, expr: &SetExpr) -> Result<Object, LoxResult> {
-        let object = self.evaluate(expr.object.clone())?;
-        if let Object::Instance(inst) = object {
-            let value = self.evaluate(expr.value.clone())?;
-            inst.set(&expr.name, value.clone());
-            Ok(value)
-        } else {
-            Err(LoxResult::error_runtime(
-                &expr.name,
-                "Only instances have fields",
-            ))
+    // Look up a method on the instance's class and bind 'this' to the instance
+    pub fn bind_method(inst: &Rc<LoxInstance>, name: &str) -> Option<Object> {
+        match inst.klass.find_method(name.to_string()) {
+            Some(Object::Func(func)) => Some(func.bind(&Object::Instance(inst.clone()))),
+            _ => None,
         }
     }
 
-    fn visit_super_expr(&self, base: Rc<Expr>, expr: &SuperExpr) -> Result<Object, LoxResult> {
-        let distance = *self.locals.borrow().get(&base).unwrap();
-        let superclass = if let Ok(Object::Class(superclass)) =
-            self.environment.borrow().borrow().get_at(distance, "super")
-        {
-            superclass
+    // Values of different types are never equal. Numbers compare by value
+    // across 'Int', 'BigInt' and 'Number'. Instances, classes and functions
+    // are only equal to themselves unless the class defines 'equals'.
+    pub fn is_equal(
+        &self,
+        left: &Object,
+        right: &Object,
+        token: &Token,
+    ) -> Result<bool, LoxResult> {
+        // Equality is symmetric, so either operand's 'equals' decides it
+        for (this, other) in [(left, right), (right, left)] {
+            if let Object::Instance(inst) = this {
+                if let Some(equals) = Self::bind_method(inst, "equals") {
+                    let result = self.call_object(equals, vec![other.clone()], token)?;
+                    return Ok(Self::is_truthy(&result));
+                }
+            }
+        }
+        if left.is_numeric() && right.is_numeric() {
+            return Ok(numeric::compare(left, right) == Some(Ordering::Equal));
+        }
+        // Records of the same class are equal if their positional fields are
+        if let (Object::Instance(a), Object::Instance(b)) = (left, right) {
+            if let Some(fields) = a.klass.record_fields() {
+                if !Rc::ptr_eq(&a.klass, &b.klass) {
+                    return Ok(false);
+                }
+                for name in fields {
+                    let x = a.get_field(&name).unwrap_or(Object::Nil);
+                    let y = b.get_field(&name).unwrap_or(Object::Nil);
+                    if !self.is_equal(&x, &y, token)? {
+                        return Ok(false);
+                    }
+                }
+                return Ok(true);
+            }
+        }
+        // Tuples are equal if their items are
+        if let (Object::Tuple(a), Object::Tuple(b)) = (left, right) {
+            if a.len() != b.len() {
+                return Ok(false);
+            }
+            for (a, b) in a.items().iter().zip(b.items()) {
+                if !self.is_equal(a, b, token)? {
+                    return Ok(false);
+                }
+            }
+            return Ok(true);
+        }
+        Ok(left == right)
+    }
+
+    // Name of the special method that overloads a binary operator on instances
+    fn operator_method(ttype: TokenType) -> Option<&'static str> {
+        match ttype {
+            TokenType::Plus => Some("__add__"),
+            TokenType::Minus => Some("__sub__"),
+            TokenType::Star => Some("__mul__"),
+            TokenType::Slash => Some("__div__"),
+            TokenType::EqualEqual => Some("__eq__"),
+            TokenType::BangEqual => Some("__ne__"),
+            TokenType::Less => Some("__lt__"),
+            TokenType::LessEqual => Some("__le__"),
+            TokenType::Greater => Some("__gt__"),
+            TokenType::GreaterEqual => Some("__ge__"),
+            _ => None,
+        }
+    }
+
+    // Name of the method a right-hand instance overloads a binary operator
+    // with when the left operand doesn't. Comparisons swap their operands.
+    fn reflected_method(ttype: TokenType) -> Option<&'static str> {
+        match ttype {
+            TokenType::Plus => Some("__radd__"),
+            TokenType::Minus => Some("__rsub__"),
+            TokenType::Star => Some("__rmul__"),
+            TokenType::Slash => Some("__rdiv__"),
+            TokenType::Less => Some("__gt__"),
+            TokenType::LessEqual => Some("__ge__"),
+            TokenType::Greater => Some("__lt__"),
+            TokenType::GreaterEqual => Some("__le__"),
+            _ => None,
+        }
+    }
+
+    // Instances overload operators by defining special methods such as
+    // '__add__'. Without '__ne__', '!=' is the negation of '__eq__'. When
+    // only the right operand overloads the operator, its reflected method
+    // such as '__radd__' is called with the left operand. Equality is
+    // symmetric, so the right operand's '__eq__' is used as it is. None if
+    // the operator isn't overloaded.
+    fn overloaded_operator(
+        &self,
+        left: &Object,
+        right: &Object,
+        operator: &Token,
+    ) -> Result<Option<Object>, LoxResult> {
+        let ttype = operator.ttype;
+        let has_method = |value: &Object, name| match value {
+            Object::Instance(inst) => Self::bind_method(inst, name).is_some(),
+            _ => false,
+        };
+        // Without '__add__', adding a string concatenates the string form
+        match (ttype, left, right) {
+            (TokenType::Plus, Object::Instance(_), Object::Str(s))
+                if !has_method(left, "__add__") =>
+            {
+                let text = format!("{}{}", self.stringify(left)?, s);
+                return Ok(Some(Object::Str(text)));
+            }
+            (TokenType::Plus, Object::Str(s), Object::Instance(_))
+                if !has_method(right, "__radd__") =>
+            {
+                let text = format!("{}{}", s, self.stringify(right)?);
+                return Ok(Some(Object::Str(text)));
+            }
+            _ => {}
+        }
+        if let TokenType::EqualEqual | TokenType::BangEqual = ttype {
+            for (this, other) in [(left, right), (right, left)] {
+                let Object::Instance(inst) = this else {
+                    continue;
+                };
+                if ttype == TokenType::BangEqual && has_method(this, "__ne__") {
+                    let result =
+                        self.call_operator(inst, "__ne__", vec![other.clone()], operator)?;
+                    return Ok(Some(result));
+                }
+                if has_method(this, "__eq__") {
+                    let equal =
+                        self.call_operator(inst, "__eq__", vec![other.clone()], operator)?;
+                    let equal = Self::is_truthy(&equal) == (ttype == TokenType::EqualEqual);
+                    return Ok(Some(Object::Bool(equal)));
+                }
+            }
+            // Without '__eq__' equality falls back to 'equals' or identity
+            return Ok(None);
+        }
+        let (Some(method), Some(reflected)) =
+            (Self::operator_method(ttype), Self::reflected_method(ttype))
+        else {
+            return Ok(None);
+        };
+        match (left, right) {
+            (Object::Instance(inst), _) if has_method(left, method) => {
+                self.call_operator(inst, method, vec![right.clone()], operator)
+            }
+            (_, Object::Instance(inst)) if has_method(right, reflected) => {
+                self.call_operator(inst, reflected, vec![left.clone()], operator)
+            }
+            (Object::Instance(inst), _) => {
+                self.call_operator(inst, method, vec![right.clone()], operator)
+            }
+            (_, Object::Instance(inst)) => {
+                self.call_operator(inst, reflected, vec![left.clone()], operator)
+            }
+            _ => return Ok(None),
+        }
+        .map(Some)
+    }
+
+    // Dispatch an operator to the special method that overloads it
+    fn call_operator(
+        &self,
+        inst: &Rc<LoxInstance>,
+        method: &str,
+        arguments: Vec<Object>,
+        token: &Token,
+    ) -> Result<Object, LoxResult> {
+        match Self::bind_method(inst, method) {
+            Some(method) => self.call_object(method, arguments, token),
+            None => Err(LoxResult::error_runtime(
+                token,
+                &format!(
+                    "Operator '{}' is not supported by {}. Define a '{}' method to overload it",
+                    token.lexeme, inst, method
+                ),
+            )),
+        }
+    }
+
+    // The string form of a value as shown by 'print'. Instances are converted
+    // using their 'toString' method if the class defines one. Otherwise
+    // their fields are listed in order of their names, or records list
+    // their positional fields like a call: 'Point(x: 1, y: 2)'.
+    pub fn stringify(&self, value: &Object) -> Result<String, LoxResult> {
+        let inst = match value {
+            Object::Instance(inst) => inst,
+            Object::List(list) => return self.stringify_list(list),
+            Object::Tuple(tuple) => return self.stringify_tuple(tuple),
+            _ => return Ok(value.to_string()),
+        };
+        if let Some(Object::Func(method)) = Self::bind_method(inst, "toString") {
+            if !method.arity().accepts(0) {
+                return Err(LoxResult::system_error(&format!(
+                    "'toString' of class {} must not take any arguments",
+                    inst.klass.name
+                )));
+            }
+            return Ok(method.call(self, Vec::new(), None)?.to_string());
+        }
+
+        let ptr = Rc::as_ptr(inst) as *const ();
+        if self.stringifying.borrow().contains(&ptr) {
+            return Ok(format!("{} {{...}}", inst.klass.name));
+        }
+        self.stringifying.borrow_mut().push(ptr);
+        let record = inst.klass.record_fields();
+        let fields = match &record {
+            Some(names) => names
+                .iter()
+                .map(|name| (name.clone(), inst.get_field(name).unwrap_or(Object::Nil)))
+                .collect(),
+            None => inst.sorted_fields(),
+        };
+        let fields = fields
+            .iter()
+            .map(|(name, value)| Ok(format!("{}: {}", name, self.stringify_item(value)?)))
+            .collect::<Result<Vec<String>, LoxResult>>();
+        self.stringifying.borrow_mut().pop();
+        let fields = fields?;
+
+        if record.is_some() {
+            Ok(format!("{}({})", inst.klass.name, fields.join(", ")))
+        } else if fields.is_empty() {
+            Ok(format!("{} {{}}", inst.klass.name))
         } else {
-            return Err(LoxResult::system_error("Failed to extract superclass"));
+            Ok(format!("{} {{ {} }}", inst.klass.name, fields.join(", ")))
+        }
+    }
+
+    fn stringify_list(&self, list: &Rc<LoxList>) -> Result<String, LoxResult> {
+        let ptr = Rc::as_ptr(list) as *const ();
+        if self.stringifying.borrow().contains(&ptr) {
+            return Ok("[...]".to_string());
+        }
+        self.stringifying.borrow_mut().push(ptr);
+        let items = list
+            .items()
+            .iter()
+            .map(|item| self.stringify_item(item))
+            .collect::<Result<Vec<String>, LoxResult>>();
+        self.stringifying.borrow_mut().pop();
+        Ok(format!("[{}]", items?.join(", ")))
+    }
+
+    fn stringify_tuple(&self, tuple: &LoxTuple) -> Result<String, LoxResult> {
+        let items = tuple
+            .items()
+            .iter()
+            .map(|item| self.stringify_item(item))
+            .collect::<Result<Vec<String>, LoxResult>>()?;
+        match items.as_slice() {
+            [item] => Ok(format!("({},)", item)),
+            _ => Ok(format!("({})", items.join(", "))),
+        }
+    }
+
+    // Strings nested inside a list, a tuple or an instance are shown quoted
+    fn stringify_item(&self, value: &Object) -> Result<String, LoxResult> {
+        match value {
+            Object::Str(s) => Ok(format!("\"{}\"", s)),
+            _ => self.stringify(value),
+        }
+    }
+
+    // The body of the first case of a match whose pattern matches and whose
+    // guard holds, with an environment holding the bindings of the pattern
+    pub fn select_case(
+        &self,
+        stmt: &MatchStmt,
+    ) -> Result<(Rc<Stmt>, Rc<RefCell<Environment>>), LoxResult> {
+        let subject = self.evaluate(stmt.subject.clone())?;
+        for case in stmt.cases.iter() {
+            for pattern in case.patterns.iter() {
+                let mut bindings = Vec::new();
+                if !self.match_pattern(pattern, &subject, &mut bindings, &stmt.keyword)? {
+                    continue;
+                }
+                let mut e = Environment::new_enclosing(self.environment.borrow().clone());
+                for (name, value) in bindings {
+                    e.define(&name, value);
+                }
+                let e = Rc::new(RefCell::new(e));
+                if let Some(guard) = &case.guard {
+                    let guard = self.with_environment(&e, || self.evaluate(guard.clone()))?;
+                    if !Self::is_truthy(&guard) {
+                        continue;
+                    }
+                }
+                return Ok((case.body.clone(), e));
+            }
+        }
+        // The default is resolved in the scope of the match itself
+        match &stmt.default {
+            Some(default) => Ok((default.clone(), self.environment.borrow().clone())),
+            None => Err(LoxResult::error_runtime(
+                &stmt.keyword,
+                &format!("No case matches {}", self.stringify(&subject)?),
+            )),
+        }
+    }
+
+    fn match_pattern(
+        &self,
+        pattern: &Pattern,
+        value: &Object,
+        bindings: &mut Vec<(String, Object)>,
+        token: &Token,
+    ) -> Result<bool, LoxResult> {
+        match pattern {
+            Pattern::Wildcard => Ok(true),
+            Pattern::Literal(literal) => self.is_equal(value, literal, token),
+            Pattern::Binding(name) => {
+                bindings.push((name.lexeme.clone(), value.clone()));
+                Ok(true)
+            }
+            Pattern::Instance {
+                class,
+                name,
+                fields,
+            } => {
+                let klass = match self.evaluate(class.clone())? {
+                    Object::Class(klass) => klass,
+                    _ => {
+                        return Err(LoxResult::error_runtime(
+                            name,
+                            &format!("'{}' in a pattern is not a class", name.lexeme),
+                        ))
+                    }
+                };
+                let inst = match value {
+                    Object::Instance(inst) if inst.klass.is_subclass_of(&klass) => inst,
+                    _ => return Ok(false),
+                };
+                self.match_fields(fields, |name| inst.get_field(name), bindings, token)
+            }
+            Pattern::Variant {
+                enumeration,
+                name,
+                fields,
+            } => {
+                let variant = match self.evaluate(enumeration.clone())? {
+                    Object::Enum(enumeration) => match enumeration.variant(&name.lexeme) {
+                        Some(variant) => variant,
+                        None => return Err(enumeration.undefined_variant(name)),
+                    },
+                    _ => {
+                        return Err(LoxResult::error_runtime(
+                            name,
+                            &format!("'{}' in a pattern is not a variant of an enum", name.lexeme),
+                        ))
+                    }
+                };
+                let value = match value {
+                    Object::EnumValue(value) if Rc::ptr_eq(&value.variant, &variant) => value,
+                    _ => return Ok(false),
+                };
+                self.match_fields(fields, |name| value.get_field(name), bindings, token)
+            }
+        }
+    }
+
+    fn match_fields(
+        &self,
+        fields: &[(Token, Pattern)],
+        get_field: impl Fn(&str) -> Option<Object>,
+        bindings: &mut Vec<(String, Object)>,
+        token: &Token,
+    ) -> Result<bool, LoxResult> {
+        for (field, pattern) in fields {
+            match get_field(&field.lexeme) {
+                Some(value) if self.match_pattern(pattern, &value, bindings, token)? => {}
+                _ => return Ok(false),
+            }
+        }
+        Ok(true)
+    }
+
+    // Evaluate arguments or list elements, expanding the iterables that are
+    // spread with '...' into their items
+    fn evaluate_spread(&self, exprs: &[Rc<Expr>]) -> Result<Vec<Object>, LoxResult> {
+        let mut values = Vec::new();
+        for expr in exprs {
+            let spread = match expr.deref() {
+                Expr::Spread(spread) => spread,
+                _ => {
+                    values.push(self.evaluate(expr.clone())?);
+                    continue;
+                }
+            };
+            let iterable = self.evaluate(spread.value.clone())?;
+            let mut iterator = LoxIterator::new(self, iterable, &spread.ellipsis)?;
+            while let Some(value) = iterator.next(self, &spread.ellipsis)? {
+                values.push(value);
+            }
+        }
+        Ok(values)
+    }
+
+    // The items of a list or a tuple destructured into 'count' names
+    fn unpack(
+        &self,
+        value: &Object,
+        count: usize,
+        token: &Token,
+    ) -> Result<Vec<Object>, LoxResult> {
+        match value {
+            Object::List(list) if list.len() == count => Ok(list.items()),
+            Object::Tuple(tuple) if tuple.len() == count => Ok(tuple.items().to_vec()),
+            Object::List(_) | Object::Tuple(_) => Err(LoxResult::error_runtime(
+                token,
+                &format!("Expected {} values to unpack from {}", count, value),
+            )),
+            _ => Err(LoxResult::error_runtime(
+                token,
+                &format!(
+                    "Expected a list or a tuple to unpack but got a {}",
+                    value.type_name()
+                ),
+            )),
+        }
+    }
+
+    // Assign to a variable, property or index as if by an assignment
+    // expression whose value was already evaluated
+    fn assign_target(&self, target: &Rc<Expr>, value: Object) -> Result<(), LoxResult> {
+        match target.deref() {
+            Expr::Variable(variable) => self.assign_variable(target.clone(), &variable.name, value),
+            Expr::Get(get) => {
+                let object = self.evaluate(get.object.clone())?;
+                let name = self.member_name(target, &get.name)?;
+                self.set_property(object, &name, value)
+            }
+            Expr::Index(index_expr) => {
+                let object = self.evaluate(index_expr.object.clone())?;
+                let index = self.evaluate(index_expr.index.clone())?;
+                self.set_index(object, index, value, &index_expr.bracket)
+            }
+            _ => Err(LoxResult::system_error("Invalid assignment target")),
+        }
+    }
+
+    // 'value in collection'. Instances take part by defining '__contains__'.
+    fn contains(
+        &self,
+        collection: &Object,
+        value: &Object,
+        token: &Token,
+    ) -> Result<bool, LoxResult> {
+        let items = match collection {
+            Object::Range(range) => return Ok(range.contains(value)),
+            Object::Str(s) => match value {
+                Object::Str(value) => return Ok(s.contains(value.as_str())),
+                _ => {
+                    return Err(LoxResult::error_runtime(
+                        token,
+                        "Only strings can be searched for in a string",
+                    ))
+                }
+            },
+            Object::Instance(inst) => {
+                let found = self.call_operator(inst, "__contains__", vec![value.clone()], token)?;
+                return Ok(Self::is_truthy(&found));
+            }
+            Object::List(list) => list.items(),
+            Object::Tuple(tuple) => tuple.items().to_vec(),
+            _ => {
+                return Err(LoxResult::error_runtime(
+                    token,
+                    &format!("Can't look for values in a {}", collection.type_name()),
+                ))
+            }
         };
-        // The environment where 'this' is bound is always right inside the
-        // environment where 'super' is stored. Offsetting the distance by one
-        // looks up 'this' in that inner environment.
-        let object = self
-            .environment
+        for item in items.iter() {
+            if self.is_equal(item, value, token)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    // The characters of a string or the items of a list or a tuple at the
+    // indexes in a range
+    fn slice(
+        &self,
+        object: &Object,
+        range: &LoxRange,
+        bracket: &Token,
+    ) -> Result<Object, LoxResult> {
+        let items = match object {
+            Object::Str(s) => s.chars().map(|c| Object::Str(c.to_string())).collect(),
+            Object::List(list) => list.items(),
+            Object::Tuple(tuple) => tuple.items().to_vec(),
+            _ => {
+                return Err(LoxResult::error_runtime(
+                    bracket,
+                    "Only strings, lists and tuples can be sliced",
+                ))
+            }
+        };
+        let mut sliced = Vec::new();
+        for index in range.iter() {
+            match usize::try_from(index).ok().and_then(|i| items.get(i)) {
+                Some(item) => sliced.push(item.clone()),
+                None => {
+                    return Err(LoxResult::error_runtime(
+                        bracket,
+                        &format!("Slice {} out of range", range),
+                    ))
+                }
+            }
+        }
+        Ok(match object {
+            Object::Str(_) => Object::Str(sliced.iter().map(|c| c.to_string()).collect()),
+            Object::Tuple(_) => Object::Tuple(Rc::new(LoxTuple::new(sliced))),
+            _ => Object::List(Rc::new(LoxList::new(sliced))),
+        })
+    }
+
+    // Lists are indexed by non-negative integers
+    fn list_index(index: &Object) -> Option<usize> {
+        match index {
+            Object::Int(i) => usize::try_from(*i).ok(),
+            _ => None,
+        }
+    }
+
+    // Run a getter right away when the property is read
+    fn get_property(&self, property: Object) -> Result<Object, LoxResult> {
+        match property {
+            Object::Func(func) if func.is_getter() => func.call(self, Vec::new(), None),
+            _ => Ok(property),
+        }
+    }
+
+    // A setter intercepts the assignment instead of storing a field
+    fn set_property(&self, object: Object, name: &Token, value: Object) -> Result<(), LoxResult> {
+        let setter = format!("{}=", name.lexeme);
+        match object {
+            Object::Instance(inst) => {
+                if let Some(Object::Func(func)) = inst.klass.find_method(setter) {
+                    self.call_setter(&func, Object::Instance(inst.clone()), value)?;
+                } else {
+                    inst.set(name, value)?;
+                }
+                Ok(())
+            }
+            Object::Class(klass) => {
+                if let Some(Object::Func(func)) = klass.find_static_method(setter) {
+                    self.call_setter(&func, Object::Class(klass.clone()), value)?;
+                } else {
+                    klass.set(name, value);
+                }
+                Ok(())
+            }
+            _ => Err(LoxResult::error_runtime(
+                name,
+                "Only instances and classes have fields",
+            )),
+        }
+    }
+
+    fn set_index(
+        &self,
+        object: Object,
+        index: Object,
+        value: Object,
+        bracket: &Token,
+    ) -> Result<(), LoxResult> {
+        match object {
+            Object::Instance(inst) => {
+                self.call_operator(&inst, "__setitem__", vec![index, value], bracket)?;
+                Ok(())
+            }
+            Object::List(list) => match Self::list_index(&index) {
+                Some(i) if list.set(i, value) => Ok(()),
+                _ => Err(LoxResult::error_runtime(
+                    bracket,
+                    &format!("List index '{}' out of range", index),
+                )),
+            },
+            _ => Err(LoxResult::error_runtime(
+                bracket,
+                "Only lists and instances support index assignment",
+            )),
+        }
+    }
+
+    pub fn print_env(&self) {
+        println!("{:?}", self.environment);
+    }
+}
+
+impl StmtVisitor<()> for Interpreter {
+    fn visit_block_stmt(&self, _: Rc<Stmt>, stmt: &BlockStmt) -> Result<(), LoxResult> {
+        let e = Environment::new_enclosing(self.environment.borrow().clone());
+        self.execute_block(&stmt.statements, e)
+    }
+    fn visit_class_stmt(&self, base: Rc<Stmt>, stmt: &ClassStmt) -> Result<(), LoxResult> {
+        let superclass = if let Some(superclass_expr) = &stmt.superclass {
+            let superclass = self.evaluate(superclass_expr.clone())?;
+            if let Object::Class(c) = superclass {
+                Some(c)
+            } else if let Expr::Variable(v) = superclass_expr.deref() {
+                return Err(LoxResult::error_runtime(
+                    &v.name,
+                    "superclass must be a class",
+                ));
+            } else {
+                return Err(LoxResult::system_error("Failed to extract variable from"));
+            }
+        } else {
+            None
+        };
+
+        let mut traits = Vec::new();
+        for tr in stmt.traits.iter() {
+            let Expr::Variable(v) = tr.deref() else {
+                return Err(LoxResult::system_error("Failed to extract trait name"));
+            };
+            match self.evaluate(tr.clone())? {
+                Object::Trait(t) => traits.push((v.name.clone(), t)),
+                _ => return Err(LoxResult::error_runtime(&v.name, "Can only include traits")),
+            }
+        }
+
+        self.environment
+            .borrow()
+            .borrow_mut()
+            .declare(&stmt.name, VarKind::Var, Object::Nil)?;
+
+        // While evaluating a subclass definition, create a new environment
+        // that contains the 'super' keyword definition
+        let enclosing = if let Some(ref s) = superclass {
+            let mut e = Environment::new_enclosing(self.environment.borrow().clone());
+            e.define("super", Object::Class(s.clone()));
+            Some(self.environment.replace(Rc::new(RefCell::new(e))))
+        } else {
+            None
+        };
+
+        let owner = self.private_owner(&base);
+        let methods = self.class_methods(&stmt.name, owner, &stmt.methods, true)?;
+        let static_methods = self.class_methods(&stmt.name, owner, &stmt.static_methods, false)?;
+        let klass = Rc::new(LoxClass::new(
+            &stmt.name.lexeme,
+            superclass,
+            methods,
+            static_methods,
+            stmt.is_abstract.then(|| {
+                stmt.abstract_methods
+                    .iter()
+                    .map(|name| name.lexeme.clone())
+                    .collect()
+            }),
+            self.field_declarations(stmt, owner)?,
+        ));
+
+        // Pop the environment (or scope) that contains the 'super' keyword
+        if let Some(previous) = enclosing {
+            self.environment.replace(previous);
+        }
+        // The same check as including the traits at runtime, reported at
+        // the trait's name
+        for (token, tr) in traits.iter() {
+            if let Some(message) = klass.trait_conflict(tr) {
+                return Err(LoxResult::error_runtime(token, &message));
+            }
+            klass.include(tr)?;
+        }
+        self.environment
             .borrow()
+            .borrow_mut()
+            .assign(&stmt.name, Object::Class(klass))?;
+        Ok(())
+    }
+    fn visit_enum_stmt(&self, _base: Rc<Stmt>, stmt: &EnumStmt) -> Result<(), LoxResult> {
+        let enumeration = Object::Enum(Rc::new(LoxEnum::new(&stmt.name.lexeme, &stmt.variants)));
+        self.environment
             .borrow()
-            .get_at(distance - 1, "this")
-            .unwrap();
+            .borrow_mut()
+            .declare(&stmt.name, VarKind::Var, enumeration)
+    }
+    fn visit_trait_stmt(&self, base: Rc<Stmt>, stmt: &TraitStmt) -> Result<(), LoxResult> {
+        let owner = self.private_owner(&base);
+        let methods = self.class_methods(&stmt.name, owner, &stmt.methods, true)?;
+        let tr = Object::Trait(Rc::new(LoxTrait::new(&stmt.name.lexeme, methods)));
+        self.environment
+            .borrow()
+            .borrow_mut()
+            .declare(&stmt.name, VarKind::Var, tr)
+    }
+    fn visit_expression_stmt(&self, _: Rc<Stmt>, stmt: &ExpressionStmt) -> Result<(), LoxResult> {
+        self.evaluate(stmt.expression.clone())?;
+        Ok(())
+    }
+    // Each iteration runs the body in a fresh environment holding the loop
+    // variable, so closures capture the value of that iteration
+    fn visit_forin_stmt(&self, _: Rc<Stmt>, stmt: &ForInStmt) -> Result<(), LoxResult> {
+        let iterable = self.evaluate(stmt.iterable.clone())?;
+        let mut iterator = LoxIterator::new(self, iterable, &stmt.name)?;
+        let body = Rc::new(vec![stmt.body.clone()]);
+        while let Some(item) = iterator.next(self, &stmt.name)? {
+            let mut e = Environment::new_enclosing(self.environment.borrow().clone());
+            e.define(&stmt.name.lexeme, item);
+            match self.execute_block(&body, e) {
+                Err(LoxResult::Break) => break,
+                Err(e) => return Err(e),
+                Ok(_) => {}
+            }
+        }
+        Ok(())
+    }
+    fn visit_function_stmt(&self, _: Rc<Stmt>, stmt: &FunctionStmt) -> Result<(), LoxResult> {
+        // Closure holds on to the surrounding variables when a function is declared.
+        // Save the current environment in 'closure' which is the environment
+        // that is active when a function is declared, not when it is called.
+        // For actual function declarations, the initializer is always false
+        let function = LoxFunction::new(stmt, self.environment.borrow().deref(), false);
+        self.environment.borrow().borrow_mut().declare(
+            &stmt.name,
+            VarKind::Var,
+            Object::Func(Rc::new(function)),
+        )
+    }
+    fn visit_if_stmt(&self, _: Rc<Stmt>, stmt: &IfStmt) -> Result<(), LoxResult> {
+        if Self::is_truthy(&self.evaluate(stmt.condition.clone())?) {
+            self.execute(stmt.then_branch.clone())
+        } else if let Some(else_branch) = stmt.else_branch.clone() {
+            self.execute(else_branch)
+        } else {
+            Ok(())
+        }
+    }
+    fn visit_destructure_stmt(&self, _: Rc<Stmt>, stmt: &DestructureStmt) -> Result<(), LoxResult> {
+        let value = self.evaluate(stmt.initializer.clone())?;
+        let values = match (stmt.shape, &value) {
+            (DestructureKind::List, _) => self.unpack(&value, stmt.names.len(), &stmt.bracket)?,
+            (DestructureKind::Fields, Object::Instance(inst)) => {
+                let mut values = Vec::new();
+                for name in stmt.names.iter() {
+                    values.push(self.get_property(inst.get(name, inst)?)?);
+                }
+                values
+            }
+            (DestructureKind::Fields, _) => {
+                return Err(LoxResult::error_runtime(
+                    &stmt.bracket,
+                    &format!("Can't destructure fields of a {}", value.type_name()),
+                ))
+            }
+        };
+        for (name, value) in stmt.names.iter().zip(values) {
+            self.environment
+                .borrow()
+                .borrow_mut()
+                .declare(name, stmt.kind, value)?;
+        }
+        Ok(())
+    }
+    fn visit_multiassign_stmt(&self, _: Rc<Stmt>, stmt: &MultiAssignStmt) -> Result<(), LoxResult> {
+        let mut values = Vec::new();
+        for value in stmt.values.iter() {
+            values.push(self.evaluate(value.clone())?);
+        }
+        if values.len() == 1 {
+            values = self.unpack(&values[0], stmt.targets.len(), &stmt.equals)?;
+        }
+        for (target, value) in stmt.targets.iter().zip(values) {
+            self.assign_target(target, value)?;
+        }
+        Ok(())
+    }
+    fn visit_match_stmt(&self, _: Rc<Stmt>, stmt: &MatchStmt) -> Result<(), LoxResult> {
+        let (body, environment) = self.select_case(stmt)?;
+        self.with_environment(&environment, || self.execute(body))
+    }
+    fn visit_print_stmt(&self, _: Rc<Stmt>, stmt: &PrintStmt) -> Result<(), LoxResult> {
+        let value = self.evaluate(stmt.expression.clone())?;
+        println!("{}", self.stringify(&value)?);
+        Ok(())
+    }
+    fn visit_return_stmt(&self, _base: Rc<Stmt>, stmt: &ReturnStmt) -> Result<(), LoxResult> {
+        if let Some(value) = stmt.value.clone() {
+            Err(LoxResult::return_value(self.evaluate(value)?))
+        } else {
+            Err(LoxResult::return_value(Object::Nil))
+        }
+    }
+    fn visit_var_stmt(&self, _: Rc<Stmt>, stmt: &VarStmt) -> Result<(), LoxResult> {
+        let value = if let Some(initilalizer) = stmt.initializer.clone() {
+            self.evaluate(initilalizer)?
+        } else {
+            Object::Nil
+        };
+        self.environment
+            .borrow()
+            .borrow_mut()
+            .declare(&stmt.name, stmt.kind, value)
+    }
+    fn visit_while_stmt(&self, _: Rc<Stmt>, stmt: &WhileStmt) -> Result<(), LoxResult> {
+        while Self::is_truthy(&self.evaluate(stmt.condition.clone())?) {
+            match self.execute(stmt.body.clone()) {
+                Err(LoxResult::Break) => break,
+                Err(e) => return Err(e),
+                Ok(_) => {}
+            }
+        }
+        Ok(())
+    }
+    fn visit_break_stmt(&self, _: Rc<Stmt>, _stmt: &BreakStmt) -> Result<(), LoxResult> {
+        Err(LoxResult::Break)
+    }
+}
+
+impl ExprVisitor<Object> for Interpreter {
+    fn visit_assign_expr(&self, base: Rc<Expr>, expr: &AssignExpr) -> Result<Object, LoxResult> {
+        let value = self.evaluate(expr.value.clone())?;
+        self.assign_variable(base, &expr.name, value.clone())?;
+        Ok(value)
+    }
+
+    // Simplest all expression. Just convert the literal to a 'value'
+    // Do not call this when an identifier is encountered.
+    fn visit_literal_expr(
+        &self,
+        _: Rc<Expr>,
+        expr: &LiteralExpr,
+    ) -> result::Result<Object, LoxResult> {
+        Ok(match &expr.value {
+            Some(v) => v.clone(),
+            None => Object::Nil,
+        })
+    }
+
+    // Evaluate left and right subexpressions first and then perform arithmetic,
+    // logical or equality operations. The arithmetic operation produces result
+    // whose type is same as  the operands. However, the logical and equality
+    // operators produce a boolean result.
+    fn visit_binary_expr(&self, _: Rc<Expr>, expr: &BinaryExpr) -> Result<Object, LoxResult> {
+        let left = self.evaluate(expr.left.clone())?;
+        let right = self.evaluate(expr.right.clone())?;
+        let ttype = expr.operator.ttype;
+        if ttype == TokenType::In {
+            let found = self.contains(&right, &left, &expr.operator)?;
+            return Ok(Object::Bool(found));
+        }
+
+        if let Some(result) = self.overloaded_operator(&left, &right, &expr.operator)? {
+            return Ok(result);
+        }
+        // Any two values can be compared for equality
+        if let TokenType::EqualEqual | TokenType::BangEqual = ttype {
+            let equal = self.is_equal(&left, &right, &expr.operator)?;
+            return Ok(Object::Bool(equal == (ttype == TokenType::EqualEqual)));
+        }
+
+        let result = match (left, right) {
+            // Integers and floats mix freely. See 'numeric' for promotion rules
+            (left, right) if left.is_numeric() && right.is_numeric() => {
+                numeric::binary(ttype, &left, &right)
+            }
+            (left, Object::Str(right)) if left.is_numeric() => match ttype {
+                TokenType::Plus => Object::Str(format!("{left}{right}")),
+                TokenType::Star => match numeric::repeat_count(&left) {
+                    Some(count) => Object::Str(right.repeat(count)),
+                    None => Object::IllegalOperation,
+                },
+                _ => Object::IllegalOperation,
+            },
+            (Object::Str(left), right) if right.is_numeric() => match ttype {
+                TokenType::Plus => Object::Str(format!("{left}{right}")),
+                TokenType::Star => match numeric::repeat_count(&right) {
+                    Some(count) => Object::Str(left.repeat(count)),
+                    None => Object::IllegalOperation,
+                },
+                _ => Object::IllegalOperation,
+            },
+            (Object::Str(left), Object::Str(right)) => match ttype {
+                TokenType::Plus => Object::Str(format!("{left}{right}")),
+                _ => Object::IllegalOperation,
+            },
+            _ => Object::IllegalOperation,
+        };
+
+        if result == Object::IllegalOperation {
+            Err(LoxResult::error_runtime(
+                &expr.operator,
+                "Illegal operation",
+            ))
+        } else {
+            Ok(result)
+        }
+    }
+
+    fn visit_call_expr(&self, _base: Rc<Expr>, expr: &CallExpr) -> Result<Object, LoxResult> {
+        let callee = self.evaluate(expr.callee.clone())?;
+        let arguments = self.evaluate_spread(&expr.arguments)?;
+        if expr.named.is_empty() {
+            return self.call_object(callee, arguments, &expr.paren);
+        }
+        let mut named = Vec::new();
+        for argument in expr.named.iter() {
+            let value = self.evaluate(argument.value.clone())?;
+            named.push((argument.name.clone(), value));
+        }
+        self.call_named(callee, arguments, named, &expr.paren)
+    }
+
+    fn visit_get_expr(&self, base: Rc<Expr>, expr: &GetExpr) -> Result<Object, LoxResult> {
+        // First evaluate the expression whose property is being accessed
+        let object = self.evaluate(expr.object.clone())?;
+        let name = self.member_name(&base, &expr.name)?;
+        // Only allow get expressions on instances and classes
+        match object {
+            // If object is an instance, then look up the property
+            Object::Instance(inst) => self.get_property(inst.get(&name, &inst)?),
+            // Classes have static fields and methods
+            Object::Class(klass) => self.get_property(klass.get(&name, &klass)?),
+            Object::Enum(enumeration) => enumeration.get(&name),
+            Object::EnumValue(value) => value.get(&name),
+            Object::Generator(generator) => generator.get(&expr.name, &generator),
+            Object::Fiber(fiber) => fiber.get(&expr.name, &fiber),
+            _ => Err(LoxResult::error_runtime(
+                &expr.name,
+                "Only instances, classes, generators and fibers have properties.",
+            )),
+        }
+    }
+
+    fn visit_grouping_expr(
+        &self,
+        _base: Rc<Expr>,
+        expr: &GroupingExpr,
+    ) -> Result<Object, LoxResult> {
+        self.evaluate(expr.expression.clone())
+    }
+
+    fn visit_index_expr(&self, _base: Rc<Expr>, expr: &IndexExpr) -> Result<Object, LoxResult> {
+        let object = self.evaluate(expr.object.clone())?;
+        let index = self.evaluate(expr.index.clone())?;
+        // Indexing with a range takes a slice
+        if let Object::Range(range) = &index {
+            if !matches!(object, Object::Instance(_)) {
+                return self.slice(&object, range, &expr.bracket);
+            }
+        }
+        match object {
+            Object::Instance(inst) => {
+                self.call_operator(&inst, "__getitem__", vec![index], &expr.bracket)
+            }
+            Object::Str(s) => {
+                let c = match index {
+                    Object::Int(i) if i >= 0 => s.chars().nth(i as usize),
+                    _ => None,
+                };
+                match c {
+                    Some(c) => Ok(Object::Str(c.to_string())),
+                    None => Err(LoxResult::error_runtime(
+                        &expr.bracket,
+                        &format!("String index '{}' out of range", index),
+                    )),
+                }
+            }
+            Object::List(list) => {
+                let item = Self::list_index(&index).and_then(|i| list.get(i));
+                item.ok_or_else(|| {
+                    LoxResult::error_runtime(
+                        &expr.bracket,
+                        &format!("List index '{}' out of range", index),
+                    )
+                })
+            }
+            Object::Tuple(tuple) => {
+                let item = Self::list_index(&index).and_then(|i| tuple.get(i));
+                item.ok_or_else(|| {
+                    LoxResult::error_runtime(
+                        &expr.bracket,
+                        &format!("Tuple index '{}' out of range", index),
+                    )
+                })
+            }
+            _ => Err(LoxResult::error_runtime(
+                &expr.bracket,
+                "Only strings, lists, tuples and instances can be indexed",
+            )),
+        }
+    }
+
+    fn visit_list_expr(&self, _base: Rc<Expr>, expr: &ListExpr) -> Result<Object, LoxResult> {
+        let elements = self.evaluate_spread(&expr.elements)?;
+        Ok(Object::List(Rc::new(LoxList::new(elements))))
+    }
+
+    fn visit_logical_expr(&self, _base: Rc<Expr>, expr: &LogicalExpr) -> Result<Object, LoxResult> {
+        let left = self.evaluate(expr.left.clone())?;
+        if expr.operator.ttype == TokenType::Or {
+            // If lhs of logical or is true, do not evaluate rhs
+            if Self::is_truthy(&left) {
+                return Ok(left);
+            }
+        } else {
+            // If lhs of logical and is true, do not evaluate rhs
+            if !Self::is_truthy(&left) {
+                return Ok(left);
+            }
+        }
+        // evaluate rhs only if the result wasn't enough to determine the truthiness
+        self.evaluate(expr.right.clone())
+    }
+
+    fn visit_set_expr(&self, base: Rc<Expr>, expr: &SetExpr) -> Result<Object, LoxResult> {
+        let object = self.evaluate(expr.object.clone())?;
+        if !matches!(object, Object::Instance(_) | Object::Class(_)) {
+            return Err(LoxResult::error_runtime(
+                &expr.name,
+                "Only instances and classes have fields",
+            ));
+        }
+        let value = self.evaluate(expr.value.clone())?;
+        let name = self.member_name(&base, &expr.name)?;
+        self.set_property(object, &name, value.clone())?;
+        Ok(value)
+    }
+
+    fn visit_setindex_expr(
+        &self,
+        _base: Rc<Expr>,
+        expr: &SetIndexExpr,
+    ) -> Result<Object, LoxResult> {
+        let object = self.evaluate(expr.object.clone())?;
+        let index = self.evaluate(expr.index.clone())?;
+        let value = self.evaluate(expr.value.clone())?;
+        self.set_index(object, index, value.clone(), &expr.bracket)?;
+        Ok(value)
+    }
+
+    fn visit_range_expr(&self, _base: Rc<Expr>, expr: &RangeExpr) -> Result<Object, LoxResult> {
+        let start = self.evaluate(expr.start.clone())?;
+        let end = self.evaluate(expr.end.clone())?;
+        let step = match expr.step.clone() {
+            Some(step) => self.evaluate(step)?,
+            None => Object::Int(1),
+        };
+        let (start, end, step) = match (start, end, step) {
+            (Object::Int(start), Object::Int(end), Object::Int(step)) => (start, end, step),
+            _ => {
+                return Err(LoxResult::error_runtime(
+                    &expr.operator,
+                    "The bounds and step of a range must be integers",
+                ))
+            }
+        };
+        let range = if expr.operator.ttype == TokenType::DotDotEqual {
+            LoxRange::inclusive(start, end, step)
+        } else {
+            LoxRange::new(start, end, step)
+        };
+        match range {
+            Some(range) => Ok(Object::Range(Rc::new(range))),
+            None => Err(LoxResult::error_runtime(
+                &expr.operator,
+                "The step of a range can't be zero",
+            )),
+        }
+    }
+
+    fn visit_tuple_expr(&self, _base: Rc<Expr>, expr: &TupleExpr) -> Result<Object, LoxResult> {
+        let elements = self.evaluate_spread(&expr.elements)?;
+        Ok(Object::Tuple(Rc::new(LoxTuple::new(elements))))
+    }
+
+    fn visit_spread_expr(&self, _base: Rc<Expr>, expr: &SpreadExpr) -> Result<Object, LoxResult> {
+        Err(LoxResult::error_runtime(
+            &expr.ellipsis,
+            "Can only spread arguments and list elements",
+        ))
+    }
+
+    fn visit_super_expr(&self, base: Rc<Expr>, expr: &SuperExpr) -> Result<Object, LoxResult> {
+        let distance = *self.locals.borrow().get(&base).unwrap();
+        let superclass = if let Ok(Object::Class(superclass)) =
+            self.environment.borrow().borrow().get_at(distance, "super")
+        {
+            superclass
+        } else {
+            return Err(LoxResult::system_error("Failed to extract superclass"));
+        };
+        // The environment where 'this' is bound is always right inside the
+        // environment where 'super' is stored. Offsetting the distance by one
+        // looks up 'this' in that inner environment.
+        let object = self
+            .environment
+            .borrow()
+            .borrow()
+            .get_at(distance - 1, "this")
+            .unwrap();
+
+        // Inside a static method 'this' is the class, so look for a static method
+        let method = if let Object::Class(_) = object {
+            superclass.find_static_method(expr.method.lexeme.clone())
+        } else {
+            superclass.find_method(expr.method.lexeme.clone())
+        };
+        if let Some(method) = method {
+            if let Object::Func(func) = method {
+                self.get_property(func.bind(&object))
+            } else {
+                Err(LoxResult::system_error(&format!(
+                    "Failed to bind object - method '{}' not a function",
+                    expr.method.lexeme
+                )))
+            }
+        } else {
+            Err(LoxResult::error_runtime(
+                &expr.method,
+                &format!("Undefined property '{}'", expr.method.lexeme),
+            ))
+        }
+    }
+
+    fn visit_this_expr(&self, base: Rc<Expr>, expr: &ThisExpr) -> Result<Object, LoxResult> {
+        self.lookup_variable(&expr.keyword, base)
+    }
+
+    // unary expressions have a single subexpression must be evaluated first
+    // Then apply the unary operator itself to the result. Here, the minus ('-')
+    // operator negates the subexpression, whereas the Bang ('!') operator
+    // inverts the truth value.
+    fn visit_unary_expr(&self, _: Rc<Expr>, expr: &UnaryExpr) -> Result<Object, LoxResult> {
+        let right = self.evaluate(expr.right.clone())?;
+        match expr.operator.ttype {
+            TokenType::Minus => {
+                if let Object::Instance(inst) = &right {
+                    self.call_operator(inst, "__neg__", Vec::new(), &expr.operator)
+                } else if let Some(n) = numeric::negate(&right) {
+                    Ok(n)
+                } else {
+                    Err(LoxResult::error_at_token(
+                        &expr.operator,
+                        &format!("Negation operation is not allowed on '{}'", right),
+                    ))
+                }
+            }
+            TokenType::Bang => Ok(Object::Bool(!Self::is_truthy(&right))),
+            _ => Err(LoxResult::error_at_token(&expr.operator, "Unreachable")),
+        }
+    }
+
+    fn visit_variable_expr(
+        &self,
+        base: Rc<Expr>,
+        expr: &VariableExpr,
+    ) -> Result<Object, LoxResult> {
+        self.lookup_variable(&expr.name, base)
+    }
+
+    // Generators run their 'yield' expressions themselves (see 'lox_generator'),
+    // so this one is in the function run by a fiber
+    fn visit_yield_expr(&self, _base: Rc<Expr>, expr: &YieldExpr) -> Result<Object, LoxResult> {
+        let value = match &expr.value {
+            Some(value) => self.evaluate(value.clone())?,
+            None => Object::Nil,
+        };
+        self.suspend_fiber(value).ok_or_else(|| {
+            LoxResult::error_runtime(&expr.keyword, "Can't yield outside of a generator")
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use crate::resolver::Resolver;
+    use crate::scanner::Scanner;
+    // helpers
+    fn make_literal(o: Object) -> Rc<Expr> {
+        Rc::new(Expr::Literal(Rc::new(LiteralExpr { value: Some(o) })))
+    }
+    fn make_token(ttype: TokenType, lexeme: &str) -> Token {
+        Token::new(ttype, lexeme.to_string(), None, 1, 0)
+    }
+    // Run a script end to end so that tests can inspect the globals it defined
+    fn run(source: &str) -> Result<Interpreter, LoxResult> {
+        let interpreter = Interpreter::new();
+        interpreter.event_loop.use_virtual_clock();
+        let tokens = Scanner::new(source).scan_tokens()?;
+        let mut parser = Parser::new(tokens);
+        let statements = Rc::new(parser.parse()?);
+        if !parser.success() {
+            return Err(LoxResult::SystemError {
+                message: "parse failed".to_string(),
+            });
+        }
+        let resolver = Resolver::new(&interpreter);
+        resolver.resolve(&statements)?;
+        if !resolver.success() {
+            return Err(LoxResult::SystemError {
+                message: "resolve failed".to_string(),
+            });
+        }
+        interpreter.interpret(&statements)?;
+        interpreter.event_loop.run(&interpreter)?;
+        Ok(interpreter)
+    }
+    fn global(interpreter: &Interpreter, name: &str) -> Object {
+        interpreter
+            .globals
+            .borrow()
+            .get(&make_token(TokenType::Identifier, name))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_unary_minus() {
+        let interpreter = Interpreter::new();
+        let unary_expr = UnaryExpr {
+            operator: make_token(TokenType::Minus, "-"),
+            right: make_literal(Object::Number(123.)),
+        };
+        let result = interpreter.visit_unary_expr(
+            Rc::new(Expr::Unary(Rc::new(unary_expr.clone()))),
+            &unary_expr,
+        );
+        assert!(result.is_ok());
+        assert_eq!(result.ok(), Some(Object::Number(-123.0)));
+    }
+
+    #[test]
+    fn test_unary_not() {
+        let interpreter = Interpreter::new();
+        let unary_expr = UnaryExpr {
+            operator: make_token(TokenType::Bang, "!"),
+            right: make_literal(Object::Bool(false)),
+        };
+        let result = interpreter.visit_unary_expr(
+            Rc::new(Expr::Unary(Rc::new(unary_expr.clone()))),
+            &unary_expr
+        );
+        assert!(result.is_ok());
+        assert_eq!(result.ok(), Some(Object::Bool(true)));
+    }
+
+    #[test]
+    fn test_binary_sub() {
+        let interpreter = Interpreter::new();
+        let binary_expr = BinaryExpr {
+            left: make_literal(Object::Number(321.)),
+            operator: make_token(TokenType::Minus, "-"),
+            right: make_literal(Object::Number(123.)),
+        };
+        let result = interpreter.visit_binary_expr(
+            Rc::new(Expr::Binary(Rc::new(binary_expr.clone()))),
+            &binary_expr
+        );
+        assert!(result.is_ok());
+        assert_eq!(result.ok(), Some(Object::Number(198.)));
+    }
+
+    #[test]
+    fn test_binary_div() {
+        let interpreter = Interpreter::new();
+        let binary_expr = BinaryExpr {
+            left: make_literal(Object::Number(100.)),
+            operator: make_token(TokenType::Slash, "/"),
+            right: make_literal(Object::Number(10.)),
+        };
+        let result = interpreter.visit_binary_expr(
+            Rc::new(Expr::Binary(Rc::new(binary_expr.clone()))),
+            &binary_expr
+        );
+        assert!(result.is_ok());
+        assert_eq!(result.ok(), Some(Object::Number(10.)));
+    }
+
+    #[test]
+    fn test_binary_mul() {
+        let interpreter = Interpreter::new();
+        let binary_expr = BinaryExpr {
+            left: make_literal(Object::Number(100.)),
+            operator: make_token(TokenType::Star, "*"),
+            right: make_literal(Object::Number(10.)),
+        };
+        let result = interpreter.visit_binary_expr(
+            Rc::new(Expr::Binary(Rc::new(binary_expr.clone()))),
+            &binary_expr
+        );
+        assert!(result.is_ok());
+        assert_eq!(result.ok(), Some(Object::Number(1000.)));
+    }
+
+    #[test]
+    fn test_binary_add() {
+        let interpreter = Interpreter::new();
+        let binary_expr = BinaryExpr {
+            left: make_literal(Object::Number(100.)),
+            operator: make_token(TokenType::Plus, "+"),
+            right: make_literal(Object::Number(10.)),
+        };
+        let result = interpreter.visit_binary_expr(
+            Rc::new(Expr::Binary(Rc::new(binary_expr.clone()))),
+            &binary_expr
+        );
+        assert!(result.is_ok());
+        assert_eq!(result.ok(), Some(Object::Number(110.)));
+    }
+
+    #[test]
+    fn test_binary_int_overflow() {
+        let interpreter = Interpreter::new();
+        let binary_expr = BinaryExpr {
+            left: make_literal(Object::Int(i64::MAX)),
+            operator: make_token(TokenType::Star, "*"),
+            right: make_literal(Object::Int(4)),
+        };
+        let result = interpreter.visit_binary_expr(
+            Rc::new(Expr::Binary(Rc::new(binary_expr.clone()))),
+            &binary_expr
+        );
+        assert_eq!(
+            result.ok().map(|r| r.to_string()),
+            Some("36893488147419103228".to_string())
+        );
+    }
+
+    #[test]
+    fn test_binary_eq_int_number() {
+        let interpreter = Interpreter::new();
+        let binary_expr = BinaryExpr {
+            left: make_literal(Object::Int(15)),
+            operator: make_token(TokenType::EqualEqual, "=="),
+            right: make_literal(Object::Number(15.)),
+        };
+        let result = interpreter.visit_binary_expr(
+            Rc::new(Expr::Binary(Rc::new(binary_expr.clone()))),
+            &binary_expr
+        );
+        assert_eq!(result.ok(), Some(Object::Bool(true)));
+    }
+
+    #[test]
+    fn test_binary_concat() {
+        let interpreter = Interpreter::new();
+        let binary_expr = BinaryExpr {
+            left: make_literal(Object::Str("Hello, ".to_string())),
+            operator: make_token(TokenType::Plus, "+"),
+            right: make_literal(Object::Str("World!".to_string())),
+        };
+        let result = interpreter.visit_binary_expr(
+            Rc::new(Expr::Binary(Rc::new(binary_expr.clone()))),
+            &binary_expr
+        );
+        assert!(result.is_ok());
+        assert_eq!(result.ok(), Some(Object::Str("Hello, World!".to_string())));
+    }
+
+    #[test]
+    fn test_binary_illegal() {
+        let interpreter = Interpreter::new();
+        let binary_expr = BinaryExpr {
+            left: make_literal(Object::Number(321.)),
+            operator: make_token(TokenType::Minus, "-"),
+            right: make_literal(Object::Bool(true)),
+        };
+        let result = interpreter.visit_binary_expr(
+            Rc::new(Expr::Binary(Rc::new(binary_expr.clone()))),
+            &binary_expr
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_binary_eq_nil() {
+        let interpreter = Interpreter::new();
+        let binary_expr = BinaryExpr {
+            left: make_literal(Object::Nil),
+            operator: make_token(TokenType::EqualEqual, "=="),
+            right: make_literal(Object::Nil),
+        };
+        let result = interpreter.visit_binary_expr(
+            Rc::new(Expr::Binary(Rc::new(binary_expr.clone()))),
+            &binary_expr
+        );
+        assert!(result.is_ok());
+        assert_eq!(result.ok(), Some(Object::Bool(true)));
+    }
+
+    #[test]
+    fn test_binary_eq_str() {
+        let interpreter = Interpreter::new();
+        let binary_expr = BinaryExpr {
+            left: make_literal(Object::Str("Hello".to_string())),
+            operator: make_token(TokenType::EqualEqual, "=="),
+            right: make_literal(Object::Str("Hello".to_string())),
+        };
+        let result = interpreter.visit_binary_expr(
+            Rc::new(Expr::Binary(Rc::new(binary_expr.clone()))),
+            &binary_expr
+        );
+        assert!(result.is_ok());
+        assert_eq!(result.ok(), Some(Object::Bool(true)));
+    }
+
+    #[test]
+    fn test_binary_ne_str() {
+        let interpreter = Interpreter::new();
+        let binary_expr = BinaryExpr {
+            left: make_literal(Object::Str("Hello".to_string())),
+            operator: make_token(TokenType::EqualEqual, "=="),
+            right: make_literal(Object::Str("World".to_string())),
+        };
+        let result = interpreter.visit_binary_expr(
+            Rc::new(Expr::Binary(Rc::new(binary_expr.clone()))),
+            &binary_expr
+        );
+        assert!(result.is_ok());
+        assert_eq!(result.ok(), Some(Object::Bool(false)));
+    }
+
+    // Use a helper to test '==' and '!=', '>', '>=', '<', and '<='
+    fn run_comparisons(tok: &Token, nums: Vec<f64>, value: f64, results: Vec<bool>) {
+        let interpreter = Interpreter::new();
+
+        for (num, r) in nums.iter().zip(results) {
+            let binary_expr = BinaryExpr {
+                left: make_literal(Object::Number(*num)),
+                operator: tok.clone(),
+                right: make_literal(Object::Number(value)),
+            };
+            let result = interpreter.visit_binary_expr(
+                Rc::new(Expr::Binary(Rc::new(binary_expr.clone()))),
+                &binary_expr
+            );
+            assert!(result.is_ok());
+            assert_eq!(
+                result.ok(),
+                Some(Object::Bool(r)),
+                "Testing {} {} {}",
+                num,
+                tok.lexeme,
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn test_binary_eq() {
+        let numbers = vec![14., 15., 16.];
+        let results = vec![false, true, false];
+        run_comparisons(
+            &make_token(TokenType::EqualEqual, "=="),
+            numbers,
+            15.,
+            results,
+        );
+    }
+
+    #[test]
+    fn test_binary_ne() {
+        let numbers = vec![14., 15., 16.];
+        let results = vec![true, false, true];
+        run_comparisons(
+            &make_token(TokenType::BangEqual, "!="),
+            numbers,
+            15.,
+            results,
+        );
+    }
+
+    #[test]
+    fn test_binary_gt() {
+        let numbers = vec![14., 15., 16.];
+        let results = vec![false, false, true];
+        run_comparisons(&make_token(TokenType::Greater, ">"), numbers, 15., results);
+    }
+
+    #[test]
+    fn test_binary_ge() {
+        let numbers = vec![14., 15., 16.];
+        let results = vec![false, true, true];
+        run_comparisons(
+            &make_token(TokenType::GreaterEqual, ">="),
+            numbers,
+            15.,
+            results,
+        );
+    }
+
+    #[test]
+    fn test_binary_lt() {
+        let numbers = vec![14., 15., 16.];
+        let results = vec![true, false, false];
+        run_comparisons(&make_token(TokenType::Less, "<"), numbers, 15., results);
+    }
+
+    #[test]
+    fn test_binary_le() {
+        let numbers = vec![14., 15., 16.];
+        let results = vec![true, true, false];
+        run_comparisons(
+            &make_token(TokenType::LessEqual, "<="),
+            numbers,
+            15.,
+            results,
+        );
+    }
+
+    #[test]
+    fn test_nested_expr() {
+        //-123 * (45.67) = -5617.41
+        let binary_expr = BinaryExpr {
+            left: Rc::new(Expr::Unary({
+                Rc::new(UnaryExpr {
+                    operator: make_token(TokenType::Minus, "-"),
+                    right: make_literal(Object::Number(123.)),
+                })
+            })),
+            operator: make_token(TokenType::Star, "*"),
+            right: make_literal(Object::Number(45.67)),
+        };
+        let interpreter = Interpreter::new();
+        let result = interpreter.visit_binary_expr(
+            Rc::new(Expr::Binary(Rc::new(binary_expr.clone()))),
+            &binary_expr
+        );
+        assert!(result.is_ok());
+        assert_eq!(result.ok(), Some(Object::Number(-5617.41)));
+    }
+
+    #[test]
+    fn test_var_stmt() {
+        let token = Token::new(TokenType::Identifier, "var_num".to_string(), None, 1, 1);
+        let var_stmt = VarStmt {
+            name: token.clone(),
+            kind: VarKind::Var,
+            initializer: None,
+        };
+        let interpreter = Interpreter::new();
+        assert!(interpreter
+            .visit_var_stmt(Rc::new(Stmt::Var(Rc::new(var_stmt.clone()))), &var_stmt)
+            .is_ok());
+        assert_eq!(
+            interpreter
+                .environment
+                .borrow()
+                .borrow()
+                .get(&token)
+                .unwrap(),
+            Object::Nil
+        );
+    }
+
+    #[test]
+    fn test_var_stmt_initialized() {
+        let token = Token::new(TokenType::Identifier, "var_num".to_string(), None, 1, 1);
+        let var_stmt = VarStmt {
+            name: token.clone(),
+            kind: VarKind::Var,
+            initializer: Some(make_literal(Object::Number(123.))),
+        };
+        let interpreter = Interpreter::new();
+        assert!(interpreter
+            .visit_var_stmt(
+                Rc::new(Stmt::Var(Rc::new(var_stmt.clone()))),
+                &var_stmt
+            ).is_ok());
+        assert_eq!(
+            interpreter
+                .environment
+                .borrow()
+                .borrow()
+                .get(&token)
+                .unwrap(),
+            Object::Number(123.)
+        );
+    }
+
+    #[test]
+    fn test_variable_expression() {
+        // First define a variable
+        let token = Token::new(TokenType::Identifier, "var_num".to_string(), None, 1, 1);
+        let var_stmt = VarStmt {
+            name: token.clone(),
+            kind: VarKind::Var,
+            initializer: Some(make_literal(Object::Number(123.))),
+        };
+        let interpreter = Interpreter::new();
+        assert!(interpreter
+            .visit_var_stmt(
+                Rc::new(Stmt::Var(Rc::new(var_stmt.clone()))),
+                &var_stmt
+            ).is_ok());
+
+        // Now use the defined variable in an expression
+        let var_expr = VariableExpr {
+            name: token.clone(),
+        };
+        assert!(interpreter
+            .visit_variable_expr(
+                Rc::new(Expr::Variable(Rc::new(var_expr.clone()))),
+                &var_expr
+            ).is_ok());
+        assert_eq!(
+            interpreter
+                .visit_variable_expr(
+                    Rc::new(Expr::Variable(Rc::new(var_expr.clone()))),
+                    &var_expr
+                ).unwrap(),
+            Object::Number(123.)
+        );
+    }
+    #[test]
+    fn test_variable_expression_undefined() {
+        let token = Token::new(TokenType::Identifier, "var_num".to_string(), None, 1, 1);
+        let interpreter = Interpreter::new();
+        // Try to use an undefined variable in an expression
+        let var_expr = VariableExpr {
+            name: token.clone(),
+        };
+        assert!(interpreter
+            .visit_variable_expr(
+                Rc::new(Expr::Variable(Rc::new(var_expr.clone()))),
+                &var_expr
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_static_methods() {
+        let interpreter = run("
+            class Math {
+                class square(n) { return n * n; }
+                static twice(n) { return this.square(n) * 2; }
+            }
+            class Sub < Math {}
+            var a = Math.square(3);
+            var b = Sub.twice(2);
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "a"), Object::Int(9));
+        assert_eq!(global(&interpreter, "b"), Object::Int(8));
+    }
+
+    #[test]
+    fn test_static_fields() {
+        let interpreter = run("
+            class Math {}
+            class Sub < Math {}
+            Math.unit = 1;
+            var c = Sub.unit;
+            Sub.unit = 2;
+            var d = Math.unit;
+            var e = Sub.unit;
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "c"), Object::Int(1));
+        assert_eq!(global(&interpreter, "d"), Object::Int(1));
+        assert_eq!(global(&interpreter, "e"), Object::Int(2));
+    }
+
+    #[test]
+    fn test_getters() {
+        let interpreter = run("
+            class Circle {
+                init(r) { this.r = r; }
+                diameter { return this.r * 2; }
+            }
+            var diameter = Circle(2).diameter;
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "diameter"), Object::Int(4));
+    }
+
+    #[test]
+    fn test_setters() {
+        let interpreter = run("
+            class Circle {
+                init(r) { this.r = r; }
+                diameter { return this.r * 2; }
+                diameter=(d) { this.r = d / 2; }
+            }
+            var c = Circle(2);
+            c.diameter = 10;
+            var radius = c.r;
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "radius"), Object::Int(5));
+    }
+
+    #[test]
+    fn test_setter_parameters() {
+        // Setters take exactly one parameter
+        assert!(run("class Bad { x=(a, b) {} }").is_err());
+    }
+
+    #[test]
+    fn test_arithmetic_operator_overloading() {
+        let interpreter = run("
+            class Vec {
+                init(x, y) { this.x = x; this.y = y; }
+                __add__(o) { return Vec(this.x + o.x, this.y + o.y); }
+            }
+            var v = Vec(1, 2) + Vec(3, 4);
+            var sum = v.x * 10 + v.y;
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "sum"), Object::Int(46));
+    }
+
+    #[test]
+    fn test_equality_operator_overloading() {
+        let interpreter = run("
+            class Vec {
+                init(x, y) { this.x = x; this.y = y; }
+                __eq__(o) { return this.x == o.x and this.y == o.y; }
+            }
+            var v = Vec(4, 6);
+            var same = v == Vec(4, 6);
+            var different = v != Vec(4, 6);
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "same"), Object::Bool(true));
+        assert_eq!(global(&interpreter, "different"), Object::Bool(false));
+    }
+
+    #[test]
+    fn test_unary_operator_overloading() {
+        let interpreter = run("
+            class Vec {
+                init(x, y) { this.x = x; this.y = y; }
+                __neg__() { return Vec(-this.x, -this.y); }
+            }
+            var y = (-Vec(4, 6)).y;
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "y"), Object::Int(-6));
+    }
+
+    #[test]
+    fn test_index_operator_overloading() {
+        let interpreter = run("
+            class Vec {
+                init(x, y) { this.x = x; this.y = y; }
+                __getitem__(i) { if (i == 0) return this.x; return this.y; }
+            }
+            var y = Vec(4, 6)[1];
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "y"), Object::Int(6));
+    }
+
+    #[test]
+    fn test_call_operator_overloading() {
+        let interpreter = run("
+            class Vec {
+                init(x, y) { this.x = x; this.y = y; }
+                __call__(k) { return this.x * k; }
+            }
+            var v = Vec(4, 6);
+            var scaled = v(3);
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "scaled"), Object::Int(12));
+    }
+
+    #[test]
+    fn test_operators_without_methods() {
+        // No '__mul__' method to fall back to
+        assert!(run("class A {} var a = A() * 2;").is_err());
+        let interpreter = run("class A {} var a = A(); var same = a == a and a != A();").unwrap();
+        assert_eq!(global(&interpreter, "same"), Object::Bool(true));
+    }
+
+    #[test]
+    fn test_reflected_operators() {
+        let interpreter = run("
+            class V {
+                init(x) { this.x = x; }
+                __mul__(k) { return V(this.x * k); }
+                __rmul__(k) { return V(k * this.x); }
+                __rsub__(k) { return k - this.x; }
+            }
+            var scaled = (2 * V(3)).x;
+            var difference = 10 - V(3);
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "scaled"), Object::Int(6));
+        assert_eq!(global(&interpreter, "difference"), Object::Int(7));
+        // No '__radd__' method to fall back to
+        assert!(run("class A {} var a = 2 + A();").is_err());
+    }
+
+    #[test]
+    fn test_reflected_comparisons() {
+        let interpreter = run("
+            class V {
+                init(x) { this.x = x; }
+                __lt__(o) { return this.x < o; }
+            }
+            var greater = 5 > V(3);
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "greater"), Object::Bool(true));
+    }
+
+    #[test]
+    fn test_symmetric_equality() {
+        let interpreter = run("
+            class E {
+                __eq__(o) { return o == 5; }
+            }
+            var equal = 5 == E();
+            var unequal = 5 != E();
+            class P {
+                init(x) { this.x = x; }
+                equals(o) { return o == this.x; }
+            }
+            var equals = 1 == P(1);
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "equal"), Object::Bool(true));
+        assert_eq!(global(&interpreter, "unequal"), Object::Bool(false));
+        assert_eq!(global(&interpreter, "equals"), Object::Bool(true));
+    }
+
+    #[test]
+    fn test_default_string_form() {
+        let interpreter = run("
+            class Point { init(x, y) { this.y = y; this.x = x; } }
+            var p = Point(1, \"a\");
+            p.self = p;
+            var plain = str(p);
+        ")
+        .unwrap();
+        assert_eq!(
+            global(&interpreter, "plain"),
+            Object::Str("Point { self: Point {...}, x: 1, y: \"a\" }".to_string())
+        );
+    }
+
+    #[test]
+    fn test_to_string() {
+        let interpreter = run("
+            class Money {
+                init(n) { this.n = n; }
+                toString() { return \"$\" + this.n; }
+            }
+            var custom = \"cost: \" + Money(5);
+        ")
+        .unwrap();
+        assert_eq!(
+            global(&interpreter, "custom"),
+            Object::Str("cost: $5".to_string())
+        );
+    }
+
+    #[test]
+    fn test_instance_identity() {
+        let interpreter = run("
+            class P { init(x) { this.x = x; } }
+            var a = P(1);
+            a.self = a;
+            var same = a == a;
+            var distinct = a == P(1);
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "same"), Object::Bool(true));
+        assert_eq!(global(&interpreter, "distinct"), Object::Bool(false));
+    }
+
+    #[test]
+    fn test_custom_equality() {
+        let interpreter = run("
+            class Q {
+                init(x) { this.x = x; }
+                equals(o) { return this.x == o.x; }
+            }
+            var custom = Q(1) == Q(1);
+            var negated = Q(1) != Q(2);
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "custom"), Object::Bool(true));
+        assert_eq!(global(&interpreter, "negated"), Object::Bool(true));
+    }
+
+    #[test]
+    fn test_class_identity() {
+        let interpreter = run("
+            class P {}
+            class Q {}
+            var classes = P == P and P != Q;
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "classes"), Object::Bool(true));
+    }
+
+    #[test]
+    fn test_equality_across_types() {
+        let interpreter = run("
+            class P {}
+            var a = P();
+            var mixed = a == nil or nil == a or 1 == \"1\";
+            var numbers = 1 == 1.0;
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "mixed"), Object::Bool(false));
+        assert_eq!(global(&interpreter, "numbers"), Object::Bool(true));
+    }
+
+    #[test]
+    fn test_list_literals() {
+        let interpreter = run("
+            var xs = [1, \"two\", [3]];
+            var shown = str(xs);
+            var size = len(xs) * 10 + len(\"four\");
+        ")
+        .unwrap();
+        assert_eq!(
+            global(&interpreter, "shown"),
+            Object::Str("[1, \"two\", [3]]".to_string())
+        );
+        assert_eq!(global(&interpreter, "size"), Object::Int(34));
+    }
+
+    #[test]
+    fn test_list_indexing() {
+        let interpreter = run("
+            var xs = [1, \"two\", [3]];
+            xs[0] = xs[0] + 10;
+            var first = xs[0];
+            var nested = xs[2][0];
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "first"), Object::Int(11));
+        assert_eq!(global(&interpreter, "nested"), Object::Int(3));
+        assert!(run("var xs = [1]; xs[1] = 2;").is_err());
+        assert!(run("var xs = [1]; var x = xs[-1];").is_err());
+    }
+
+    #[test]
+    fn test_list_identity() {
+        let interpreter = run("
+            var xs = [11, \"two\", [3]];
+            var same = xs == xs and xs != [11, \"two\", [3]];
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "same"), Object::Bool(true));
+    }
+
+    #[test]
+    fn test_type_native() {
+        let interpreter = run("
+            class B {}
+            var b = B();
+            var types = type(1) + \" \" + type(1.5) + \" \" + type(\"s\") + \" \" + type(nil)
+                + \" \" + type(b) + \" \" + type(B) + \" \" + type(clock) + \" \" + type([]);
+        ")
+        .unwrap();
+        assert_eq!(
+            global(&interpreter, "types"),
+            Object::Str("number number string nil instance class function list".to_string())
+        );
+    }
+
+    #[test]
+    fn test_class_reflection() {
+        let interpreter = run("
+            class A { init(x) { this.x = x; } m() {} get { return 1; } }
+            class B < A { n(a, b) {} }
+            var b = B(1);
+            var parent = superclass(B) == A and superclass(A) == nil;
+            var isA = isInstance(b, A) and !isInstance(A(1), B) and !isInstance(1, A);
+            var klass = classOf(b) == B;
+            var methodList = str(methods(B));
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "parent"), Object::Bool(true));
+        assert_eq!(global(&interpreter, "isA"), Object::Bool(true));
+        assert_eq!(global(&interpreter, "klass"), Object::Bool(true));
+        assert_eq!(
+            global(&interpreter, "methodList"),
+            Object::Str("[\"get\", \"init\", \"m\", \"n\"]".to_string())
+        );
+    }
+
+    #[test]
+    fn test_field_reflection() {
+        let interpreter = run("
+            class A { init(x) { this.x = x; } m() {} }
+            var a = A(1);
+            setField(a, \"y\", \"two\");
+            var fieldList = str(fields(a));
+            var has = hasField(a, \"x\") and !hasField(a, \"m\");
+            var y = getField(a, \"y\");
+        ")
+        .unwrap();
+        assert_eq!(
+            global(&interpreter, "fieldList"),
+            Object::Str("[\"x\", \"y\"]".to_string())
+        );
+        assert_eq!(global(&interpreter, "has"), Object::Bool(true));
+        assert_eq!(global(&interpreter, "y"), Object::Str("two".to_string()));
+        assert!(run("getField(1, \"x\");").is_err());
+    }
+
+    #[test]
+    fn test_arity_native() {
+        let interpreter = run("
+            class A { init(x) {} }
+            fun n(a, b) {}
+            var arities = arity(A) * 100 + arity(n) * 10 + arity(clock);
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "arities"), Object::Int(120));
+    }
+
+    #[test]
+    fn test_for_in_builtins() {
+        let interpreter = run("
+            var sum = 0;
+            for (var i in range(0, 5)) sum = sum + i;
+            var chars = \"\";
+            for (var c in \"abc\") chars = c + chars;
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "sum"), Object::Int(10));
+        assert_eq!(
+            global(&interpreter, "chars"),
+            Object::Str("cba".to_string())
+        );
+        assert!(run("for (var x in 1) print x;").is_err());
+    }
+
+    #[test]
+    fn test_for_in_iterator_protocol() {
+        let interpreter = run("
+            class Countdown {
+                init(n) { this.n = n; }
+                __hasnext__() { return this.n > 0; }
+                __next__() { this.n = this.n - 1; return this.n + 1; }
+            }
+            class Bag {
+                init() { this.items = [\"x\", \"y\"]; }
+                __iter__() { return this.items; }
+            }
+            var counted = \"\";
+            for (var n in Countdown(3)) counted = counted + n;
+            var bag = \"\";
+            for (var item in Bag()) bag = bag + item;
+        ")
+        .unwrap();
+        assert_eq!(
+            global(&interpreter, "counted"),
+            Object::Str("321".to_string())
+        );
+        assert_eq!(global(&interpreter, "bag"), Object::Str("xy".to_string()));
+        assert!(run("class A {} for (var x in A()) print x;").is_err());
+    }
+
+    #[test]
+    fn test_for_in_break() {
+        let interpreter = run("
+            var found = nil;
+            for (var x in [1, 2, 3, 4]) {
+                if (x * x > 5) { found = x; break; }
+            }
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "found"), Object::Int(3));
+    }
+
+    #[test]
+    fn test_for_in_fresh_variable() {
+        let interpreter = run("
+            var list = [nil, nil];
+            for (var i in range(0, 2)) {
+                fun f() { return i; }
+                list[i] = f;
+            }
+            var captured = list[0]() + list[1]();
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "captured"), Object::Int(1));
+    }
+
+    #[test]
+    fn test_generators() {
+        let interpreter = run("
+            fun count(n) {
+                var i = 0;
+                while (i < n) {
+                    yield i;
+                    i = i + 1;
+                }
+            }
+            var sum = 0;
+            for (var x in count(5)) sum = sum + x;
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "sum"), Object::Int(10));
+    }
+
+    #[test]
+    fn test_generators_are_lazy() {
+        let interpreter = run("
+            var log = \"\";
+            fun lazy() {
+                log = log + \"a\";
+                yield 1;
+                log = log + \"b\";
+                for (var c in \"xy\") {
+                    if (c == \"y\") { yield c; break; }
+                }
+                log = log + \"c\";
+            }
+            var g = lazy();
+            var before = log;
+            var first = g.next();
+            var middle = log;
+            var second = g.next();
+            var has = g.hasNext();
+            var last = g.next();
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "before"), Object::Str("".to_string()));
+        assert_eq!(global(&interpreter, "first"), Object::Int(1));
+        assert_eq!(global(&interpreter, "middle"), Object::Str("a".to_string()));
+        assert_eq!(global(&interpreter, "second"), Object::Str("y".to_string()));
+        assert_eq!(global(&interpreter, "has"), Object::Bool(false));
+        assert_eq!(global(&interpreter, "last"), Object::Nil);
+        assert_eq!(global(&interpreter, "log"), Object::Str("abc".to_string()));
+    }
+
+    #[test]
+    fn test_generator_send() {
+        let interpreter = run("
+            fun echo() {
+                var total = 0;
+                while (true) {
+                    var x = yield total;
+                    if (x == nil) return;
+                    total = total + x;
+                }
+            }
+            var e = echo();
+            e.next();
+            e.send(2);
+            var echoed = e.send(3);
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "echoed"), Object::Int(5));
+    }
+
+    #[test]
+    fn test_misplaced_yield() {
+        assert!(run("yield 1;").is_err());
+        assert!(run("class A { init() { yield 1; } }").is_err());
+        assert!(run("fun f() { print yield 1; }").is_err());
+    }
+
+    #[test]
+    fn test_fibers() {
+        let interpreter = run("
+            var log = \"\";
+            fun worker(name) {
+                var i = 0;
+                while (i < 2) {
+                    log = log + name + i;
+                    i = i + 1;
+                    yield i;
+                }
+            }
+            var a = Fiber(worker);
+            var b = Fiber(worker);
+            a.resume(\"a\");
+            b.resume(\"b\");
+            a.resume(nil);
+            b.resume(nil);
+        ")
+        .unwrap();
+        assert_eq!(
+            global(&interpreter, "log"),
+            Object::Str("a0b0a1b1".to_string())
+        );
+    }
+
+    #[test]
+    fn test_fiber_results() {
+        let interpreter = run("
+            fun worker(name) {
+                yield 1;
+                return name + \" done\";
+            }
+            var a = Fiber(worker);
+            a.resume(\"a\");
+            var waiting = a.isDone();
+            var result = a.resume(nil);
+            var finished = a.isDone();
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "waiting"), Object::Bool(false));
+        assert_eq!(
+            global(&interpreter, "result"),
+            Object::Str("a done".to_string())
+        );
+        assert_eq!(global(&interpreter, "finished"), Object::Bool(true));
+    }
+
+    #[test]
+    fn test_fiber_resume_values() {
+        let interpreter = run("
+            fun adder() {
+                var total = 0;
+                while (true) {
+                    var x = yield total;
+                    total = total + x;
+                }
+            }
+            var sum = Fiber(adder);
+            sum.resume(nil);
+            sum.resume(1);
+            var total = sum.resume(2);
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "total"), Object::Int(3));
+    }
+
+    #[test]
+    fn test_fiber_errors() {
+        // Errors propagate to the resumer and finish the fiber
+        assert!(
+            run("fun f() { yield 1; nil.x; } var f = Fiber(f); f.resume(nil); f.resume(nil);")
+                .is_err()
+        );
+        assert!(run("fun f() {} var f = Fiber(f); f.resume(nil); f.resume(nil);").is_err());
+        assert!(run("var f = Fiber(clock);").is_err());
+        assert!(run("suspend(1);").is_err());
+        assert!(
+            run("var f; fun again() { f.resume(nil); } f = Fiber(again); f.resume(nil);").is_err()
+        );
+    }
+
+    #[test]
+    fn test_fibers_suspend_in_called_functions() {
+        let interpreter = run("
+            fun inner() { var v = suspend(1); return v + 5; }
+            fun body() {
+                var x = inner();
+                yield type(x) + str(x);
+                return 1 + suspend(3);
+            }
+            var f = Fiber(body);
+            var first = f.resume(nil);
+            var second = f.resume(10);
+            var third = f.resume(nil);
+            var last = f.resume(4);
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "first"), Object::Int(1));
+        assert_eq!(
+            global(&interpreter, "second"),
+            Object::Str("number15".to_string())
+        );
+        assert_eq!(global(&interpreter, "third"), Object::Int(3));
+        assert_eq!(global(&interpreter, "last"), Object::Int(5));
+    }
+
+    #[test]
+    fn test_fibers_keep_generators() {
+        let interpreter = run("
+            fun count(n) { var i = 0; while (i < n) { yield i; i = i + 1; } }
+            fun body() {
+                var total = 0;
+                for (var x in count(3)) { total = total + suspend(x); }
+                return total;
+            }
+            var f = Fiber(body);
+            f.resume(nil);
+            f.resume(10);
+            f.resume(20);
+            var total = f.resume(30);
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "total"), Object::Int(60));
+    }
+
+    #[test]
+    fn test_fiber_stack_overflow() {
+        // Fails with an error instead of running into the fiber's guard page
+        let result = run("
+            fun down(n) { return down(n + 1); }
+            fun body() { down(0); }
+            Fiber(body).resume(nil);
+        ");
+        match result {
+            Err(LoxResult::RuntimeError { message, .. }) => {
+                assert_eq!(message, "Stack overflow in fiber 'body'")
+            }
+            _ => panic!("expected a stack overflow error"),
+        }
+    }
+
+    #[test]
+    fn test_timers_and_deferred_callbacks() {
+        let interpreter = run("
+            var log = \"\";
+            fun note(s) { log = log + s + \"@\" + clock() + \" \"; }
+            fun late() { note(\"late\"); }
+            fun early() { note(\"early\"); defer(fun_micro); }
+            fun fun_micro() { note(\"micro\"); }
+            setTimeout(late, 100);
+            setTimeout(early, 10);
+            defer(fun_micro);
+            note(\"script\");
+        ")
+        .unwrap();
+        assert_eq!(
+            global(&interpreter, "log"),
+            Object::Str("script@0 micro@0 early@10 micro@10 late@100 ".to_string())
+        );
+    }
+
+    #[test]
+    fn test_intervals() {
+        let interpreter = run("
+            var log = \"\";
+            var ticks = 0;
+            var interval;
+            fun tick() {
+                ticks = ticks + 1;
+                log = log + \"tick@\" + clock() + \" \";
+                if (ticks == 3) clearInterval(interval);
+            }
+            interval = setInterval(tick, 30);
+        ")
+        .unwrap();
+        assert_eq!(
+            global(&interpreter, "log"),
+            Object::Str("tick@30 tick@60 tick@90 ".to_string())
+        );
+    }
+
+    #[test]
+    fn test_cleared_timeouts() {
+        let interpreter = run("
+            var log = \"\";
+            fun cancelled() { log = \"cancelled\"; }
+            clearTimeout(setTimeout(cancelled, 5));
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "log"), Object::Str("".to_string()));
+    }
+
+    #[test]
+    fn test_event_loop_callbacks() {
+        assert!(run("setTimeout(clock, \"soon\");").is_err());
+        assert!(run("fun f(x) {} defer(f);").is_err());
+    }
+
+    #[test]
+    fn test_match_literals() {
+        let interpreter = run("
+            fun describe(v) {
+                match (v) {
+                    case 1, 2 => return \"small\";
+                    case -1 => return \"negative\";
+                    case \"x\", nil => return \"x or nil\";
+                    default => return \"other\";
+                }
+            }
+            var a = describe(2);
+            var b = describe(-1);
+            var c = describe(nil);
+            var d = describe(true);
+        ")
+        .unwrap();
+        let expected = [
+            ("a", "small"),
+            ("b", "negative"),
+            ("c", "x or nil"),
+            ("d", "other"),
+        ];
+        for (name, value) in expected {
+            assert_eq!(global(&interpreter, name), Object::Str(value.to_string()));
+        }
+    }
+
+    #[test]
+    fn test_match_class_patterns() {
+        let interpreter = run("
+            class Point { init(x, y) { this.x = x; this.y = y; } }
+            class Point3 < Point { init(x, y, z) { super.init(x, y); this.z = z; } }
+            class Line { init(a, b) { this.a = a; this.b = b; } }
+            fun describe(v) {
+                match (v) {
+                    case Point(x: 0, y: 0) => return \"origin\";
+                    case Line(a: Point(x: ax), b: Point(x: bx)) => return \"line \" + ax + \" \" + bx;
+                    case Point(x) => return \"point \" + x;
+                    default => return \"other\";
+                }
+            }
+            var a = describe(Point(0, 0));
+            var b = describe(Line(Point(1, 2), Point(5, 6)));
+            var c = describe(Point(4, 5));
+            var d = describe(Point3(3, 3, 1));
+        ")
+        .unwrap();
+        let expected = [
+            ("a", "origin"),
+            ("b", "line 1 5"),
+            ("c", "point 4"),
+            ("d", "point 3"),
+        ];
+        for (name, value) in expected {
+            assert_eq!(global(&interpreter, name), Object::Str(value.to_string()));
+        }
+    }
+
+    #[test]
+    fn test_match_guards() {
+        let interpreter = run("
+            class Point { init(x, y) { this.x = x; this.y = y; } }
+            fun describe(v) {
+                match (v) {
+                    case Point(x, y) if x == y => return \"diagonal \" + x;
+                    case n if type(n) == \"number\" and n > 100 => return \"big \" + n;
+                    default => return \"other\";
+                }
+            }
+            var a = describe(Point(3, 3));
+            var b = describe(500);
+            var c = describe(Point(3, 4));
+            var d = describe(5);
+        ")
+        .unwrap();
+        let expected = [
+            ("a", "diagonal 3"),
+            ("b", "big 500"),
+            ("c", "other"),
+            ("d", "other"),
+        ];
+        for (name, value) in expected {
+            assert_eq!(global(&interpreter, name), Object::Str(value.to_string()));
+        }
+    }
+
+    #[test]
+    fn test_match_in_generators() {
+        let interpreter = run("
+            fun gen() {
+                for (var n = 0; n < 3; n = n + 1) {
+                    match (n) {
+                        case 1 => yield \"one\";
+                        case _ => yield n;
+                    }
+                }
+            }
+            var it = gen();
+            var j = \"\" + it.next() + it.next() + it.next();
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "j"), Object::Str("0one2".to_string()));
+    }
+
+    #[test]
+    fn test_invalid_matches() {
+        assert!(run("match (3) { case 1 => print 1; }").is_err());
+        assert!(run("match (3) { case 1 => print 1; case 2 => print 2; } ").is_err());
+        assert!(run("var v = 1; match (v) { case x, y => print 1; }").is_err());
+        assert!(run("var v = 1; match (v) { case v(x) => print x; }").is_err());
+    }
+
+    #[test]
+    fn test_const_let() {
+        let interpreter = run("
+            const limit = 3;
+            let total = 0;
+            for (let i = 0; i < limit; i = i + 1) total = total + i;
+            fun f() {
+                const k = 10;
+                let j = k + 1;
+                j = j + 1;
+                return j;
+            }
+            var twelve = f();
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "total"), Object::Int(3));
+        assert_eq!(global(&interpreter, "twelve"), Object::Int(12));
+    }
+
+    #[test]
+    fn test_const_assignment() {
+        assert!(run("const c;").is_err());
+        assert!(run("const c = 1; c = 2;").is_err());
+        assert!(run("fun f() { const c = 1; c = 2; }").is_err());
+        assert!(run("fun f() { const c = 1; fun g() { c = 2; } }").is_err());
+        assert!(run("fun f() { const c = 1; { var c = 2; c = 3; } }").is_ok());
+    }
+
+    #[test]
+    fn test_var_redeclaration() {
+        let interpreter = run("var x = 1; var x = 2;").unwrap();
+        assert_eq!(global(&interpreter, "x"), Object::Int(2));
+        assert!(run("fun f() {} fun f() {}").is_ok());
+    }
 
-        if let Some(method) = superclass.find_method(expr.method.lexeme.clone()) {
-            if let Object::Func(func) = method {
-                Ok(func.bind(&object))
-            } else {
-                Err(LoxResult::system_error(&format!(
-                    "Failed to bind object - method '{}' not a function",
-                    expr.method.lexeme
-                )))
+    #[test]
+    fn test_const_let_redeclaration() {
+        assert!(run("let a = 1; let a = 2;").is_err());
+        assert!(run("var a = 1; let a = 2;").is_err());
+        assert!(run("const a = 1; var a = 2;").is_err());
+        assert!(run("fun f() { let a = 1; var a = 2; }").is_err());
+    }
+
+    #[test]
+    fn test_const_let_declaration_conflicts() {
+        assert!(run("const X = 1; fun X() {}").is_err());
+        assert!(run("const X = 1; class X {}").is_err());
+        assert!(run("let T = 1; trait T {}").is_err());
+        assert!(run("const E = 1; enum E { A }").is_err());
+    }
+
+    #[test]
+    fn test_default_parameters() {
+        let interpreter = run("
+            fun f(a, b = 10, c = a + b) { return a * 100 + b * 10 + c; }
+            var one = f(1);
+            var two = f(1, 2);
+            var three = f(1, 2, 3);
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "one"), Object::Int(211));
+        assert_eq!(global(&interpreter, "two"), Object::Int(123));
+        assert_eq!(global(&interpreter, "three"), Object::Int(123));
+    }
+
+    #[test]
+    fn test_rest_parameters() {
+        let interpreter = run("
+            fun count(first, ...rest) { return len(rest); }
+            var none = count(1);
+            var many = count(1, 2, 3);
+            fun g(...all) { return all; }
+            var empty = len(g());
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "none"), Object::Int(0));
+        assert_eq!(global(&interpreter, "many"), Object::Int(2));
+        assert_eq!(global(&interpreter, "empty"), Object::Int(0));
+    }
+
+    #[test]
+    fn test_spread_arguments() {
+        let interpreter = run("
+            fun f(a, b = 10, c = a + b) { return a * 100 + b * 10 + c; }
+            fun count(first, ...rest) { return len(rest); }
+            var xs = [1, 2];
+            var spread = f(...xs);
+            var mixed = count(0, ...xs, ...range(0, 3), 9);
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "spread"), Object::Int(123));
+        assert_eq!(global(&interpreter, "mixed"), Object::Int(6));
+    }
+
+    #[test]
+    fn test_spread_in_list_literals() {
+        let interpreter = run("
+            var xs = [1, 2];
+            var copy = [0, ...xs, ...\"ab\"];
+            var copied = len(copy);
+            var last = copy[4];
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "copied"), Object::Int(5));
+        assert_eq!(global(&interpreter, "last"), Object::Str("b".to_string()));
+    }
+
+    #[test]
+    fn test_default_and_rest_reflection() {
+        let interpreter = run("
+            fun f(a, b = 10, c = a + b) { return a * 100 + b * 10 + c; }
+            fun count(first, ...rest) { return len(rest); }
+            var arities = arity(f) * 10 + arity(count);
+            var shown = str(count);
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "arities"), Object::Int(11));
+        assert_eq!(
+            global(&interpreter, "shown"),
+            Object::Str("<fun count(first, ...rest)>".to_string())
+        );
+    }
+
+    #[test]
+    fn test_invalid_default_rest_spread() {
+        assert!(run("fun f(a, b = 1) {} f();").is_err());
+        assert!(run("fun f(a, b = 1) {} f(1, 2, 3);").is_err());
+        assert!(run("fun f(a = 1, b) {}").is_err());
+        assert!(run("fun f(...a, b) {}").is_err());
+        assert!(run("fun f(a) {} f(...[1, 2]);").is_err());
+        assert!(run("var x = ...[1];").is_err());
+        assert!(run("fun f(a = b) {} f();").is_err());
+    }
+
+    #[test]
+    fn test_named_arguments() {
+        let interpreter = run("
+            fun f(a, b, c) { return a * 100 + b * 10 + c; }
+            var all = f(c: 3, a: 1, b: 2);
+            var mixed = f(1, c: 3, b: 2);
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "all"), Object::Int(123));
+        assert_eq!(global(&interpreter, "mixed"), Object::Int(123));
+    }
+
+    #[test]
+    fn test_named_arguments_with_defaults() {
+        let interpreter = run("
+            class Point {
+                init(x, y = 0, z = x + y) { this.x = x; this.y = y; this.z = z; }
+                scaled(by, offset = 0) { return this.x * by + offset; }
             }
-        } else {
-            Err(LoxResult::error_runtime(
-                &expr.method,
-                &format!("Undefined property '{}'", expr.method.lexeme),
-            ))
-        }
+            var p = Point(y: 2, x: 1);
+            var sum = p.x * 100 + p.y * 10 + p.z;
+            var q = Point(5, z: 9);
+            var qsum = q.x * 100 + q.y * 10 + q.z;
+            var scaled = p.scaled(offset: 1, by: 3);
+            fun tail(first, ...rest) { return len(rest); }
+            var rest = tail(first: 1);
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "sum"), Object::Int(123));
+        assert_eq!(global(&interpreter, "qsum"), Object::Int(509));
+        assert_eq!(global(&interpreter, "scaled"), Object::Int(4));
+        assert_eq!(global(&interpreter, "rest"), Object::Int(0));
     }
 
-    fn visit_this_expr(&self, base: Rc<Expr>, expr: &ThisExpr) -> Result<Object, LoxResult> {
-        self.lookup_variable(&expr.keyword, base)
+    #[test]
+    fn test_named_arguments_to_natives() {
+        let interpreter = run("
+            class Point {}
+            var r = len(range(end: 5, start: 2));
+            var ok = isInstance(klass: Point, value: Point());
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "r"), Object::Int(3));
+        assert_eq!(global(&interpreter, "ok"), Object::Bool(true));
     }
 
-    // unary expressions have a single subexpression must be evaluated first
-    // Then apply the unary operator itself to the result. Here, the minus ('-')
-    // operator negates the subexpression, whereas the Bang ('!') operator
-    // inverts the truth value.
-    fn visit_unary_expr(&self, _: Rc<Expr>, expr: &UnaryExpr) -> Result<Object, LoxResult> {
-        let right = self.evaluate(expr.right.clone())?;
-        match expr.operator.ttype {
-            TokenType::Minus => {
-                if let Object::Number(n) = right {
-                    Ok(Object::Number(-n))
-                } else {
-                    Err(LoxResult::error_at_token(
-                        &expr.operator,
-                        &format!("Negation operation is not allowed on '{}'", right),
-                    ))
+    #[test]
+    fn test_invalid_named_arguments() {
+        assert!(run("fun f(a, b) {} f(a: 1, c: 2);").is_err());
+        assert!(run("fun f(a, b) {} f(1, a: 2);").is_err());
+        assert!(run("fun f(a, b) {} f(b: 1, b: 2);").is_err());
+        assert!(run("fun f(a, b) {} f(b: 1);").is_err());
+        assert!(run("fun f(a, b) {} f(a: 1, 2);").is_err());
+        assert!(run("clock(x: 1);").is_err());
+    }
+
+    #[test]
+    fn test_initializer_defaults() {
+        let interpreter = run("
+            class C {
+                init(a, b = this.base()) { this.sum = a + b; }
+                base() { return 42; }
+            }
+            fun outer() {
+                var k = 7;
+                class D {
+                    init(a, b = k) { this.sum = a + b; }
                 }
+                return D;
             }
-            TokenType::Bang => Ok(Object::Bool(!Self::is_truthy(&right))),
-            _ => Err(LoxResult::error_at_token(&expr.operator, "Unreachable")),
-        }
+            var c = C(a: 1).sum;
+            var d = outer()(a: 1).sum;
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "c"), Object::Int(43));
+        assert_eq!(global(&interpreter, "d"), Object::Int(8));
     }
 
-    fn visit_variable_expr(
-        &self,
-        base: Rc<Expr>,
-        expr: &VariableExpr,
-    ) -> Result<Object, LoxResult> {
-        self.lookup_variable(&expr.name, base)
+    #[test]
+    fn test_list_destructuring() {
+        let interpreter = run("
+            var [a, b] = [1, 2];
+            fun f() {
+                const [m, n] = [10, 20];
+                return m + n;
+            }
+            var total = f();
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "a"), Object::Int(1));
+        assert_eq!(global(&interpreter, "b"), Object::Int(2));
+        assert_eq!(global(&interpreter, "total"), Object::Int(30));
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    // helpers
-    fn make_literal(o: Object) -> Rc<Expr> {
-        Rc::new(Expr::Literal(Rc::new(LiteralExpr { value: Some(o) })))
+    #[test]
+    fn test_object_destructuring() {
+        let interpreter = run("
+            class Point {
+                init(x, y) { this.x = x; this.y = y; }
+                sum { return this.x + this.y; }
+            }
+            var {x, y, sum} = Point(3, 4);
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "x"), Object::Int(3));
+        assert_eq!(global(&interpreter, "y"), Object::Int(4));
+        assert_eq!(global(&interpreter, "sum"), Object::Int(7));
     }
-    fn make_token(ttype: TokenType, lexeme: &str) -> Token {
-        Token::new(ttype, lexeme.to_string(), None, 1, 0)
+
+    #[test]
+    fn test_multiple_assignment() {
+        let interpreter = run("
+            var a = 1;
+            var b = 2;
+            a, b = b, a;
+            fun f() {
+                var first;
+                var second;
+                first = 0;
+                first, second = [10, 20];
+                return first + second;
+            }
+            var total = f();
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "a"), Object::Int(2));
+        assert_eq!(global(&interpreter, "b"), Object::Int(1));
+        assert_eq!(global(&interpreter, "total"), Object::Int(30));
     }
 
     #[test]
-    fn test_unary_minus() {
-        let interpreter = Interpreter::new();
-        let unary_expr = UnaryExpr {
-            operator: make_token(TokenType::Minus, "-"),
-            right: make_literal(Object::Number(123.)),
-        };
-        let result = interpreter.visit_unary_expr(
-            Rc::new(Expr::Unary(Rc::new(unary_expr.clone()))),
-            &unary_expr,
+    fn test_multiple_assignment_targets() {
+        let interpreter = run("
+            class Point { init(x, y) { this.x = x; this.y = y; } }
+            var p = Point(0, 0);
+            var xs = [0, 0];
+            var y = 0;
+            p.x, xs[1], y = 5, 6, 7;
+            var px = p.x;
+            var x1 = xs[1];
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "px"), Object::Int(5));
+        assert_eq!(global(&interpreter, "x1"), Object::Int(6));
+        assert_eq!(global(&interpreter, "y"), Object::Int(7));
+    }
+
+    #[test]
+    fn test_invalid_destructuring() {
+        assert!(run("var [a, b] = [1, 2, 3];").is_err());
+        assert!(run("var [a, b] = 1;").is_err());
+        assert!(run("var {a} = [1];").is_err());
+        assert!(run("class A {} var {a} = A();").is_err());
+        assert!(run("fun f() { var [a, a] = [1, 2]; }").is_err());
+    }
+
+    #[test]
+    fn test_invalid_multiple_assignment() {
+        assert!(run("var a; var b; a, b = 1, 2, 3;").is_err());
+        assert!(run("var a; var b; a, b = [1];").is_err());
+        assert!(run("var a; a, 1 = 1, 2;").is_err());
+        assert!(run("fun f() { const [a, b] = [1, 2]; a, b = b, a; }").is_err());
+    }
+
+    #[test]
+    fn test_tuple_literals() {
+        let interpreter = run("
+            var pair = (1, \"a\");
+            var first = pair[0];
+            var size = len(pair) + len(()) + len((1,));
+            var joined = (0, ...[1, 2]);
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "first"), Object::Int(1));
+        assert_eq!(global(&interpreter, "size"), Object::Int(3));
+        assert_eq!(
+            global(&interpreter, "joined"),
+            Object::Tuple(Rc::new(LoxTuple::new(vec![
+                Object::Int(0),
+                Object::Int(1),
+                Object::Int(2)
+            ])))
         );
-        assert!(result.is_ok());
-        assert_eq!(result.ok(), Some(Object::Number(-123.0)));
     }
 
     #[test]
-    fn test_unary_not() {
-        let interpreter = Interpreter::new();
-        let unary_expr = UnaryExpr {
-            operator: make_token(TokenType::Bang, "!"),
-            right: make_literal(Object::Bool(false)),
-        };
-        let result = interpreter.visit_unary_expr(
-            Rc::new(Expr::Unary(Rc::new(unary_expr.clone()))),
-            &unary_expr
+    fn test_tuple_equality() {
+        let interpreter = run("
+            var same = (1, (2, \"x\")) == (1.0, (2, \"x\"));
+            var different = (1, 2) != (1, 2, 3);
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "same"), Object::Bool(true));
+        assert_eq!(global(&interpreter, "different"), Object::Bool(true));
+    }
+
+    #[test]
+    fn test_tuple_string_form() {
+        let interpreter = run("
+            var shown = str((1, \"a\", [2], (nil,)));
+            var empty = str(());
+        ")
+        .unwrap();
+        assert_eq!(
+            global(&interpreter, "shown"),
+            Object::Str("(1, \"a\", [2], (nil,))".to_string())
         );
-        assert!(result.is_ok());
-        assert_eq!(result.ok(), Some(Object::Bool(true)));
+        assert_eq!(global(&interpreter, "empty"), Object::Str("()".to_string()));
     }
 
     #[test]
-    fn test_binary_sub() {
-        let interpreter = Interpreter::new();
-        let binary_expr = BinaryExpr {
-            left: make_literal(Object::Number(321.)),
-            operator: make_token(TokenType::Minus, "-"),
-            right: make_literal(Object::Number(123.)),
-        };
-        let result = interpreter.visit_binary_expr(
-            Rc::new(Expr::Binary(Rc::new(binary_expr.clone()))),
-            &binary_expr
+    fn test_tuple_unpacking() {
+        let interpreter = run("
+            fun sumdiff(a, b) { return a + b, a - b; }
+            var [q, r] = sumdiff(7, 3);
+            var a = 1;
+            var b = 2;
+            a, b = (b, a);
+            var total = 0;
+            for (var x in (1, 2, 3)) total = total + x;
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "q"), Object::Int(10));
+        assert_eq!(global(&interpreter, "r"), Object::Int(4));
+        assert_eq!(global(&interpreter, "a"), Object::Int(2));
+        assert_eq!(global(&interpreter, "b"), Object::Int(1));
+        assert_eq!(global(&interpreter, "total"), Object::Int(6));
+    }
+
+    #[test]
+    fn test_invalid_tuple_operations() {
+        assert!(run("var t = (1, 2); t[0] = 3;").is_err());
+        assert!(run("var t = (1, 2); t[2];").is_err());
+        assert!(run("var [a, b, c] = (1, 2);").is_err());
+    }
+
+    #[test]
+    fn test_ranges() {
+        let interpreter = run("
+            var total = 0;
+            for (var i in 1..=4) total = total + i;
+            var evens = \"\";
+            for (var i in 0..10 step 3) evens = evens + i;
+            var down = \"\";
+            for (var i in 5..0 step -2) down = down + i;
+            var n = 4;
+            var sizes = len(0..n - 1) * 100 + len(0..=10 step 5) * 10 + len(3..1);
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "total"), Object::Int(10));
+        assert_eq!(
+            global(&interpreter, "evens"),
+            Object::Str("0369".to_string())
         );
-        assert!(result.is_ok());
-        assert_eq!(result.ok(), Some(Object::Number(198.)));
+        assert_eq!(global(&interpreter, "down"), Object::Str("531".to_string()));
+        assert_eq!(global(&interpreter, "sizes"), Object::Int(330));
+        assert!(run("var r = 0..5 step 0;").is_err());
+        assert!(run("var r = 0..1.5;").is_err());
     }
 
     #[test]
-    fn test_binary_div() {
-        let interpreter = Interpreter::new();
-        let binary_expr = BinaryExpr {
-            left: make_literal(Object::Number(100.)),
-            operator: make_token(TokenType::Slash, "/"),
-            right: make_literal(Object::Number(10.)),
-        };
-        let result = interpreter.visit_binary_expr(
-            Rc::new(Expr::Binary(Rc::new(binary_expr.clone()))),
-            &binary_expr
+    fn test_range_native() {
+        let interpreter = run("
+            var stepped = str(range(0, 10, 5));
+            var named = len(range(0, 10, step: 2));
+        ")
+        .unwrap();
+        assert_eq!(
+            global(&interpreter, "stepped"),
+            Object::Str("range(0, 10, 5)".to_string())
         );
-        assert!(result.is_ok());
-        assert_eq!(result.ok(), Some(Object::Number(10.)));
+        assert_eq!(global(&interpreter, "named"), Object::Int(5));
     }
 
     #[test]
-    fn test_binary_mul() {
-        let interpreter = Interpreter::new();
-        let binary_expr = BinaryExpr {
-            left: make_literal(Object::Number(100.)),
-            operator: make_token(TokenType::Star, "*"),
-            right: make_literal(Object::Number(10.)),
-        };
-        let result = interpreter.visit_binary_expr(
-            Rc::new(Expr::Binary(Rc::new(binary_expr.clone()))),
-            &binary_expr
+    fn test_range_bounds() {
+        let interpreter = run("
+            var max = 9223372036854775807;
+            var min = -max - 1;
+            var contained = (max in 0..=max) and !(max in 0..max) and (min in min..=0);
+            var last = 0;
+            var count = 0;
+            for (var i in max - 1..=max) { last = i; count = count + 1; }
+            var down = len(-1..min step -1);
+            var full = len(min + 1..max step 2);
+            var shown = str(0..=10 step 5) + \" \" + str(1..=3);
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "contained"), Object::Bool(true));
+        assert_eq!(global(&interpreter, "last"), Object::Int(i64::MAX));
+        assert_eq!(global(&interpreter, "count"), Object::Int(2));
+        assert_eq!(global(&interpreter, "down"), Object::Int(i64::MAX));
+        assert_eq!(global(&interpreter, "full"), Object::Int(i64::MAX));
+        assert_eq!(
+            global(&interpreter, "shown"),
+            Object::Str("0..=10 step 5 1..=3".to_string())
         );
-        assert!(result.is_ok());
-        assert_eq!(result.ok(), Some(Object::Number(1000.)));
+        assert!(run("var n = len(0..=9223372036854775807);").is_err());
+        assert!(run("var max = 9223372036854775807; var n = len(-max - 1..max);").is_err());
     }
 
     #[test]
-    fn test_binary_add() {
-        let interpreter = Interpreter::new();
-        let binary_expr = BinaryExpr {
-            left: make_literal(Object::Number(100.)),
-            operator: make_token(TokenType::Plus, "+"),
-            right: make_literal(Object::Number(10.)),
-        };
-        let result = interpreter.visit_binary_expr(
-            Rc::new(Expr::Binary(Rc::new(binary_expr.clone()))),
-            &binary_expr
+    fn test_membership() {
+        let interpreter = run("
+            var members = (3 in 0..10 step 3) and !(4 in 0..10 step 3) and !(10 in 0..10);
+            var numbers = (3.0 in 0..5) and !(3.5 in 0..5) and !(\"3\" in 0..5);
+            var others = (2 in [1, 2]) and (\"ell\" in \"hello\") and !(3 in (1, 2));
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "members"), Object::Bool(true));
+        assert_eq!(global(&interpreter, "numbers"), Object::Bool(true));
+        assert_eq!(global(&interpreter, "others"), Object::Bool(true));
+        assert!(run("var b = 1 in 2;").is_err());
+    }
+
+    #[test]
+    fn test_slices() {
+        let interpreter = run("
+            var xs = [10, 20, 30, 40];
+            var sliced = xs[1..3];
+            var reversed = str(xs[3..=0 step -1]);
+            var word = \"hello\"[1..=3];
+            var pair = (1, 2, 3)[0..2];
+        ")
+        .unwrap();
+        match global(&interpreter, "sliced") {
+            Object::List(list) => assert_eq!(list.items(), vec![Object::Int(20), Object::Int(30)]),
+            value => panic!("expected a list but got {value}"),
+        }
+        assert_eq!(
+            global(&interpreter, "reversed"),
+            Object::Str("[40, 30, 20, 10]".to_string())
+        );
+        assert_eq!(global(&interpreter, "word"), Object::Str("ell".to_string()));
+        assert_eq!(
+            global(&interpreter, "pair"),
+            Object::Tuple(Rc::new(LoxTuple::new(vec![Object::Int(1), Object::Int(2)])))
+        );
+        assert!(run("var s = [1, 2][0..3];").is_err());
+    }
+
+    #[test]
+    fn test_traits() {
+        let interpreter = run("
+            trait Greets {
+                greet() { return \"hi \" + this.name; }
+            }
+            trait Counts {
+                count() { return len(this.name); }
+            }
+            class Base { init(name) { this.name = name; } }
+            class Person < Base with Greets, Counts {}
+            var p = Person(\"bob\");
+            var greeting = p.greet();
+            var count = p.count();
+            var kind = type(Greets);
+        ")
+        .unwrap();
+        assert_eq!(
+            global(&interpreter, "greeting"),
+            Object::Str("hi bob".to_string())
+        );
+        assert_eq!(global(&interpreter, "count"), Object::Int(3));
+        assert_eq!(
+            global(&interpreter, "kind"),
+            Object::Str("trait".to_string())
+        );
+        assert!(run("trait A { f() { return super.f(); } }").is_err());
+        assert!(run("class A {} class B with A {}").is_err());
+    }
+
+    #[test]
+    fn test_includes() {
+        let interpreter = run("
+            trait Greets {}
+            trait Counts {}
+            class Base {}
+            class Person < Base with Greets, Counts {}
+            class Student < Person {}
+            var checks = includes(Person, Greets) and includes(Student(), Counts)
+                and !includes(Base, Greets);
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "checks"), Object::Bool(true));
+    }
+
+    #[test]
+    fn test_trait_conflicts() {
+        let interpreter = run("
+            trait Greets { shout() { return \"HI\"; } }
+            trait Counts { shout() { return \"LOUD\"; } }
+            class Person with Greets, Counts {
+                shout() { return \"own\"; }
+            }
+            var shout = Person().shout();
+        ")
+        .unwrap();
+        assert_eq!(
+            global(&interpreter, "shout"),
+            Object::Str("own".to_string())
+        );
+        // Conflicts are reported by the resolver, or at runtime when a
+        // trait isn't known statically
+        assert!(run("trait A { f() {} } trait B { f() {} } class C with A, B {}").is_err());
+        assert!(run("
+            trait A { f() {} }
+            trait B { f() {} }
+            var b = B;
+            { var D = b; class C with A, D {} }
+        ")
+        .is_err());
+        // Only traits the names refer to are checked
+        assert!(run("
+            trait T { g() {} }
+            fun h() { trait T { f() {} } return T; }
+            trait U { f() {} }
+            class C with T, U {}
+        ")
+        .is_ok());
+        assert!(run("
+            trait T { f() {} }
+            trait U { f() {} }
+            trait V { g() {} }
+            T = V;
+            class C with T, U {}
+        ")
+        .is_ok());
+    }
+
+    #[test]
+    fn test_abstract_classes() {
+        let interpreter = run("
+            abstract class Shape {
+                abstract area();
+                describe() { return \"area \" + str(this.area()); }
+            }
+            class Square < Shape {
+                init(side) { this.side = side; }
+                area() { return this.side * this.side; }
+            }
+            var described = Square(3).describe();
+        ")
+        .unwrap();
+        assert_eq!(
+            global(&interpreter, "described"),
+            Object::Str("area 9".to_string())
+        );
+        // Abstract classes can't be instantiated
+        assert!(matches!(
+            run("abstract class A {} var a = A();"),
+            Err(LoxResult::RuntimeError { token, .. }) if token.lexeme == ")"
+        ));
+        assert!(run("class A { abstract f(); }").is_err());
+    }
+
+    #[test]
+    fn test_abstract_methods_from_traits() {
+        let interpreter = run("
+            abstract class Shape { abstract perimeter(); }
+            trait Perimeter { perimeter() { return 4 * this.side; } }
+            class Square < Shape with Perimeter {
+                init(side) { this.side = side; }
+            }
+            var perimeter = Square(3).perimeter();
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "perimeter"), Object::Int(12));
+    }
+
+    #[test]
+    fn test_unimplemented_abstract_methods() {
+        // Unimplemented methods are reported when the class is declared
+        assert!(run("abstract class A { abstract f(); } class B < A {}").is_err());
+        // ... or when it is instantiated if the superclass isn't known statically
+        assert!(run("
+            abstract class A { abstract f(); }
+            fun make(base) { class B < base {} return B; }
+            var b = make(A)();
+        ")
+        .is_err());
+        assert!(run("
+            abstract class A { abstract f(); }
+            fun make(base) { class B < base { f() { return 1; } } return B; }
+            var b = make(A)().f();
+        ")
+        .is_ok());
+        // Only the class the superclass name refers to is checked
+        assert!(run("
+            class A {}
+            fun g() { abstract class A { abstract f(); } return A; }
+            class B < A {}
+            var b = B();
+        ")
+        .is_ok());
+    }
+
+    #[test]
+    fn test_private_members() {
+        let interpreter = run("
+            class Account {
+                init(balance) { this.#balance = balance; }
+                deposit(amount) { this.#balance = this.#check(amount) + this.#balance; }
+                #check(amount) { return amount; }
+                balance { return this.#balance; }
+            }
+            var a = Account(10);
+            a.deposit(5);
+            var balance = a.balance;
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "balance"), Object::Int(15));
+        assert!(run("class A { init() { this.#x = 1; } } var x = A().#x;").is_err());
+        assert!(run("class A { f(other) { return other.#x; } }").is_err());
+        assert!(run("class A { #f() {} } var f = A().#f;").is_err());
+    }
+
+    #[test]
+    fn test_private_members_per_class() {
+        let interpreter = run("
+            class Account {
+                init(balance) { this.#balance = balance; }
+                balance { return this.#balance; }
+            }
+            class Savings < Account {
+                init(balance) { super.init(balance); this.#balance = 0; }
+                own { return this.#balance; }
+            }
+            var s = Savings(7);
+            var separate = s.balance * 10 + s.own;
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "separate"), Object::Int(70));
+        assert!(
+            run("class A { init() { this.#x = 1; } } class B < A { f() { return this.#x; } }")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_private_members_per_declaration() {
+        let interpreter = run("
+            class A {
+                init() { this.#x = 1; }
+                getx() { return this.#x; }
+            }
+            fun mk(B) {
+                class A < B {
+                    init() { super.init(); this.#x = 99; }
+                    own() { return this.#x; }
+                }
+                return A;
+            }
+            var a = mk(A)();
+            var kept = a.getx() * 100 + a.own();
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "kept"), Object::Int(199));
+    }
+
+    #[test]
+    fn test_private_members_hidden() {
+        let interpreter = run("
+            class Account {
+                init() { this.#balance = 1; }
+                #check() {}
+                deposit() {}
+            }
+            var a = Account();
+            var hidden = str(fields(a)) + str(methods(a)) + str(hasField(a, \"#balance\"));
+            var shown = str(a);
+        ")
+        .unwrap();
+        assert_eq!(
+            global(&interpreter, "hidden"),
+            Object::Str(r#"[]["deposit", "init"]false"#.to_string())
+        );
+        assert_eq!(
+            global(&interpreter, "shown"),
+            Object::Str("Account {}".to_string())
+        );
+        assert!(run("class A {} var a = A(); setField(a, \"#x\", 1);").is_err());
+    }
+
+    #[test]
+    fn test_field_declarations() {
+        let interpreter = run("
+            var created = 0;
+            class Counter {
+                var count = 0;
+                var items = [];
+                var label;
+                var #id = created = created + 1;
+                id { return this.#id; }
+            }
+            var a = Counter();
+            var b = Counter();
+            var counts = a.count + len(b.items) + b.id * 100;
+            var fresh = a.items != b.items;
+            var label = a.label;
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "counts"), Object::Int(200));
+        assert_eq!(global(&interpreter, "fresh"), Object::Bool(true));
+        assert_eq!(global(&interpreter, "label"), Object::Nil);
+    }
+
+    #[test]
+    fn test_inherited_field_declarations() {
+        let interpreter = run("
+            class Counter {
+                var count = 0;
+                var label;
+            }
+            class Named < Counter {
+                var label = \"named\";
+                init() { this.count = this.count + 10; }
+            }
+            var n = Named();
+            var named = n.label + str(n.count);
+        ")
+        .unwrap();
+        assert_eq!(
+            global(&interpreter, "named"),
+            Object::Str("named10".to_string())
         );
-        assert!(result.is_ok());
-        assert_eq!(result.ok(), Some(Object::Number(110.)));
     }
 
     #[test]
-    fn test_binary_concat() {
-        let interpreter = Interpreter::new();
-        let binary_expr = BinaryExpr {
-            left: make_literal(Object::Str("Hello, ".to_string())),
-            operator: make_token(TokenType::Plus, "+"),
-            right: make_literal(Object::Str("World!".to_string())),
-        };
-        let result = interpreter.visit_binary_expr(
-            Rc::new(Expr::Binary(Rc::new(binary_expr.clone()))),
-            &binary_expr
+    fn test_strict_classes() {
+        let interpreter = run("
+            strict class Point {
+                var x = 0;
+                var y = 0;
+                init(x, y) { this.x = x; this.y = y; }
+            }
+            var p = Point(1, 2);
+            p.x = 5;
+            var point = str(p);
+        ")
+        .unwrap();
+        assert_eq!(
+            global(&interpreter, "point"),
+            Object::Str("Point { x: 5, y: 2 }".to_string())
         );
-        assert!(result.is_ok());
-        assert_eq!(result.ok(), Some(Object::Str("Hello, World!".to_string())));
+        assert!(run("strict class P { var x; } var p = P(); p.y = 1;").is_err());
+        assert!(run("strict class P {} class Q < P {} var q = Q(); q.y = 1;").is_err());
+        assert!(run("strict class P {} var p = P(); setField(p, \"y\", 1);").is_err());
+        assert!(run("class P {} var p = P(); p.y = 1;").is_ok());
     }
 
     #[test]
-    fn test_binary_illegal() {
-        let interpreter = Interpreter::new();
-        let binary_expr = BinaryExpr {
-            left: make_literal(Object::Number(321.)),
-            operator: make_token(TokenType::Minus, "-"),
-            right: make_literal(Object::Bool(true)),
-        };
-        let result = interpreter.visit_binary_expr(
-            Rc::new(Expr::Binary(Rc::new(binary_expr.clone()))),
-            &binary_expr
-        );
-        assert!(result.is_err());
+    fn test_private_names_in_errors() {
+        let messages = [
+            (
+                "strict class P { init() { this.#y = 1; } } P();",
+                "Can't assign undeclared field '#y' of strict class P",
+            ),
+            (
+                "class P { set() { this.#x = 1; } get() { return this.#x; } } P().get();",
+                "Undefined property #x",
+            ),
+        ];
+        for (source, expected) in messages {
+            match run(source) {
+                Err(LoxResult::RuntimeError { message, .. }) => assert_eq!(message, expected),
+                _ => panic!("expected a runtime error from {source}"),
+            }
+        }
     }
 
     #[test]
-    fn test_binary_eq_nil() {
-        let interpreter = Interpreter::new();
-        let binary_expr = BinaryExpr {
-            left: make_literal(Object::Nil),
-            operator: make_token(TokenType::EqualEqual, "=="),
-            right: make_literal(Object::Nil),
-        };
-        let result = interpreter.visit_binary_expr(
-            Rc::new(Expr::Binary(Rc::new(binary_expr.clone()))),
-            &binary_expr
+    fn test_enums() {
+        let interpreter = run("
+            enum Color { Red, Green, Blue, }
+            var names = \"\";
+            for (var c in Color) names = names + str(c) + \" \";
+            var same = Color.Red == Color.Red and Color.Red != Color.Green;
+            var kind = type(Color) + \" \" + type(Color.Red);
+        ")
+        .unwrap();
+        assert_eq!(
+            global(&interpreter, "names"),
+            Object::Str("Color.Red Color.Green Color.Blue ".to_string())
         );
-        assert!(result.is_ok());
-        assert_eq!(result.ok(), Some(Object::Bool(true)));
+        assert_eq!(global(&interpreter, "same"), Object::Bool(true));
+        assert_eq!(
+            global(&interpreter, "kind"),
+            Object::Str("enum variant".to_string())
+        );
+        assert!(run("enum Color { Red, Red }").is_err());
     }
 
     #[test]
-    fn test_binary_eq_str() {
-        let interpreter = Interpreter::new();
-        let binary_expr = BinaryExpr {
-            left: make_literal(Object::Str("Hello".to_string())),
-            operator: make_token(TokenType::EqualEqual, "=="),
-            right: make_literal(Object::Str("Hello".to_string())),
-        };
-        let result = interpreter.visit_binary_expr(
-            Rc::new(Expr::Binary(Rc::new(binary_expr.clone()))),
-            &binary_expr
+    fn test_enum_variant_data() {
+        let interpreter = run("
+            enum Shape { Dot, Circle(r), Rect(w, h) }
+            fun area(shape) {
+                match (shape) {
+                    case Shape.Dot => return 0;
+                    case Shape.Circle(r) => return 3 * r * r;
+                    case Shape.Rect(w, h: 1) => return w;
+                    case Shape.Rect(w, h) => return w * h;
+                }
+            }
+            var areas = area(Shape.Dot) + area(Shape.Circle(2)) + area(Shape.Rect(5, 1))
+                + area(Shape.Rect(h: 2, w: 3));
+            var shown = str(Shape.Rect(2, 3)) + \" \" + str(Shape.Circle(1).r);
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "areas"), Object::Int(23));
+        assert_eq!(
+            global(&interpreter, "shown"),
+            Object::Str("Shape.Rect(2, 3) 1".to_string())
         );
-        assert!(result.is_ok());
-        assert_eq!(result.ok(), Some(Object::Bool(true)));
+        assert!(run("enum Shape { Circle(r) } var c = Shape.Circle();").is_err());
     }
 
     #[test]
-    fn test_binary_ne_str() {
-        let interpreter = Interpreter::new();
-        let binary_expr = BinaryExpr {
-            left: make_literal(Object::Str("Hello".to_string())),
-            operator: make_token(TokenType::EqualEqual, "=="),
-            right: make_literal(Object::Str("World".to_string())),
-        };
-        let result = interpreter.visit_binary_expr(
-            Rc::new(Expr::Binary(Rc::new(binary_expr.clone()))),
-            &binary_expr
+    fn test_enum_variant_constructors() {
+        let interpreter = run("
+            enum Shape { Dot, Circle(r), Rect(w, h) }
+            var names = \"\";
+            for (var s in Shape) names = names + str(s) + \" \";
+            var kind = type(Shape.Circle);
+            var same = Shape.Circle == Shape.Circle and Shape.Circle != Shape.Rect;
+            var size = arity(Shape.Rect);
+        ")
+        .unwrap();
+        assert_eq!(
+            global(&interpreter, "names"),
+            Object::Str("Shape.Dot Shape.Circle Shape.Rect ".to_string())
         );
-        assert!(result.is_ok());
-        assert_eq!(result.ok(), Some(Object::Bool(false)));
+        assert_eq!(
+            global(&interpreter, "kind"),
+            Object::Str("variant".to_string())
+        );
+        assert_eq!(global(&interpreter, "same"), Object::Bool(true));
+        assert_eq!(global(&interpreter, "size"), Object::Int(2));
     }
 
-    // Use a helper to test '==' and '!=', '>', '>=', '<', and '<='
-    fn run_comparisons(tok: &Token, nums: Vec<f64>, value: f64, results: Vec<bool>) {
-        let interpreter = Interpreter::new();
-
-        for (num, r) in nums.iter().zip(results) {
-            let binary_expr = BinaryExpr {
-                left: make_literal(Object::Number(*num)),
-                operator: tok.clone(),
-                right: make_literal(Object::Number(value)),
-            };
-            let result = interpreter.visit_binary_expr(
-                Rc::new(Expr::Binary(Rc::new(binary_expr.clone()))),
-                &binary_expr
-            );
-            assert!(result.is_ok());
-            assert_eq!(
-                result.ok(),
-                Some(Object::Bool(r)),
-                "Testing {} {} {}",
-                num,
-                tok.lexeme,
-                value
-            );
-        }
+    #[test]
+    fn test_misspelled_enum_variants() {
+        // Misspelled variants are caught by the resolver or at runtime
+        assert!(run("enum Color { Red } var c = Color.Rde;").is_err());
+        assert!(run("enum Color { Red } var e = Color; var c = e.Rde;").is_err());
+        // Only names referring to the enum are checked
+        assert!(run("
+            { enum Color { Red } }
+            class Color { init() { this.name = \"c\"; } }
+            fun f(Color) { return Color.name; }
+            var name = f(Color());
+        ")
+        .is_ok());
     }
 
     #[test]
-    fn test_binary_eq() {
-        let numbers = vec![14., 15., 16.];
-        let results = vec![false, true, false];
-        run_comparisons(
-            &make_token(TokenType::EqualEqual, "=="),
-            numbers,
-            15.,
-            results,
+    fn test_records() {
+        let interpreter = run("
+            record Point(x, y) {
+                norm() { return this.x * this.x + this.y * this.y; }
+            }
+            var p = Point(1, 2);
+            var q = p.copy(y: 5);
+            var norm = q.norm();
+            var kept = p.y;
+            var shown = str(p);
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "norm"), Object::Int(26));
+        assert_eq!(global(&interpreter, "kept"), Object::Int(2));
+        assert_eq!(
+            global(&interpreter, "shown"),
+            Object::Str("Point(x: 1, y: 2)".to_string())
         );
+        assert!(run("record Point(x, y); var p = Point(1);").is_err());
+        assert!(run("record Point(x, y); var p = Point(1, 2).copy(z: 3);").is_err());
     }
 
     #[test]
-    fn test_binary_ne() {
-        let numbers = vec![14., 15., 16.];
-        let results = vec![true, false, true];
-        run_comparisons(
-            &make_token(TokenType::BangEqual, "!="),
-            numbers,
-            15.,
-            results,
-        );
+    fn test_record_equality() {
+        let interpreter = run("
+            record Point(x, y);
+            record Point3(z) < Point;
+            var p = Point(1, 2);
+            var same = p == Point(1, 2) and p != Point(1, 3);
+            var other = Point(1, 2) != Point3(1, 2, 0);
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "same"), Object::Bool(true));
+        assert_eq!(global(&interpreter, "other"), Object::Bool(true));
     }
 
     #[test]
-    fn test_binary_gt() {
-        let numbers = vec![14., 15., 16.];
-        let results = vec![false, false, true];
-        run_comparisons(&make_token(TokenType::Greater, ">"), numbers, 15., results);
+    fn test_record_inheritance() {
+        let interpreter = run("
+            record Point(x, y);
+            record Point3(z) < Point;
+            class Labeled < Point {
+                label { return \"p\" + str(this.x); }
+            }
+            var shown = str(Point3(x: 1, y: 2, z: 3));
+            var label = Labeled(7, 0).label;
+        ")
+        .unwrap();
+        assert_eq!(
+            global(&interpreter, "shown"),
+            Object::Str("Point3(x: 1, y: 2, z: 3)".to_string())
+        );
+        assert_eq!(global(&interpreter, "label"), Object::Str("p7".to_string()));
     }
 
     #[test]
-    fn test_binary_ge() {
-        let numbers = vec![14., 15., 16.];
-        let results = vec![false, true, true];
-        run_comparisons(
-            &make_token(TokenType::GreaterEqual, ">="),
-            numbers,
-            15.,
-            results,
+    fn test_records_inheriting_initializers() {
+        let interpreter = run("
+            class Base {
+                init() { this.tag = \"b\"; }
+            }
+            record R(a, b) < Base;
+            record S(c) < R;
+            var r = R(1, 2);
+            var fields = r.tag + str(r.a + r.b);
+            var s = S(b: 2, a: 1, c: 3);
+            var inherited = s.tag + str(s.a + s.b + s.c);
+        ")
+        .unwrap();
+        assert_eq!(
+            global(&interpreter, "fields"),
+            Object::Str("b3".to_string())
+        );
+        assert_eq!(
+            global(&interpreter, "inherited"),
+            Object::Str("b6".to_string())
         );
+        assert!(run("class Base { init(x) {} } record R(a) < Base; R(1);").is_err());
     }
 
     #[test]
-    fn test_binary_lt() {
-        let numbers = vec![14., 15., 16.];
-        let results = vec![true, false, false];
-        run_comparisons(&make_token(TokenType::Less, "<"), numbers, 15., results);
+    fn test_duplicate_record_fields() {
+        assert!(run("record S(a, a);").is_err());
+        assert!(run("record S(a, b, a) {}").is_err());
     }
 
     #[test]
-    fn test_binary_le() {
-        let numbers = vec![14., 15., 16.];
-        let results = vec![true, true, false];
-        run_comparisons(
-            &make_token(TokenType::LessEqual, "<="),
-            numbers,
-            15.,
-            results,
+    fn test_class_members() {
+        let interpreter = run("
+            class Shape {
+                area() { return 0; }
+            }
+            class Point < Shape {
+                init() { this.name = \"p\"; }
+            }
+            var names = Point.name + Point.superclass.name;
+            var root = Shape.superclass;
+            var methods = str(Point.methods());
+        ")
+        .unwrap();
+        assert_eq!(
+            global(&interpreter, "names"),
+            Object::Str("PointShape".to_string())
+        );
+        assert_eq!(global(&interpreter, "root"), Object::Nil);
+        assert_eq!(
+            global(&interpreter, "methods"),
+            Object::Str(r#"["area", "init"]"#.to_string())
         );
+        assert!(run("class A {} var x = A.missing;").is_err());
     }
 
     #[test]
-    fn test_nested_expr() {
-        //-123 * (45.67) = -5617.41
-        let binary_expr = BinaryExpr {
-            left: Rc::new(Expr::Unary({
-                Rc::new(UnaryExpr {
-                    operator: make_token(TokenType::Minus, "-"),
-                    right: make_literal(Object::Number(123.)),
-                })
-            })),
-            operator: make_token(TokenType::Star, "*"),
-            right: make_literal(Object::Number(45.67)),
-        };
-        let interpreter = Interpreter::new();
-        let result = interpreter.visit_binary_expr(
-            Rc::new(Expr::Binary(Rc::new(binary_expr.clone()))),
-            &binary_expr
+    fn test_static_members_shadow_class_members() {
+        let interpreter = run("
+            class Named {
+                static name() { return \"custom\"; }
+            }
+            var custom = Named.name();
+        ")
+        .unwrap();
+        assert_eq!(
+            global(&interpreter, "custom"),
+            Object::Str("custom".to_string())
         );
-        assert!(result.is_ok());
-        assert_eq!(result.ok(), Some(Object::Number(-5617.41)));
     }
 
     #[test]
-    fn test_var_stmt() {
-        let token = Token::new(TokenType::Identifier, "var_num".to_string(), None, 1, 1);
-        let var_stmt = VarStmt {
-            name: token.clone(),
-            initializer: None,
-        };
-        let interpreter = Interpreter::new();
-        assert!(interpreter
-            .visit_var_stmt(Rc::new(Stmt::Var(Rc::new(var_stmt.clone()))), &var_stmt)
-            .is_ok());
+    fn test_metaclass_objects() {
+        let interpreter = run("
+            class Shape {
+                static create() { return this(); }
+            }
+            class Point < Shape {}
+            var meta = classOf(Point);
+            var name = meta.name;
+            var inherits = meta.superclass == classOf(Shape);
+            var statics = str(meta.methods());
+            var created = isInstance(Point.create(), Point);
+            var instance = isInstance(Point, classOf(Shape));
+            var top = classOf(meta);
+        ")
+        .unwrap();
         assert_eq!(
-            interpreter
-                .environment
-                .borrow()
-                .borrow()
-                .get(&token)
-                .unwrap(),
-            Object::Nil
+            global(&interpreter, "name"),
+            Object::Str("Point class".to_string())
+        );
+        assert_eq!(global(&interpreter, "inherits"), Object::Bool(true));
+        assert_eq!(
+            global(&interpreter, "statics"),
+            Object::Str(r#"["create"]"#.to_string())
         );
+        assert_eq!(global(&interpreter, "created"), Object::Bool(true));
+        assert_eq!(global(&interpreter, "instance"), Object::Bool(true));
+        assert_eq!(global(&interpreter, "top"), Object::Nil);
+        assert!(run("class A {} var a = classOf(A)();").is_err());
     }
 
     #[test]
-    fn test_var_stmt_initialized() {
-        let token = Token::new(TokenType::Identifier, "var_num".to_string(), None, 1, 1);
-        let var_stmt = VarStmt {
-            name: token.clone(),
-            initializer: Some(make_literal(Object::Number(123.))),
-        };
-        let interpreter = Interpreter::new();
-        assert!(interpreter
-            .visit_var_stmt(
-                Rc::new(Stmt::Var(Rc::new(var_stmt.clone()))),
-                &var_stmt
-            ).is_ok());
+    fn test_defining_methods() {
+        let interpreter = run("
+            class Shape {}
+            class Point < Shape { init(x) { this.x = x; } }
+            var p = Point(3);
+            fun scaled(self, by) { return self.x * by; }
+            Shape.define(\"scaled\", scaled);
+            var six = p.scaled(2);
+            var named = p.scaled(by: 5);
+            fun origin(klass) { return klass(0); }
+            classOf(Shape).define(\"origin\", origin);
+            var zero = Point.origin().x;
+            Point.helper = scaled;
+            var methods = str(Point.methods());
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "six"), Object::Int(6));
+        assert_eq!(global(&interpreter, "named"), Object::Int(15));
+        assert_eq!(global(&interpreter, "zero"), Object::Int(0));
         assert_eq!(
-            interpreter
-                .environment
-                .borrow()
-                .borrow()
-                .get(&token)
-                .unwrap(),
-            Object::Number(123.)
+            global(&interpreter, "methods"),
+            Object::Str(r#"["init", "scaled"]"#.to_string())
         );
+        assert!(run("class A {} fun f() {} A.define(\"f\", f);").is_err());
+        assert!(run("class A {} A.define(\"f\", 1);").is_err());
+        assert!(run("class A {} fun f(a) {} A.define(\"#f\", f);").is_err());
     }
 
     #[test]
-    fn test_variable_expression() {
-        // First define a variable
-        let token = Token::new(TokenType::Identifier, "var_num".to_string(), None, 1, 1);
-        let var_stmt = VarStmt {
-            name: token.clone(),
-            initializer: Some(make_literal(Object::Number(123.))),
-        };
-        let interpreter = Interpreter::new();
-        assert!(interpreter
-            .visit_var_stmt(
-                Rc::new(Stmt::Var(Rc::new(var_stmt.clone()))),
-                &var_stmt
-            ).is_ok());
-
-        // Now use the defined variable in an expression
-        let var_expr = VariableExpr {
-            name: token.clone(),
-        };
-        assert!(interpreter
-            .visit_variable_expr(
-                Rc::new(Expr::Variable(Rc::new(var_expr.clone()))),
-                &var_expr
-            ).is_ok());
+    fn test_including_traits_at_runtime() {
+        let interpreter = run("
+            trait Greets { greet() { return \"hi \" + this.name; } }
+            class Point { init() { this.name = \"p\"; } }
+            var p = Point();
+            Point.include(Greets);
+            var greeting = p.greet();
+            var included = includes(p, Greets);
+        ")
+        .unwrap();
         assert_eq!(
-            interpreter
-                .visit_variable_expr(
-                    Rc::new(Expr::Variable(Rc::new(var_expr.clone()))),
-                    &var_expr
-                ).unwrap(),
-            Object::Number(123.)
+            global(&interpreter, "greeting"),
+            Object::Str("hi p".to_string())
         );
+        assert_eq!(global(&interpreter, "included"), Object::Bool(true));
+        assert!(run("class A {} A.include(1);").is_err());
     }
+
     #[test]
-    fn test_variable_expression_undefined() {
-        let token = Token::new(TokenType::Identifier, "var_num".to_string(), None, 1, 1);
-        let interpreter = Interpreter::new();
-        // Try to use an undefined variable in an expression
-        let var_expr = VariableExpr {
-            name: token.clone(),
-        };
-        assert!(interpreter
-            .visit_variable_expr(
-                Rc::new(Expr::Variable(Rc::new(var_expr.clone()))),
-                &var_expr
-            )
-            .is_err());
+    fn test_trait_conflicts_at_runtime() {
+        let interpreter = run("
+            trait A { f() { return \"a\"; } }
+            trait B { f() { return \"b\"; } }
+            class P { f() { return \"p\"; } }
+            P.include(A);
+            P.include(B);
+            var own = P().f();
+            class Q with A {}
+            Q.include(A);
+            var again = Q().f();
+        ")
+        .unwrap();
+        assert_eq!(global(&interpreter, "own"), Object::Str("p".to_string()));
+        assert_eq!(global(&interpreter, "again"), Object::Str("a".to_string()));
+        assert!(run("
+            trait A { f() {} }
+            trait B { f() {} }
+            class P {}
+            P.include(A);
+            P.include(B);
+        ")
+        .is_err());
+        assert!(
+            run("trait A { f() {} } trait B { f() {} } class P with A {} P.include(B);").is_err()
+        );
     }
 }
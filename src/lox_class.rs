@@ -1,17 +1,70 @@
 use crate::callable::*;
+use crate::environment::Environment;
 use crate::error::*;
+use crate::expr::Expr;
+use crate::functions_native::{string_list, LoxNative, NativeMethod};
 use crate::interpreter::*;
 use crate::lox_instance::LoxInstance;
+use crate::lox_trait::LoxTrait;
 use crate::object::*;
+use crate::token::*;
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fmt;
 use std::rc::Rc;
 
-#[derive(Debug, Clone, PartialEq)]
+// Private members ('#name') are stored under a key that includes the id of
+// the class (or trait) declaration declaring them, so other classes can't
+// reach them even if they have the same name
+pub fn private_key(name: &str, owner: usize) -> String {
+    format!("{}@{}", name, owner)
+}
+
+pub fn is_private(name: &str) -> bool {
+    name.starts_with('#')
+}
+
+// The name of a member as written in the source. Private keys drop the id
+// of their owner, so error messages don't show it.
+pub fn source_name(key: &str) -> &str {
+    key.split_once('@').map_or(key, |(name, _)| name)
+}
+
+// Instance fields declared in the class body. The initializers are evaluated
+// for every new instance, with 'this' bound to it, in the scope the class was
+// declared in. Strict classes only allow assigning declared fields.
+#[derive(Clone)]
+pub struct FieldDeclarations {
+    pub fields: Vec<(String, Option<Rc<Expr>>)>,
+    pub closure: Rc<RefCell<Environment>>,
+    pub strict: bool,
+    // The positional fields of a record, which are declared fields as well
+    pub record: Option<Vec<String>>,
+}
+
+impl fmt::Debug for FieldDeclarations {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names: Vec<&String> = self.fields.iter().map(|(name, _)| name).collect();
+        write!(f, "{:?}", names)
+    }
+}
+
+// A class is itself an instance of its metaclass, which holds the static
+// methods. The metaclass of a subclass inherits from the metaclass of its
+// superclass, so static methods are inherited like instance methods.
+#[derive(Debug, Clone)]
 pub struct LoxClass {
     pub name: String,
     pub superclass: Option<Rc<LoxClass>>,
-    pub methods: HashMap<String, Object>,
+    // None if this class is a metaclass
+    metaclass: Option<Rc<LoxClass>>,
+    // Methods and traits can be added at runtime through 'include' and 'define'
+    methods: RefCell<HashMap<String, Object>>,
+    traits: RefCell<Vec<Rc<LoxTrait>>>,
+    // Methods declared 'abstract'. None unless the class is abstract.
+    abstract_methods: Option<Vec<String>>,
+    field_declarations: FieldDeclarations,
+    static_fields: RefCell<HashMap<String, Object>>,
 }
 
 impl LoxClass {
@@ -19,30 +72,217 @@ impl LoxClass {
         name: &str,
         superclass: Option<Rc<LoxClass>>,
         methods: HashMap<String, Object>,
+        static_methods: HashMap<String, Object>,
+        abstract_methods: Option<Vec<String>>,
+        field_declarations: FieldDeclarations,
     ) -> Self {
+        let metaclass = Self {
+            name: format!("{} class", name),
+            superclass: superclass.as_ref().and_then(|s| s.metaclass.clone()),
+            metaclass: None,
+            methods: RefCell::new(static_methods),
+            traits: RefCell::new(Vec::new()),
+            abstract_methods: None,
+            field_declarations: FieldDeclarations {
+                fields: Vec::new(),
+                closure: Rc::clone(&field_declarations.closure),
+                strict: false,
+                record: None,
+            },
+            static_fields: RefCell::new(HashMap::new()),
+        };
         Self {
             name: name.to_owned(),
             superclass,
-            methods,
+            metaclass: Some(Rc::new(metaclass)),
+            methods: RefCell::new(methods),
+            traits: RefCell::new(Vec::new()),
+            abstract_methods,
+            field_declarations,
+            static_fields: RefCell::new(HashMap::new()),
         }
     }
+
+    pub fn metaclass(&self) -> Option<Rc<LoxClass>> {
+        self.metaclass.clone()
+    }
+
     pub fn instantiate(
         &self,
         interpreter: &Interpreter,
         arguments: Vec<Object>,
         klass: Rc<LoxClass>,
     ) -> Result<Object, LoxResult> {
-        let instance = Object::Instance(Rc::new(LoxInstance::new(&klass)));
-        if let Some(Object::Func(initializer)) = self.find_method("init".to_string()) {
-            if let Object::Func(func) = initializer.bind(&instance) {
-                func.call(interpreter, arguments, None)?;
+        let (instance, initializer) = self.allocate(interpreter, &klass)?;
+        match initializer {
+            Some(Object::Func(func)) => func.call(interpreter, arguments, None)?,
+            Some(Object::Native(native)) => native.func.call(interpreter, arguments, None)?,
+            _ => Object::Nil,
+        };
+        Ok(Object::Instance(instance))
+    }
+
+    // Create an instance with its declared fields initialized, along with
+    // the initializer, if any, bound to it. Defaults of the initializer's
+    // parameters may refer to 'this', so they are evaluated on the bound one.
+    pub fn allocate(
+        &self,
+        interpreter: &Interpreter,
+        klass: &Rc<LoxClass>,
+    ) -> Result<(Rc<LoxInstance>, Option<Object>), LoxResult> {
+        let instance = Rc::new(LoxInstance::new(klass));
+        self.initialize_fields(interpreter, &instance)?;
+        let initializer = self
+            .initializing_class()
+            .map(|k| k.bind_initializer(&instance));
+        Ok((instance, initializer))
+    }
+
+    // The class whose initializer a call to this class runs: the nearest one
+    // in the superclass chain that defines 'init' or is a record
+    pub fn initializing_class(&self) -> Option<&LoxClass> {
+        if self.methods.borrow().contains_key("init") || self.field_declarations.record.is_some() {
+            Some(self)
+        } else {
+            self.superclass.as_ref()?.initializing_class()
+        }
+    }
+
+    // Records without an 'init' method get one generated that takes their
+    // positional fields
+    fn bind_initializer(&self, instance: &Rc<LoxInstance>) -> Object {
+        let this = Object::Instance(Rc::clone(instance));
+        if let Some(Object::Func(initializer)) = self.methods.borrow().get("init") {
+            return initializer.bind(&this);
+        }
+        let superclass = self.superclass.as_ref().and_then(|superclass| {
+            match superclass.find_method("init".to_string()) {
+                Some(Object::Func(initializer)) => Some(initializer.bind(&this)),
+                _ => None,
+            }
+        });
+        Object::Native(Rc::new(LoxNative {
+            func: Rc::new(RecordInitializer {
+                instance: Rc::clone(instance),
+                fields: self.record_fields().unwrap_or_default(),
+                superclass,
+            }),
+        }))
+    }
+
+    // The positional fields of a record: those of the records it inherits
+    // from followed by its own. None if the class isn't a record.
+    pub fn record_fields(&self) -> Option<Vec<String>> {
+        let inherited = self
+            .superclass
+            .as_ref()
+            .and_then(|superclass| superclass.record_fields());
+        match &self.field_declarations.record {
+            Some(own) => {
+                let mut fields = inherited.unwrap_or_default();
+                fields.extend(own.iter().cloned());
+                Some(fields)
             }
+            None => inherited,
+        }
+    }
+
+    // Declared fields are initialized before 'init' runs, those of the
+    // superclasses first so that a subclass can redeclare them
+    fn initialize_fields(
+        &self,
+        interpreter: &Interpreter,
+        instance: &Rc<LoxInstance>,
+    ) -> Result<(), LoxResult> {
+        if let Some(superclass) = &self.superclass {
+            superclass.initialize_fields(interpreter, instance)?;
         }
-        Ok(instance)
+        let declarations = &self.field_declarations;
+        if declarations.fields.is_empty() {
+            return Ok(());
+        }
+        let mut env = Environment::new_enclosing(Rc::clone(&declarations.closure));
+        env.define("this", Object::Instance(Rc::clone(instance)));
+        let env = Rc::new(RefCell::new(env));
+        for (name, initializer) in &declarations.fields {
+            let value = match initializer {
+                Some(expr) => {
+                    interpreter.with_environment(&env, || interpreter.evaluate(expr.clone()))?
+                }
+                None => Object::Nil,
+            };
+            instance.set_field(name, value);
+        }
+        Ok(())
+    }
+
+    // A class is strict if it or one of its superclasses is declared strict
+    pub fn is_strict(&self) -> bool {
+        self.field_declarations.strict
+            || self
+                .superclass
+                .as_ref()
+                .is_some_and(|superclass| superclass.is_strict())
+    }
+
+    pub fn declares_field(&self, name: &str) -> bool {
+        self.field_declarations
+            .fields
+            .iter()
+            .any(|(field, _)| field == name)
+            || self
+                .superclass
+                .as_ref()
+                .is_some_and(|superclass| superclass.declares_field(name))
+    }
+
+    // Abstract methods of this class and its superclasses that nothing
+    // further down the hierarchy implements, in order
+    pub fn missing_methods(&self) -> Vec<String> {
+        let mut missing = self
+            .superclass
+            .as_ref()
+            .map_or_else(Vec::new, |superclass| superclass.missing_methods());
+        missing.retain(|name| !self.methods.borrow().contains_key(name));
+        missing.extend(self.abstract_methods.iter().flatten().cloned());
+        missing.sort();
+        missing.dedup();
+        missing
+    }
+
+    // Abstract classes and classes with unimplemented methods can't be
+    // instantiated. 'paren' is the call's token the error is reported at.
+    pub fn check_instantiable(&self, paren: &Token) -> Result<(), LoxResult> {
+        if self.metaclass.is_none() {
+            return Err(LoxResult::error_runtime(
+                paren,
+                &format!("Can't instantiate metaclass '{}'", self.name),
+            ));
+        }
+        let missing = self.missing_methods();
+        if self.abstract_methods.is_none() && missing.is_empty() {
+            return Ok(());
+        }
+        let kind = if self.abstract_methods.is_some() {
+            "abstract class"
+        } else {
+            "class"
+        };
+        let message = if missing.is_empty() {
+            format!("Can't instantiate {} '{}'", kind, self.name)
+        } else {
+            format!(
+                "Can't instantiate {} '{}' with unimplemented methods: {}",
+                kind,
+                self.name,
+                missing.join(", ")
+            )
+        };
+        Err(LoxResult::error_runtime(paren, &message))
     }
 
     pub fn find_method(&self, name: String) -> Option<Object> {
-        if let Some(method) = self.methods.get(&name) {
+        if let Some(method) = self.methods.borrow().get(&name) {
             Some(method.clone())
         } else if let Some(superclass) = &self.superclass {
             superclass.find_method(name)
@@ -50,6 +290,186 @@ impl LoxClass {
             None
         }
     }
+
+    // Names of the public instance methods, including inherited ones, in
+    // order. Getters and setters of the same property are listed once.
+    pub fn method_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .methods
+            .borrow()
+            .keys()
+            .filter(|name| !is_private(name))
+            .map(|name| name.trim_end_matches('=').to_string())
+            .collect();
+        if let Some(superclass) = &self.superclass {
+            names.extend(superclass.method_names());
+        }
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    // True if this class is 'klass' or inherits from it
+    pub fn is_subclass_of(&self, klass: &LoxClass) -> bool {
+        std::ptr::eq(self, klass)
+            || self
+                .superclass
+                .as_ref()
+                .is_some_and(|superclass| superclass.is_subclass_of(klass))
+    }
+
+    // True if this class or one of its superclasses includes the trait
+    pub fn includes(&self, tr: &LoxTrait) -> bool {
+        self.traits
+            .borrow()
+            .iter()
+            .any(|t| std::ptr::eq(t.as_ref(), tr))
+            || self
+                .superclass
+                .as_ref()
+                .is_some_and(|superclass| superclass.includes(tr))
+    }
+
+    // Static methods are the methods of the metaclass
+    pub fn find_static_method(&self, name: String) -> Option<Object> {
+        self.metaclass.as_ref()?.find_method(name)
+    }
+
+    fn find_static_field(&self, name: &str) -> Option<Object> {
+        if let Some(field) = self.static_fields.borrow().get(name) {
+            Some(field.clone())
+        } else if let Some(superclass) = &self.superclass {
+            superclass.find_static_field(name)
+        } else {
+            None
+        }
+    }
+
+    // Property access on the class itself. Static fields shadow static methods,
+    // which are bound with 'this' referring to the class they are accessed on.
+    // Both shadow the members shared by all classes.
+    pub fn get(&self, name: &Token, this: &Rc<LoxClass>) -> Result<Object, LoxResult> {
+        if let Some(field) = self.find_static_field(&name.lexeme) {
+            Ok(field)
+        } else if let Some(method) = self.find_static_method(name.lexeme.clone()) {
+            if let Object::Func(func) = method {
+                Ok(func.bind(&Object::Class(this.clone())))
+            } else {
+                Err(LoxResult::error_runtime(
+                    name,
+                    "Cannot bind 'this' to a non-function method",
+                ))
+            }
+        } else {
+            self.metaclass_member(name, this)
+        }
+    }
+
+    // Members shared by all classes, metaclasses included: 'name',
+    // 'superclass', 'methods()', 'include(trait)' and 'define(name, function)'
+    fn metaclass_member(&self, name: &Token, this: &Rc<LoxClass>) -> Result<Object, LoxResult> {
+        let this = Rc::clone(this);
+        match name.lexeme.as_str() {
+            "name" => Ok(Object::Str(self.name.clone())),
+            "superclass" => Ok(match &self.superclass {
+                Some(superclass) => Object::Class(Rc::clone(superclass)),
+                None => Object::Nil,
+            }),
+            "methods" => Ok(NativeMethod::bind(this, 0, |k, _, _| {
+                Ok(string_list(k.method_names()))
+            })),
+            "include" => Ok(NativeMethod::bind(
+                this,
+                1,
+                |k, _, arguments| match &arguments[0] {
+                    Object::Trait(tr) => {
+                        k.include(tr)?;
+                        Ok(Object::Nil)
+                    }
+                    _ => Err(LoxResult::system_error("Can only include traits")),
+                },
+            )),
+            "define" => Ok(NativeMethod::bind(this, 2, |k, _, arguments| {
+                k.define(&arguments[0], &arguments[1])?;
+                Ok(Object::Nil)
+            })),
+            _ => Err(LoxResult::error_runtime(
+                name,
+                &format!("Undefined property '{}'", source_name(&name.lexeme)),
+            )),
+        }
+    }
+
+    // Two traits of a class must not provide the same method unless the
+    // class overrides it. Returns the error message if 'tr' provides a method
+    // that a trait the class already includes provides as well.
+    pub fn trait_conflict(&self, tr: &LoxTrait) -> Option<String> {
+        let methods = self.methods.borrow();
+        let traits = self.traits.borrow();
+        for (name, method) in &tr.methods {
+            let Some(current) = methods.get(name).filter(|current| *current != method) else {
+                continue;
+            };
+            if let Some(other) = traits.iter().find(|t| t.methods.get(name) == Some(current)) {
+                return Some(format!(
+                    "Method '{}' is defined by both traits '{}' and '{}'",
+                    name.trim_end_matches('='),
+                    other.name,
+                    tr.name
+                ));
+            }
+        }
+        None
+    }
+
+    // Including a trait adds the methods the class doesn't define itself.
+    // Existing instances see them since methods are looked up on call.
+    pub fn include(&self, tr: &Rc<LoxTrait>) -> Result<(), LoxResult> {
+        if let Some(message) = self.trait_conflict(tr) {
+            return Err(LoxResult::system_error(&message));
+        }
+        let mut methods = self.methods.borrow_mut();
+        for (name, method) in &tr.methods {
+            if !methods.contains_key(name) {
+                methods.insert(name.clone(), method.clone());
+            }
+        }
+        self.traits.borrow_mut().push(Rc::clone(tr));
+        Ok(())
+    }
+
+    // Add a single method, replacing any method of the same name. The
+    // function takes the instance as its first parameter, since 'this' is
+    // only available inside class bodies.
+    pub fn define(&self, name: &Object, function: &Object) -> Result<(), LoxResult> {
+        let name = match name {
+            Object::Str(name) if !is_private(name) => name,
+            _ => {
+                return Err(LoxResult::system_error(
+                    "The name of a defined method must be a public name",
+                ))
+            }
+        };
+        let method = match function {
+            Object::Func(function) => function.with_receiver_param(),
+            _ => None,
+        }
+        .ok_or_else(|| {
+            LoxResult::system_error("Can only define functions taking the instance as a parameter")
+        })?;
+        self.methods
+            .borrow_mut()
+            .insert(name.clone(), Object::Func(Rc::new(method)));
+        Ok(())
+    }
+
+    // Assigning a static field always sets it on the class it is accessed
+    // through, shadowing any field of the same name in a superclass
+    pub fn set(&self, name: &Token, object: Object) {
+        self.static_fields
+            .borrow_mut()
+            .insert(name.lexeme.clone(), object);
+    }
 }
 
 impl fmt::Display for LoxClass {
@@ -67,13 +487,61 @@ impl LoxCallable for LoxClass {
     ) -> Result<Object, LoxResult> {
         self.instantiate(interpreter, arguments, klass.unwrap())
     }
-    fn arity(&self) -> usize {
+    fn arity(&self) -> Arity {
         // A class does not need to have an initializer, but if it does,
-        // then use that arity, else use 0
-        if let Some(Object::Func(initializer)) = self.find_method("init".to_string()) {
-            initializer.arity()
-        } else {
-            0
+        // then use that arity, else use 0 (or the fields of a record)
+        let Some(klass) = self.initializing_class() else {
+            return Arity::exact(0);
+        };
+        match klass.methods.borrow().get("init") {
+            Some(Object::Func(initializer)) => initializer.arity(),
+            _ => Arity::exact(klass.record_fields().unwrap_or_default().len()),
+        }
+    }
+}
+
+// The generated initializer of a record sets the positional fields, then
+// runs the initializer of the superclass, which must not take arguments
+struct RecordInitializer {
+    instance: Rc<LoxInstance>,
+    fields: Vec<String>,
+    superclass: Option<Object>,
+}
+
+impl LoxCallable for RecordInitializer {
+    fn call(
+        &self,
+        interpreter: &Interpreter,
+        arguments: Vec<Object>,
+        _klass: Option<Rc<LoxClass>>,
+    ) -> Result<Object, LoxResult> {
+        for (name, value) in self.fields.iter().zip(arguments) {
+            self.instance.set_field(name, value);
         }
+        if let Some(Object::Func(initializer)) = &self.superclass {
+            if !initializer.arity().accepts(0) {
+                return Err(LoxResult::system_error(&format!(
+                    "The superclass initializer of record {} can't take arguments",
+                    self.instance.klass.name
+                )));
+            }
+            initializer.call(interpreter, Vec::new(), None)?;
+        }
+        Ok(Object::Instance(Rc::clone(&self.instance)))
+    }
+
+    // Named arguments of a call to a record are its positional fields
+    fn param_names(&self) -> Vec<String> {
+        self.fields.clone()
+    }
+
+    fn arity(&self) -> Arity {
+        Arity::exact(self.fields.len())
+    }
+}
+
+impl PartialEq for LoxClass {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
     }
 }
@@ -0,0 +1,184 @@
+use crate::callable::*;
+use crate::error::*;
+use crate::interpreter::*;
+use crate::lox_class::LoxClass;
+use crate::object::*;
+use crate::token::*;
+use std::fmt;
+use std::rc::Rc;
+
+// A variant as written in an enum declaration: 'Red' or 'Circle(r)'
+#[derive(Clone)]
+pub struct VariantDecl {
+    pub name: Token,
+    pub fields: Vec<Token>,
+}
+
+#[derive(Debug)]
+pub struct EnumVariant {
+    pub enum_name: String,
+    pub name: String,
+    pub fields: Vec<String>,
+}
+
+// The namespace created by 'enum Color { Red, Green }'. Variants without
+// data are single values, so they compare by identity. Variants with data
+// are constructors of values carrying it, shown by name like the values.
+#[derive(Debug)]
+pub struct LoxEnum {
+    pub name: String,
+    members: Vec<(Rc<EnumVariant>, Object)>,
+}
+
+impl LoxEnum {
+    pub fn new(name: &str, declarations: &[VariantDecl]) -> Self {
+        let members = declarations
+            .iter()
+            .map(|decl| {
+                let variant = Rc::new(EnumVariant {
+                    enum_name: name.to_owned(),
+                    name: decl.name.lexeme.clone(),
+                    fields: decl.fields.iter().map(|f| f.lexeme.clone()).collect(),
+                });
+                let member = if variant.fields.is_empty() {
+                    Object::EnumValue(Rc::new(LoxEnumValue::new(&variant, Vec::new())))
+                } else {
+                    Object::Variant(Rc::new(VariantConstructor {
+                        variant: Rc::clone(&variant),
+                    }))
+                };
+                (variant, member)
+            })
+            .collect();
+        Self {
+            name: name.to_owned(),
+            members,
+        }
+    }
+
+    pub fn variant(&self, name: &str) -> Option<Rc<EnumVariant>> {
+        self.members
+            .iter()
+            .find(|(variant, _)| variant.name == name)
+            .map(|(variant, _)| Rc::clone(variant))
+    }
+
+    // The values and constructors of the variants in declaration order
+    pub fn members(&self) -> Vec<Object> {
+        self.members
+            .iter()
+            .map(|(_, member)| member.clone())
+            .collect()
+    }
+
+    pub fn get(&self, name: &Token) -> Result<Object, LoxResult> {
+        self.members
+            .iter()
+            .find(|(variant, _)| variant.name == name.lexeme)
+            .map(|(_, member)| member.clone())
+            .ok_or_else(|| self.undefined_variant(name))
+    }
+
+    pub fn undefined_variant(&self, name: &Token) -> LoxResult {
+        LoxResult::error_runtime(
+            name,
+            &format!("Enum '{}' has no variant '{}'", self.name, name.lexeme),
+        )
+    }
+}
+
+// Calling a variant with data creates a value of it
+#[derive(Debug)]
+pub struct VariantConstructor {
+    pub variant: Rc<EnumVariant>,
+}
+
+impl LoxCallable for VariantConstructor {
+    fn call(
+        &self,
+        _: &Interpreter,
+        arguments: Vec<Object>,
+        _klass: Option<Rc<LoxClass>>,
+    ) -> Result<Object, LoxResult> {
+        Ok(Object::EnumValue(Rc::new(LoxEnumValue::new(
+            &self.variant,
+            arguments,
+        ))))
+    }
+
+    fn param_names(&self) -> Vec<String> {
+        self.variant.fields.clone()
+    }
+
+    fn arity(&self) -> Arity {
+        Arity::exact(self.variant.fields.len())
+    }
+}
+
+#[derive(Debug)]
+pub struct LoxEnumValue {
+    pub variant: Rc<EnumVariant>,
+    values: Vec<Object>,
+}
+
+impl LoxEnumValue {
+    pub fn new(variant: &Rc<EnumVariant>, values: Vec<Object>) -> Self {
+        Self {
+            variant: Rc::clone(variant),
+            values,
+        }
+    }
+
+    pub fn get_field(&self, name: &str) -> Option<Object> {
+        let index = self.variant.fields.iter().position(|f| f == name)?;
+        self.values.get(index).cloned()
+    }
+
+    // The associated data is read like fields: 'shape.r'
+    pub fn get(&self, name: &Token) -> Result<Object, LoxResult> {
+        self.get_field(&name.lexeme).ok_or_else(|| {
+            LoxResult::error_runtime(name, &format!("Undefined property {}", name.lexeme))
+        })
+    }
+}
+
+impl fmt::Display for LoxEnum {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<enum {}>", self.name)
+    }
+}
+
+impl fmt::Display for VariantConstructor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}", self.variant.enum_name, self.variant.name)
+    }
+}
+
+impl fmt::Display for LoxEnumValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}", self.variant.enum_name, self.variant.name)?;
+        if !self.values.is_empty() {
+            let values: Vec<String> = self.values.iter().map(|v| v.to_string()).collect();
+            write!(f, "({})", values.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl PartialEq for LoxEnum {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl PartialEq for VariantConstructor {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.variant, &other.variant)
+    }
+}
+
+impl PartialEq for LoxEnumValue {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
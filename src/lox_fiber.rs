@@ -0,0 +1,208 @@
+use crate::callable::*;
+use crate::error::*;
+use crate::functions_lox::LoxFunction;
+use crate::functions_native::NativeMethod;
+use crate::interpreter::*;
+use crate::object::*;
+use crate::token::*;
+use corosensei::stack::{DefaultStack, Stack};
+use corosensei::{Coroutine, CoroutineResult, Yielder};
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::rc::{Rc, Weak};
+
+// The same size as the stack of the main thread
+const STACK_SIZE: usize = 8 * 1024 * 1024;
+// Calls fail with a stack overflow error when less than this is left of
+// the stack. Running into the guard page below a fiber's stack would kill
+// the process, as Rust only handles overflowing the stack of a thread.
+const STACK_RESERVE: usize = 1024 * 1024;
+
+type FiberCoroutine = Coroutine<Object, Object, Result<Object, LoxResult>>;
+
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+    New,
+    Suspended,
+    Running,
+    Done,
+}
+
+// A fiber runs a function on a call stack of its own, switched to explicitly
+// with 'resume(value)'. The first resume starts the function, passing 'value'
+// as its argument if it takes one. After that, 'value' becomes the result of
+// the 'yield' or 'suspend' the fiber is suspended at. 'resume' returns the
+// value handed to 'yield' or 'suspend', or the function's return value once
+// it has finished.
+//
+// Since the interpreter's own call stack is switched, the fiber can be
+// suspended anywhere, however deep in the functions it calls: by 'yield' in
+// the body of its function, and by the 'suspend(value)' native in any other
+// function. Calling a function containing 'yield' still creates a generator.
+pub struct LoxFiber {
+    function: Rc<LoxFunction>,
+    state: Cell<State>,
+    // Taken out while the fiber is running
+    coroutine: RefCell<Option<FiberCoroutine>>,
+    // Suspends the coroutine. Set when the fiber starts running. It points
+    // into the coroutine's stack, where corosensei keeps the yielder until
+    // the coroutine returns. It is only used by 'suspend' while the fiber is
+    // running, so never after the coroutine has returned or been dropped.
+    yielder: Cell<*const Yielder<Object, Object>>,
+    // The lowest address of the coroutine's stack. Set when the fiber starts.
+    stack_limit: Cell<usize>,
+}
+
+impl LoxFiber {
+    pub fn new(function: Rc<LoxFunction>) -> Self {
+        Self {
+            function,
+            state: Cell::new(State::New),
+            coroutine: RefCell::new(None),
+            yielder: Cell::new(std::ptr::null()),
+            stack_limit: Cell::new(0),
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.state.get() == State::Done
+    }
+
+    fn start(this: &Rc<LoxFiber>, interpreter: &Interpreter) -> Result<FiberCoroutine, LoxResult> {
+        let stack = DefaultStack::new(STACK_SIZE).map_err(|e| {
+            LoxResult::system_error(&format!("Can't allocate a stack for a fiber: {}", e))
+        })?;
+        this.stack_limit.set(stack.limit().get());
+        let fiber = Rc::downgrade(this);
+        let function = Rc::clone(&this.function);
+        // The coroutine outlives this borrow of the interpreter, so it holds a
+        // pointer to it instead. See the SAFETY comment below.
+        let interpreter: *const Interpreter = interpreter;
+        Ok(Coroutine::with_stack(
+            stack,
+            move |yielder: &Yielder<Object, Object>, value: Object| {
+                if let Some(fiber) = Weak::upgrade(&fiber) {
+                    fiber.yielder.set(yielder);
+                }
+                // SAFETY: a program has a single interpreter, which outlives
+                // its values, fibers included. The coroutine only runs inside
+                // 'resume', which borrows that interpreter, so the pointer is
+                // valid whenever it runs. Dropping a suspended fiber unwinds
+                // its stack without running any more of the program, so the
+                // interpreter isn't used after that.
+                let interpreter = unsafe { &*interpreter };
+                let arguments = if function.arity().max != Some(0) {
+                    vec![value]
+                } else {
+                    Vec::new()
+                };
+                function.run(interpreter, arguments)
+            },
+        ))
+    }
+
+    // Errors raised inside the fiber finish it and propagate to the resumer.
+    // The resumer's environment is restored whichever way the fiber stops.
+    fn resume(
+        this: &Rc<LoxFiber>,
+        interpreter: &Interpreter,
+        value: Object,
+    ) -> Result<Object, LoxResult> {
+        let mut coroutine = match this.state.get() {
+            State::New => Self::start(this, interpreter)?,
+            State::Suspended => this.coroutine.take().unwrap(),
+            State::Running | State::Done => {
+                let state = if this.is_done() { "done" } else { "running" };
+                return Err(LoxResult::system_error(&format!(
+                    "Can't resume fiber '{}' which is {}",
+                    this.function.name(),
+                    state
+                )));
+            }
+        };
+        this.state.set(State::Running);
+        let environment = interpreter.environment();
+        interpreter.fibers.borrow_mut().push(Rc::clone(this));
+        let result = coroutine.resume(value);
+        interpreter.fibers.borrow_mut().pop();
+        interpreter.set_environment(environment);
+        match result {
+            CoroutineResult::Yield(value) => {
+                this.coroutine.replace(Some(coroutine));
+                this.state.set(State::Suspended);
+                Ok(value)
+            }
+            CoroutineResult::Return(result) => {
+                this.state.set(State::Done);
+                result
+            }
+        }
+    }
+
+    // Switch from the running fiber back to its resumer, handing it 'value'.
+    // Returns the value the fiber is resumed with next.
+    pub fn suspend(this: Rc<LoxFiber>, interpreter: &Interpreter, value: Object) -> Object {
+        let yielder = this.yielder.get();
+        // The stack of a suspended fiber mustn't keep the fiber alive
+        drop(this);
+        let environment = interpreter.environment();
+        // SAFETY: 'this' is the running fiber, the last one the interpreter
+        // resumes, so we are on its coroutine's stack, which holds the
+        // yielder until the coroutine returns
+        let value = unsafe { &*yielder }.suspend(value);
+        interpreter.set_environment(environment);
+        value
+    }
+
+    // Checked for the running fiber whenever a function runs, so that deep
+    // recursion fails with an error rather than overflowing the fiber's stack
+    pub fn check_stack(&self, token: &Token) -> Result<(), LoxResult> {
+        let marker = 0u8;
+        let address = std::ptr::addr_of!(marker) as usize;
+        if address < self.stack_limit.get() + STACK_RESERVE {
+            return Err(LoxResult::error_runtime(
+                token,
+                &format!("Stack overflow in fiber '{}'", self.function.name()),
+            ));
+        }
+        Ok(())
+    }
+
+    // Fibers have the methods 'resume(value)' and 'isDone()'
+    pub fn get(&self, name: &Token, this: &Rc<LoxFiber>) -> Result<Object, LoxResult> {
+        match name.lexeme.as_str() {
+            "resume" => Ok(NativeMethod::bind(
+                Rc::new(Rc::clone(this)),
+                1,
+                |f, interpreter, mut arguments| {
+                    LoxFiber::resume(f, interpreter, arguments.remove(0))
+                },
+            )),
+            "isDone" => Ok(NativeMethod::bind(Rc::clone(this), 0, |f, _, _| {
+                Ok(Object::Bool(f.is_done()))
+            })),
+            _ => Err(LoxResult::error_runtime(
+                name,
+                &format!("Undefined property {}", name.lexeme),
+            )),
+        }
+    }
+}
+
+impl fmt::Display for LoxFiber {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<fiber {}>", self.function.name())
+    }
+}
+
+impl fmt::Debug for LoxFiber {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{self}")
+    }
+}
+
+impl PartialEq for LoxFiber {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
@@ -0,0 +1,376 @@
+use crate::environment::*;
+use crate::error::*;
+use crate::expr::*;
+use crate::functions_native::NativeMethod;
+use crate::interpreter::*;
+use crate::lox_iterator::LoxIterator;
+use crate::object::*;
+use crate::stmt::*;
+use crate::token::*;
+use std::cell::RefCell;
+use std::fmt;
+use std::ops::Deref;
+use std::rc::Rc;
+
+// True if the statement can suspend a generator. The resolver only allows
+// 'yield' as a statement, a variable initializer or the value assigned to a
+// variable, so those are the only places to look for one. Nested functions
+// are generators of their own and are not looked into.
+pub fn contains_yield(stmt: &Stmt) -> bool {
+    match stmt {
+        Stmt::Expression(stmt) => match stmt.expression.deref() {
+            Expr::Assign(assign) => matches!(assign.value.deref(), Expr::Yield(_)),
+            expr => matches!(expr, Expr::Yield(_)),
+        },
+        Stmt::Var(stmt) => matches!(stmt.initializer.as_deref(), Some(Expr::Yield(_))),
+        Stmt::Block(stmt) => stmt.statements.iter().any(|s| contains_yield(s)),
+        Stmt::If(stmt) => {
+            contains_yield(&stmt.then_branch)
+                || stmt.else_branch.as_ref().is_some_and(|s| contains_yield(s))
+        }
+        Stmt::While(stmt) => contains_yield(&stmt.body),
+        Stmt::ForIn(stmt) => contains_yield(&stmt.body),
+        Stmt::Match(stmt) => {
+            stmt.cases.iter().any(|case| contains_yield(&case.body))
+                || stmt.default.as_ref().is_some_and(|s| contains_yield(s))
+        }
+        _ => false,
+    }
+}
+
+// The recursive 'execute' can't stop halfway through a statement, so the
+// statements of a generator that contain a 'yield' are run from an explicit
+// stack of frames instead. Every other statement is handed to the
+// interpreter as a whole.
+enum Frame {
+    // Statements run in order in 'env'
+    Block {
+        stmts: Rc<Vec<Rc<Stmt>>>,
+        next: usize,
+        env: Rc<RefCell<Environment>>,
+    },
+    While {
+        stmt: Rc<WhileStmt>,
+        env: Rc<RefCell<Environment>>,
+    },
+    ForIn {
+        stmt: Rc<ForInStmt>,
+        iterator: LoxIterator,
+        env: Rc<RefCell<Environment>>,
+    },
+}
+
+impl Frame {
+    fn block(stmts: Rc<Vec<Rc<Stmt>>>, env: Rc<RefCell<Environment>>) -> Frame {
+        Frame::Block {
+            stmts,
+            next: 0,
+            env,
+        }
+    }
+}
+
+// What to do with the value the generator is resumed with
+enum Resume {
+    Discard,
+    // 'var name = yield ...;'
+    Define(String, Rc<RefCell<Environment>>),
+    // 'name = yield ...;' where the expression is the assignment
+    Assign(Rc<Expr>, Rc<RefCell<Environment>>),
+}
+
+enum Step {
+    Continue,
+    Push(Frame),
+    Pop,
+    Yield(Object, Resume),
+}
+
+enum State {
+    Suspended(Vec<Frame>, Resume),
+    Running,
+    Done,
+}
+
+// The object returned by calling a generator function. Nothing in the body
+// runs until the first value is asked for.
+pub struct LoxGenerator {
+    name: String,
+    state: RefCell<State>,
+    // A value produced ahead of time by 'hasNext'
+    peeked: RefCell<Option<Object>>,
+}
+
+impl LoxGenerator {
+    pub fn new(name: &str, body: &Rc<Vec<Rc<Stmt>>>, env: Rc<RefCell<Environment>>) -> Self {
+        let frame = Frame::block(Rc::clone(body), env);
+        Self {
+            name: name.to_string(),
+            state: RefCell::new(State::Suspended(vec![frame], Resume::Discard)),
+            peeked: RefCell::new(None),
+        }
+    }
+
+    // Run the body until the next 'yield' and return the value yielded, or
+    // None once the body has finished. An error also finishes the generator.
+    pub fn resume(
+        &self,
+        interpreter: &Interpreter,
+        value: Object,
+    ) -> Result<Option<Object>, LoxResult> {
+        let (mut frames, resume) = match self.state.replace(State::Running) {
+            State::Suspended(frames, resume) => (frames, resume),
+            State::Running => {
+                return Err(LoxResult::system_error(&format!(
+                    "Generator '{}' is already running",
+                    self.name
+                )))
+            }
+            State::Done => {
+                self.state.replace(State::Done);
+                return Ok(None);
+            }
+        };
+        match Self::run(interpreter, &mut frames, resume, value) {
+            Ok(Some((value, resume))) => {
+                self.state.replace(State::Suspended(frames, resume));
+                Ok(Some(value))
+            }
+            result => {
+                self.state.replace(State::Done);
+                result.map(|_| None)
+            }
+        }
+    }
+
+    // The next value for iteration, including one produced by 'hasNext'
+    pub fn next_value(&self, interpreter: &Interpreter) -> Result<Option<Object>, LoxResult> {
+        match self.peeked.take() {
+            Some(value) => Ok(Some(value)),
+            None => self.resume(interpreter, Object::Nil),
+        }
+    }
+
+    fn has_next(&self, interpreter: &Interpreter) -> Result<bool, LoxResult> {
+        if self.peeked.borrow().is_none() {
+            let value = self.resume(interpreter, Object::Nil)?;
+            self.peeked.replace(value);
+        }
+        Ok(self.peeked.borrow().is_some())
+    }
+
+    fn send(&self, interpreter: &Interpreter, value: Object) -> Result<Option<Object>, LoxResult> {
+        if self.peeked.borrow().is_some() {
+            return Err(LoxResult::system_error(&format!(
+                "Can't send to generator '{}' after 'hasNext' has run it ahead",
+                self.name
+            )));
+        }
+        self.resume(interpreter, value)
+    }
+
+    // Generators have the methods 'next()', 'hasNext()' and 'send(value)'.
+    // 'next' and 'send' return nil once the generator has finished.
+    pub fn get(&self, name: &Token, this: &Rc<LoxGenerator>) -> Result<Object, LoxResult> {
+        let this = Rc::clone(this);
+        match name.lexeme.as_str() {
+            "next" => Ok(NativeMethod::bind(this, 0, |g, interpreter, _| {
+                Ok(g.next_value(interpreter)?.unwrap_or(Object::Nil))
+            })),
+            "hasNext" => Ok(NativeMethod::bind(this, 0, |g, interpreter, _| {
+                Ok(Object::Bool(g.has_next(interpreter)?))
+            })),
+            "send" => Ok(NativeMethod::bind(
+                this,
+                1,
+                |g, interpreter, mut arguments| {
+                    Ok(g.send(interpreter, arguments.remove(0))?
+                        .unwrap_or(Object::Nil))
+                },
+            )),
+            _ => Err(LoxResult::error_runtime(
+                name,
+                &format!("Undefined property {}", name.lexeme),
+            )),
+        }
+    }
+
+    fn run(
+        interpreter: &Interpreter,
+        frames: &mut Vec<Frame>,
+        resume: Resume,
+        value: Object,
+    ) -> Result<Option<(Object, Resume)>, LoxResult> {
+        match resume {
+            Resume::Discard => {}
+            Resume::Define(name, env) => env.borrow_mut().define(&name, value),
+            Resume::Assign(expr, env) => {
+                if let Expr::Assign(assign) = expr.deref() {
+                    interpreter.with_environment(&env, || {
+                        interpreter.assign_variable(expr.clone(), &assign.name, value)
+                    })?;
+                }
+            }
+        }
+
+        while let Some(frame) = frames.last_mut() {
+            match Self::step(interpreter, frame) {
+                Ok(Step::Continue) => {}
+                Ok(Step::Push(frame)) => frames.push(frame),
+                Ok(Step::Pop) => {
+                    frames.pop();
+                }
+                Ok(Step::Yield(value, resume)) => return Ok(Some((value, resume))),
+                // Unwind up to and including the innermost loop
+                Err(LoxResult::Break) => {
+                    while let Some(frame) = frames.pop() {
+                        if !matches!(frame, Frame::Block { .. }) {
+                            break;
+                        }
+                    }
+                }
+                // The value a generator returns is not used
+                Err(LoxResult::ReturnValue { .. }) => return Ok(None),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(None)
+    }
+
+    fn step(interpreter: &Interpreter, frame: &mut Frame) -> Result<Step, LoxResult> {
+        match frame {
+            Frame::Block { stmts, next, env } => match stmts.get(*next) {
+                Some(stmt) => {
+                    *next += 1;
+                    Self::enter(interpreter, stmt.clone(), env)
+                }
+                None => Ok(Step::Pop),
+            },
+            Frame::While { stmt, env } => {
+                let condition = interpreter
+                    .with_environment(env, || interpreter.evaluate(stmt.condition.clone()))?;
+                if Interpreter::is_truthy(&condition) {
+                    let body = Rc::new(vec![stmt.body.clone()]);
+                    Ok(Step::Push(Frame::block(body, env.clone())))
+                } else {
+                    Ok(Step::Pop)
+                }
+            }
+            // Like 'visit_forin_stmt', every iteration gets an environment
+            // of its own holding the loop variable
+            Frame::ForIn {
+                stmt,
+                iterator,
+                env,
+            } => match iterator.next(interpreter, &stmt.name)? {
+                Some(item) => {
+                    let mut e = Environment::new_enclosing(env.clone());
+                    e.define(&stmt.name.lexeme, item);
+                    let body = Rc::new(vec![stmt.body.clone()]);
+                    Ok(Step::Push(Frame::block(body, Rc::new(RefCell::new(e)))))
+                }
+                None => Ok(Step::Pop),
+            },
+        }
+    }
+
+    // Start running a statement. Environments are created exactly where the
+    // interpreter would create them so that resolved variables are found.
+    fn enter(
+        interpreter: &Interpreter,
+        stmt: Rc<Stmt>,
+        env: &Rc<RefCell<Environment>>,
+    ) -> Result<Step, LoxResult> {
+        if !contains_yield(&stmt) {
+            interpreter.with_environment(env, || interpreter.execute(stmt))?;
+            return Ok(Step::Continue);
+        }
+        let evaluate = |expr: &Rc<Expr>| {
+            interpreter.with_environment(env, || interpreter.evaluate(expr.clone()))
+        };
+        let yielded = |expr: &YieldExpr| match &expr.value {
+            Some(value) => evaluate(value),
+            None => Ok(Object::Nil),
+        };
+        match stmt.deref() {
+            Stmt::Block(block) => {
+                let e = Environment::new_enclosing(env.clone());
+                Ok(Step::Push(Frame::block(
+                    block.statements.clone(),
+                    Rc::new(RefCell::new(e)),
+                )))
+            }
+            Stmt::If(stmt) => {
+                let branch = if Interpreter::is_truthy(&evaluate(&stmt.condition)?) {
+                    Some(stmt.then_branch.clone())
+                } else {
+                    stmt.else_branch.clone()
+                };
+                match branch {
+                    Some(branch) => {
+                        Ok(Step::Push(Frame::block(Rc::new(vec![branch]), env.clone())))
+                    }
+                    None => Ok(Step::Continue),
+                }
+            }
+            Stmt::Match(stmt) => {
+                let (body, e) =
+                    interpreter.with_environment(env, || interpreter.select_case(stmt))?;
+                Ok(Step::Push(Frame::block(Rc::new(vec![body]), e)))
+            }
+            Stmt::While(stmt) => Ok(Step::Push(Frame::While {
+                stmt: stmt.clone(),
+                env: env.clone(),
+            })),
+            Stmt::ForIn(stmt) => {
+                let iterable = evaluate(&stmt.iterable)?;
+                Ok(Step::Push(Frame::ForIn {
+                    stmt: stmt.clone(),
+                    iterator: LoxIterator::new(interpreter, iterable, &stmt.name)?,
+                    env: env.clone(),
+                }))
+            }
+            Stmt::Expression(expression) => match expression.expression.deref() {
+                Expr::Yield(expr) => Ok(Step::Yield(yielded(expr)?, Resume::Discard)),
+                Expr::Assign(assign) => match assign.value.deref() {
+                    Expr::Yield(expr) => Ok(Step::Yield(
+                        yielded(expr)?,
+                        Resume::Assign(expression.expression.clone(), env.clone()),
+                    )),
+                    _ => Err(LoxResult::system_error(
+                        "Expected a yield in the assignment",
+                    )),
+                },
+                _ => Err(LoxResult::system_error("Expected a yield statement")),
+            },
+            Stmt::Var(var) => match var.initializer.as_deref() {
+                Some(Expr::Yield(expr)) => Ok(Step::Yield(
+                    yielded(expr)?,
+                    Resume::Define(var.name.lexeme.clone(), env.clone()),
+                )),
+                _ => Err(LoxResult::system_error(
+                    "Expected a yield in the initializer",
+                )),
+            },
+            _ => Err(LoxResult::system_error("Statement can't be suspended")),
+        }
+    }
+}
+
+impl fmt::Display for LoxGenerator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<generator {}>", self.name)
+    }
+}
+
+impl fmt::Debug for LoxGenerator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{self}")
+    }
+}
+
+impl PartialEq for LoxGenerator {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
@@ -1,4 +1,7 @@
+use crate::callable::*;
 use crate::error::*;
+use crate::functions_native::LoxNative;
+use crate::interpreter::*;
 use crate::lox_class::*;
 use crate::object::*;
 use crate::token::*;
@@ -8,9 +11,9 @@ use std::collections::HashMap;
 use std::fmt;
 use std::{fmt::Display, rc::Rc};
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Clone)]
 pub struct LoxInstance {
-    klass: Rc<LoxClass>,
+    pub klass: Rc<LoxClass>,
     fields: RefCell<HashMap<String, Object>>,
 }
 
@@ -33,15 +36,97 @@ impl LoxInstance {
                     "Cannot bind 'this' to a non-function method",
                 ))
             }
+        } else if let Some(fields) = self.klass.record_fields().filter(|_| name.lexeme == "copy") {
+            Ok(Object::Native(Rc::new(LoxNative {
+                func: Rc::new(RecordCopy {
+                    instance: Rc::clone(this),
+                    fields,
+                }),
+            })))
         } else {
             Err(LoxResult::error_runtime(
                 name,
-                &format!("Undefined property {}", name.lexeme),
+                &format!("Undefined property {}", source_name(&name.lexeme)),
             ))
         }
     }
-    pub fn set(&self, name: &Token, object: Object) {
-        self.fields.borrow_mut().insert(name.lexeme.clone(), object);
+    // Instances of strict classes only take the fields their class declares
+    pub fn set(&self, name: &Token, object: Object) -> Result<(), LoxResult> {
+        if self.klass.is_strict() && !self.klass.declares_field(&name.lexeme) {
+            return Err(LoxResult::error_runtime(
+                name,
+                &format!(
+                    "Can't assign undeclared field '{}' of strict class {}",
+                    source_name(&name.lexeme),
+                    self.klass.name
+                ),
+            ));
+        }
+        self.set_field(&name.lexeme, object);
+        Ok(())
+    }
+    pub fn get_field(&self, name: &str) -> Option<Object> {
+        self.fields.borrow().get(name).cloned()
+    }
+    pub fn set_field(&self, name: &str, object: Object) {
+        self.fields.borrow_mut().insert(name.to_string(), object);
+    }
+    // Public fields ordered by name so that anything listing them is deterministic
+    pub fn sorted_fields(&self) -> Vec<(String, Object)> {
+        let mut fields: Vec<(String, Object)> = self
+            .fields
+            .borrow()
+            .iter()
+            .filter(|(k, _)| !is_private(k))
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        fields.sort_by(|a, b| a.0.cmp(&b.0));
+        fields
+    }
+}
+
+// 'point.copy(y: 0)' on a record creates a copy of it with some of the
+// positional fields changed. The fields left out keep their value.
+struct RecordCopy {
+    instance: Rc<LoxInstance>,
+    fields: Vec<String>,
+}
+
+impl LoxCallable for RecordCopy {
+    fn call(
+        &self,
+        _: &Interpreter,
+        arguments: Vec<Object>,
+        _klass: Option<Rc<LoxClass>>,
+    ) -> Result<Object, LoxResult> {
+        let copy = LoxInstance {
+            klass: Rc::clone(&self.instance.klass),
+            fields: self.instance.fields.clone(),
+        };
+        for (name, value) in self.fields.iter().zip(arguments) {
+            copy.set_field(name, value);
+        }
+        Ok(Object::Instance(Rc::new(copy)))
+    }
+
+    fn param_names(&self) -> Vec<String> {
+        self.fields.clone()
+    }
+
+    fn default_argument(
+        &self,
+        _: &Interpreter,
+        index: usize,
+        _: &[Object],
+    ) -> Result<Option<Object>, LoxResult> {
+        Ok(self.instance.get_field(&self.fields[index]))
+    }
+
+    fn arity(&self) -> Arity {
+        Arity {
+            min: 0,
+            max: Some(self.fields.len()),
+        }
     }
 }
 
@@ -50,3 +135,18 @@ impl Display for LoxInstance {
         write!(f, "instance of {}", self.klass.name)
     }
 }
+
+// Fields may refer back to the instance itself, so never print them here
+impl fmt::Debug for LoxInstance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{self}")
+    }
+}
+
+// Instances are only equal to themselves. Classes that want value equality
+// define an 'equals' method which the interpreter calls for '=='.
+impl PartialEq for LoxInstance {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
@@ -0,0 +1,116 @@
+use crate::error::*;
+use crate::interpreter::*;
+use crate::lox_generator::LoxGenerator;
+use crate::lox_instance::LoxInstance;
+use crate::lox_list::LoxList;
+use crate::lox_range::LoxRange;
+use crate::lox_tuple::LoxTuple;
+use crate::object::*;
+use crate::token::*;
+use std::rc::Rc;
+
+// Iteration state of a 'for-in' loop. Instances take part by defining
+// '__iter__', which returns an iterator object with '__hasnext__' and
+// '__next__' methods. An instance that defines those two methods itself
+// is its own iterator.
+pub enum LoxIterator {
+    Chars(Vec<char>, usize),
+    List(Rc<LoxList>, usize),
+    Tuple(Rc<LoxTuple>, usize),
+    Range(Rc<LoxRange>, i64),
+    Instance(Rc<LoxInstance>),
+    Generator(Rc<LoxGenerator>),
+}
+
+impl LoxIterator {
+    pub fn new(
+        interpreter: &Interpreter,
+        iterable: Object,
+        token: &Token,
+    ) -> Result<LoxIterator, LoxResult> {
+        match iterable {
+            Object::Str(s) => Ok(LoxIterator::Chars(s.chars().collect(), 0)),
+            Object::List(list) => Ok(LoxIterator::List(list, 0)),
+            Object::Tuple(tuple) => Ok(LoxIterator::Tuple(tuple, 0)),
+            Object::Range(range) => Ok(LoxIterator::Range(range, 0)),
+            // An enum iterates over its variants in declaration order
+            Object::Enum(enumeration) => Ok(LoxIterator::List(
+                Rc::new(LoxList::new(enumeration.members())),
+                0,
+            )),
+            Object::Generator(generator) => Ok(LoxIterator::Generator(generator)),
+            Object::Instance(inst) => {
+                if let Some(iter) = Interpreter::bind_method(&inst, "__iter__") {
+                    match interpreter.call_object(iter, Vec::new(), token)? {
+                        Object::Instance(iterator) => Self::from_instance(iterator, token),
+                        iterable => Self::new(interpreter, iterable, token),
+                    }
+                } else {
+                    Self::from_instance(inst, token)
+                }
+            }
+            _ => Err(LoxResult::error_runtime(
+                token,
+                &format!("Can't iterate over a {}", iterable.type_name()),
+            )),
+        }
+    }
+
+    fn from_instance(inst: Rc<LoxInstance>, token: &Token) -> Result<LoxIterator, LoxResult> {
+        let has_method = |name: &str| inst.klass.find_method(name.to_string()).is_some();
+        if has_method("__hasnext__") && has_method("__next__") {
+            Ok(LoxIterator::Instance(inst))
+        } else {
+            Err(LoxResult::error_runtime(
+                token,
+                &format!(
+                    "Can't iterate over {}. Define an '__iter__' method or '__hasnext__' and '__next__'",
+                    inst
+                ),
+            ))
+        }
+    }
+
+    // The next item, or None once the iterator is exhausted. Lists are read
+    // one index at a time so the loop sees changes made while iterating.
+    pub fn next(
+        &mut self,
+        interpreter: &Interpreter,
+        token: &Token,
+    ) -> Result<Option<Object>, LoxResult> {
+        match self {
+            LoxIterator::Chars(chars, index) => {
+                let item = chars.get(*index).map(|c| Object::Str(c.to_string()));
+                *index += 1;
+                Ok(item)
+            }
+            LoxIterator::List(list, index) => {
+                let item = list.get(*index);
+                *index += 1;
+                Ok(item)
+            }
+            LoxIterator::Tuple(tuple, index) => {
+                let item = tuple.get(*index);
+                *index += 1;
+                Ok(item)
+            }
+            LoxIterator::Range(range, index) => {
+                let item = range.get(*index).map(Object::Int);
+                *index += 1;
+                Ok(item)
+            }
+            LoxIterator::Instance(inst) => {
+                let call = |name| match Interpreter::bind_method(inst, name) {
+                    Some(method) => interpreter.call_object(method, Vec::new(), token),
+                    None => Ok(Object::Nil),
+                };
+                if Interpreter::is_truthy(&call("__hasnext__")?) {
+                    Ok(Some(call("__next__")?))
+                } else {
+                    Ok(None)
+                }
+            }
+            LoxIterator::Generator(generator) => generator.next_value(interpreter),
+        }
+    }
+}
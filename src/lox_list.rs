@@ -0,0 +1,55 @@
+use crate::object::*;
+use std::cell::RefCell;
+use std::fmt;
+
+// A growable list of values. Lists are mutable and shared by reference, so
+// like instances they are only equal to themselves.
+pub struct LoxList {
+    items: RefCell<Vec<Object>>,
+}
+
+impl LoxList {
+    pub fn new(items: Vec<Object>) -> Self {
+        Self {
+            items: RefCell::new(items),
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.items.borrow().len()
+    }
+    pub fn get(&self, index: usize) -> Option<Object> {
+        self.items.borrow().get(index).cloned()
+    }
+    // Replace the item at 'index'. Returns false if it is out of range.
+    pub fn set(&self, index: usize, value: Object) -> bool {
+        match self.items.borrow_mut().get_mut(index) {
+            Some(item) => {
+                *item = value;
+                true
+            }
+            None => false,
+        }
+    }
+    pub fn items(&self) -> Vec<Object> {
+        self.items.borrow().clone()
+    }
+}
+
+// Items may refer back to the list itself, so never print them here
+impl fmt::Display for LoxList {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "list of length {}", self.len())
+    }
+}
+
+impl fmt::Debug for LoxList {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{self}")
+    }
+}
+
+impl PartialEq for LoxList {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
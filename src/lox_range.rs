@@ -0,0 +1,104 @@
+use crate::numeric;
+use crate::object::Object;
+use std::cmp::Ordering;
+use std::fmt;
+
+// A range of integers counting from 'start' towards 'end' in steps of
+// 'step', which can be negative. 'end' itself is only included by inclusive
+// ranges ('a..=b'). Ranges are lazy: the numbers are only produced while
+// iterating.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoxRange {
+    pub start: i64,
+    pub end: i64,
+    pub step: i64,
+    pub inclusive: bool,
+}
+
+impl LoxRange {
+    // None if the step is zero
+    pub fn new(start: i64, end: i64, step: i64) -> Option<Self> {
+        (step != 0).then_some(Self {
+            start,
+            end,
+            step,
+            inclusive: false,
+        })
+    }
+
+    // The range of 'a..=b', which includes 'last'
+    pub fn inclusive(start: i64, last: i64, step: i64) -> Option<Self> {
+        (step != 0).then_some(Self {
+            start,
+            end: last,
+            step,
+            inclusive: true,
+        })
+    }
+
+    // The number of integers in the range, which may not fit in an i64
+    fn count(&self) -> i128 {
+        let span = (self.end as i128 - self.start as i128) * self.step.signum() as i128;
+        let step = self.step.unsigned_abs() as i128;
+        if span < 0 || (span == 0 && !self.inclusive) {
+            0
+        } else if self.inclusive {
+            span / step + 1
+        } else {
+            (span + step - 1) / step
+        }
+    }
+
+    // None if the range has more than i64::MAX integers
+    pub fn len(&self) -> Option<i64> {
+        i64::try_from(self.count()).ok()
+    }
+
+    pub fn get(&self, index: i64) -> Option<i64> {
+        if index < 0 || index as i128 >= self.count() {
+            return None;
+        }
+        i64::try_from(self.start as i128 + index as i128 * self.step as i128).ok()
+    }
+
+    // Numbers are in the range when they equal one of its integers, so
+    // '3.0 in 0..5' like '3 in 0..5'
+    pub fn contains(&self, value: &Object) -> bool {
+        let value = match value {
+            Object::Int(i) => *i,
+            Object::Number(n) => {
+                let i = *n as i64;
+                if numeric::compare(&Object::Int(i), value) != Some(Ordering::Equal) {
+                    return false;
+                }
+                i
+            }
+            _ => return false,
+        };
+        let offset = value as i128 - self.start as i128;
+        let index = offset / self.step as i128;
+        offset % self.step as i128 == 0 && (0..self.count()).contains(&index)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = i64> + '_ {
+        (0..).map_while(|index| self.get(index))
+    }
+}
+
+// Inclusive ranges can't be created with 'range', so they are shown as
+// they are written
+impl fmt::Display for LoxRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.inclusive {
+            write!(f, "{}..={}", self.start, self.end)?;
+            if self.step != 1 {
+                write!(f, " step {}", self.step)?;
+            }
+            Ok(())
+        } else if self.step == 1 {
+            write!(f, "range({}, {})", self.start, self.end)
+        } else {
+            write!(f, "range({}, {}, {})", self.start, self.end, self.step)
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use crate::object::*;
+use std::collections::HashMap;
+use std::fmt;
+
+// A named set of methods that classes include with 'with'. The methods are
+// copied into each including class, so 'this' inside them refers to an
+// instance of that class.
+#[derive(Debug, Clone)]
+pub struct LoxTrait {
+    pub name: String,
+    pub methods: HashMap<String, Object>,
+}
+
+impl LoxTrait {
+    pub fn new(name: &str, methods: HashMap<String, Object>) -> Self {
+        Self {
+            name: name.to_owned(),
+            methods,
+        }
+    }
+}
+
+impl fmt::Display for LoxTrait {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<trait {}>", self.name)
+    }
+}
+
+impl PartialEq for LoxTrait {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
@@ -0,0 +1,35 @@
+use crate::object::*;
+use std::fmt;
+
+// A fixed sequence of values. Unlike lists, tuples can't be changed and
+// compare by their items.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoxTuple {
+    items: Vec<Object>,
+}
+
+impl LoxTuple {
+    pub fn new(items: Vec<Object>) -> Self {
+        Self { items }
+    }
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+    pub fn get(&self, index: usize) -> Option<Object> {
+        self.items.get(index).cloned()
+    }
+    pub fn items(&self) -> &[Object] {
+        &self.items
+    }
+}
+
+// A tuple of one item is written with a trailing comma: '(1,)'
+impl fmt::Display for LoxTuple {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let items: Vec<String> = self.items.iter().map(|item| item.to_string()).collect();
+        match items.as_slice() {
+            [item] => write!(f, "({},)", item),
+            _ => write!(f, "({})", items.join(", ")),
+        }
+    }
+}
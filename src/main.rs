@@ -11,14 +11,25 @@ use std::str;
 mod callable;
 mod environment;
 mod error;
+mod event_loop;
 mod expr;
 mod functions_lox;
 mod functions_native;
 mod interpreter;
 mod lox_class;
+mod lox_enum;
+mod lox_fiber;
+mod lox_generator;
 mod lox_instance;
+mod lox_iterator;
+mod lox_list;
+mod lox_range;
+mod lox_trait;
+mod lox_tuple;
+mod numeric;
 mod object;
 mod parser;
+mod pattern;
 mod resolver;
 mod scanner;
 mod stmt;
@@ -26,19 +37,30 @@ mod token;
 
 fn main() -> Result<(), LoxResult> {
     let args: Vec<String> = env::args().collect();
+    let (flags, paths): (Vec<&String>, Vec<&String>) =
+        args.iter().skip(1).partition(|arg| arg.starts_with("--"));
     let lox = Lox::new();
 
-    if args.len() == 2 {
-        lox.run_file(&args[1]).unwrap();
-    } else if args.len() == 1 {
-        lox.run_prompt()?;
-    } else {
-        println!("Usage: {} <lox-script>", args[0]);
-        process::exit(1);
+    for flag in flags {
+        match flag.as_str() {
+            // Timers fire without waiting, in a deterministic order
+            "--virtual-clock" => lox.interpreter.event_loop.use_virtual_clock(),
+            _ => usage(&args[0]),
+        }
+    }
+    match paths.as_slice() {
+        [path] => lox.run_file(path).unwrap(),
+        [] => lox.run_prompt()?,
+        _ => usage(&args[0]),
     }
     Ok(())
 }
 
+fn usage(program: &str) -> ! {
+    println!("Usage: {} [--virtual-clock] [<lox-script>]", program);
+    process::exit(1);
+}
+
 struct Lox {
     interpreter: Interpreter,
 }
@@ -94,7 +116,9 @@ impl Lox {
             let statements = Rc::new(stmts);
             resolver.resolve(&Rc::clone(&statements))?;
             if resolver.success() {
-                return self.interpreter.interpret(&Rc::clone(&statements));
+                self.interpreter.interpret(&Rc::clone(&statements))?;
+                // Run whatever the script scheduled
+                return self.interpreter.event_loop.run(&self.interpreter);
             }
         }
         Ok(())
@@ -0,0 +1,226 @@
+// Arithmetic on the numeric types. Integers are kept as an 'Int' (i64) for
+// as long as they fit and grow into a 'BigInt' when an operation overflows.
+// Mixing an integer with a 'Number' (f64) promotes the result to a 'Number'.
+use crate::object::*;
+use crate::token::*;
+use num_bigint::BigInt;
+use num_traits::{FromPrimitive, Signed, ToPrimitive, Zero};
+use std::cmp::Ordering;
+use std::rc::Rc;
+
+// Every integer has exactly one representation: a big integer that fits
+// in 64 bits is turned back into an 'Int'.
+pub fn normalize(n: BigInt) -> Object {
+    match n.to_i64() {
+        Some(i) => Object::Int(i),
+        None => Object::BigInt(Rc::new(n)),
+    }
+}
+
+fn to_bigint(value: &Object) -> Option<BigInt> {
+    match value {
+        Object::Int(i) => Some(BigInt::from(*i)),
+        Object::BigInt(b) => Some(b.as_ref().clone()),
+        _ => None,
+    }
+}
+
+pub fn to_f64(value: &Object) -> Option<f64> {
+    match value {
+        Object::Number(n) => Some(*n),
+        Object::Int(i) => Some(*i as f64),
+        Object::BigInt(b) => b.to_f64(),
+        _ => None,
+    }
+}
+
+// Number of times a string is repeated by the '*' operator
+pub fn repeat_count(value: &Object) -> Option<usize> {
+    match value {
+        Object::Number(n) => Some(*n as usize),
+        Object::Int(i) => Some((*i).max(0) as usize),
+        Object::BigInt(b) if b.is_negative() => Some(0),
+        Object::BigInt(b) => b.to_usize(),
+        _ => None,
+    }
+}
+
+// Apply a binary operator to two numeric operands. Callers must make sure
+// both operands are numeric (see 'Object::is_numeric').
+pub fn binary(ttype: TokenType, left: &Object, right: &Object) -> Object {
+    match ttype {
+        TokenType::Plus | TokenType::Minus | TokenType::Star | TokenType::Slash => {
+            arithmetic(ttype, left, right)
+        }
+        TokenType::Greater => Object::Bool(compare(left, right) == Some(Ordering::Greater)),
+        TokenType::GreaterEqual => Object::Bool(matches!(
+            compare(left, right),
+            Some(Ordering::Greater | Ordering::Equal)
+        )),
+        TokenType::Less => Object::Bool(compare(left, right) == Some(Ordering::Less)),
+        TokenType::LessEqual => Object::Bool(matches!(
+            compare(left, right),
+            Some(Ordering::Less | Ordering::Equal)
+        )),
+        TokenType::EqualEqual => Object::Bool(compare(left, right) == Some(Ordering::Equal)),
+        TokenType::BangEqual => Object::Bool(compare(left, right) != Some(Ordering::Equal)),
+        _ => Object::IllegalOperation,
+    }
+}
+
+// Integer division stays an integer only when it is exact. Otherwise (and
+// for division by zero) the operands are promoted to floating point.
+fn arithmetic(ttype: TokenType, left: &Object, right: &Object) -> Object {
+    if let (Object::Int(a), Object::Int(b)) = (left, right) {
+        let result = match ttype {
+            TokenType::Plus => a.checked_add(*b),
+            TokenType::Minus => a.checked_sub(*b),
+            TokenType::Star => a.checked_mul(*b),
+            _ if a.checked_rem(*b) == Some(0) => a.checked_div(*b),
+            _ => None,
+        };
+        if let Some(n) = result {
+            return Object::Int(n);
+        }
+    }
+
+    if let (Some(a), Some(b)) = (to_bigint(left), to_bigint(right)) {
+        match ttype {
+            TokenType::Plus => return normalize(a + b),
+            TokenType::Minus => return normalize(a - b),
+            TokenType::Star => return normalize(a * b),
+            _ if !b.is_zero() && (&a % &b).is_zero() => return normalize(a / b),
+            _ => {}
+        }
+    }
+
+    match (to_f64(left), to_f64(right)) {
+        (Some(a), Some(b)) => Object::Number(match ttype {
+            TokenType::Plus => a + b,
+            TokenType::Minus => a - b,
+            TokenType::Star => a * b,
+            _ => a / b,
+        }),
+        _ => Object::IllegalOperation,
+    }
+}
+
+// Compare two numeric values exactly. Returns 'None' when either side is NaN.
+pub fn compare(left: &Object, right: &Object) -> Option<Ordering> {
+    match (left, right) {
+        (Object::Int(a), Object::Int(b)) => Some(a.cmp(b)),
+        (Object::Number(a), Object::Number(b)) => a.partial_cmp(b),
+        (Object::Number(f), other) => {
+            compare_int_float(&to_bigint(other)?, *f).map(Ordering::reverse)
+        }
+        (other, Object::Number(f)) => compare_int_float(&to_bigint(other)?, *f),
+        _ => Some(to_bigint(left)?.cmp(&to_bigint(right)?)),
+    }
+}
+
+// Compare the integer with the integral part of the float without any loss
+// of precision and let the fractional part break the tie.
+fn compare_int_float(i: &BigInt, f: f64) -> Option<Ordering> {
+    if f.is_nan() {
+        None
+    } else if f.is_infinite() {
+        Some(if f > 0. {
+            Ordering::Less
+        } else {
+            Ordering::Greater
+        })
+    } else {
+        let whole = f.trunc();
+        match i.cmp(&BigInt::from_f64(whole)?) {
+            Ordering::Equal => 0f64.partial_cmp(&(f - whole)),
+            ord => Some(ord),
+        }
+    }
+}
+
+pub fn negate(value: &Object) -> Option<Object> {
+    match value {
+        Object::Number(n) => Some(Object::Number(-n)),
+        Object::Int(i) => Some(match i.checked_neg() {
+            Some(n) => Object::Int(n),
+            None => normalize(-BigInt::from(*i)),
+        }),
+        Object::BigInt(b) => Some(normalize(-b.as_ref().clone())),
+        _ => None,
+    }
+}
+
+// Parse an integer literal, growing into a big integer when it does not fit
+pub fn parse_int(s: &str) -> Option<Object> {
+    match s.parse::<i64>() {
+        Ok(i) => Some(Object::Int(i)),
+        Err(_) => s.parse::<BigInt>().ok().map(normalize),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_int_overflow_promotes() {
+        let max = Object::Int(i64::MAX);
+        let sum = binary(TokenType::Plus, &max, &Object::Int(1));
+        assert_eq!(sum.to_string(), "9223372036854775808");
+    }
+
+    #[test]
+    fn test_big_int_shrinks() {
+        let big = binary(TokenType::Plus, &Object::Int(i64::MAX), &Object::Int(1));
+        // Shrinks back into an 'Int' once the value fits again
+        let diff = binary(TokenType::Minus, &big, &Object::Int(1));
+        assert_eq!(diff, Object::Int(i64::MAX));
+    }
+
+    #[test]
+    fn test_negation_overflow_promotes() {
+        assert_eq!(
+            negate(&Object::Int(i64::MIN)).unwrap().to_string(),
+            "9223372036854775808"
+        );
+    }
+
+    #[test]
+    fn test_int_division() {
+        let div = |a, b| binary(TokenType::Slash, &Object::Int(a), &Object::Int(b));
+        assert_eq!(div(10, 2), Object::Int(5));
+        assert_eq!(div(7, 2), Object::Number(3.5));
+        assert_eq!(div(1, 0), Object::Number(f64::INFINITY));
+        assert_eq!(div(i64::MIN, -1).to_string(), "9223372036854775808");
+    }
+
+    #[test]
+    fn test_mixed_comparisons() {
+        let big = parse_int("9007199254740993").unwrap();
+        // 2^53 + 1 is not representable as a float, so comparing through
+        // f64 would wrongly report these as equal
+        let float = Object::Number(9007199254740992.);
+        assert_eq!(compare(&big, &float), Some(Ordering::Greater));
+        assert_eq!(
+            compare(&Object::Int(3), &Object::Number(3.5)),
+            Some(Ordering::Less)
+        );
+        assert_eq!(
+            compare(&Object::Int(-3), &Object::Number(-3.5)),
+            Some(Ordering::Greater)
+        );
+        assert_eq!(
+            compare(&Object::Int(2), &Object::Number(2.)),
+            Some(Ordering::Equal)
+        );
+        assert_eq!(compare(&Object::Int(2), &Object::Number(f64::NAN)), None);
+    }
+
+    #[test]
+    fn test_mixed_arithmetic() {
+        assert_eq!(
+            binary(TokenType::Plus, &Object::Int(1), &Object::Number(0.5)),
+            Object::Number(1.5)
+        );
+    }
+}
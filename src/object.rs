@@ -1,7 +1,15 @@
 use crate::functions_lox::*;
 use crate::functions_native::*;
 use crate::lox_class::*;
+use crate::lox_enum::{LoxEnum, LoxEnumValue, VariantConstructor};
+use crate::lox_fiber::LoxFiber;
+use crate::lox_generator::LoxGenerator;
 use crate::lox_instance::LoxInstance;
+use crate::lox_list::LoxList;
+use crate::lox_range::LoxRange;
+use crate::lox_trait::LoxTrait;
+use crate::lox_tuple::LoxTuple;
+use num_bigint::BigInt;
 use std::fmt;
 use std::rc::Rc;
 
@@ -10,10 +18,21 @@ pub enum Object {
     Identifier(String),
     Str(String),
     Number(f64),
+    Int(i64),
+    BigInt(Rc<BigInt>),
     Bool(bool),
     Func(Rc<LoxFunction>),
     Class(Rc<LoxClass>),
+    Trait(Rc<LoxTrait>),
+    Enum(Rc<LoxEnum>),
+    EnumValue(Rc<LoxEnumValue>),
+    Variant(Rc<VariantConstructor>),
     Instance(Rc<LoxInstance>),
+    List(Rc<LoxList>),
+    Tuple(Rc<LoxTuple>),
+    Generator(Rc<LoxGenerator>),
+    Fiber(Rc<LoxFiber>),
+    Range(Rc<LoxRange>),
     Native(Rc<LoxNative>),
     Nil,
     IllegalOperation,
@@ -25,13 +44,53 @@ impl fmt::Display for Object {
             Self::Identifier(s) => write!(f, "{}", s),
             Self::Str(s) => write!(f, "{}", s),
             Self::Number(n) => write!(f, "{}", n),
+            Self::Int(n) => write!(f, "{}", n),
+            Self::BigInt(n) => write!(f, "{}", n),
             Self::Bool(b) => write!(f, "{}", b),
             Self::Func(c) => write!(f, "{}", c),
             Self::Class(c) => write!(f, "{}", c),
+            Self::Trait(t) => write!(f, "{}", t),
+            Self::Enum(e) => write!(f, "{}", e),
+            Self::EnumValue(v) => write!(f, "{}", v),
+            Self::Variant(v) => write!(f, "{}", v),
             Self::Instance(c) => write!(f, "{}", c),
+            Self::List(l) => write!(f, "{}", l),
+            Self::Tuple(t) => write!(f, "{}", t),
+            Self::Generator(g) => write!(f, "{}", g),
+            Self::Fiber(c) => write!(f, "{}", c),
+            Self::Range(r) => write!(f, "{}", r),
             Self::Native(c) => write!(f, "{}", c),
             Self::Nil => write!(f, "nil"),
             Self::IllegalOperation => write!(f, "illegal-op"),
         }
     }
 }
+
+impl Object {
+    pub fn is_numeric(&self) -> bool {
+        matches!(self, Self::Number(_) | Self::Int(_) | Self::BigInt(_))
+    }
+
+    // Name of the value's type as reported by the 'type' native
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::Identifier(_) => "identifier",
+            Self::Str(_) => "string",
+            Self::Number(_) | Self::Int(_) | Self::BigInt(_) => "number",
+            Self::Bool(_) => "bool",
+            Self::Func(_) | Self::Native(_) => "function",
+            Self::Class(_) => "class",
+            Self::Trait(_) => "trait",
+            Self::Enum(_) => "enum",
+            Self::EnumValue(_) | Self::Variant(_) => "variant",
+            Self::Instance(_) => "instance",
+            Self::List(_) => "list",
+            Self::Tuple(_) => "tuple",
+            Self::Generator(_) => "generator",
+            Self::Fiber(_) => "fiber",
+            Self::Range(_) => "range",
+            Self::Nil => "nil",
+            Self::IllegalOperation => "illegal-op",
+        }
+    }
+}
@@ -1,8 +1,15 @@
+use crate::callable::NamedArgument;
+use crate::environment::VarKind;
 use crate::error::*;
 use crate::expr::*;
+use crate::functions_lox::FunctionKind;
+use crate::lox_enum::VariantDecl;
+use crate::numeric;
 use crate::object::*;
+use crate::pattern::{DestructureKind, MatchCase, Pattern};
 use crate::stmt::*;
 use crate::token::*;
+use std::ops::Deref;
 use std::rc::Rc;
 
 pub struct Parser {
@@ -11,6 +18,14 @@ pub struct Parser {
     had_error: bool,
 }
 
+// Parameter list of a function: 'a, b = 1, ...rest'
+#[derive(Default)]
+struct Parameters {
+    names: Vec<Token>,
+    defaults: Vec<Rc<Expr>>,
+    rest: Option<Token>,
+}
+
 // Grammar for expressions:
 
 // When the first symbol in the body of the rule is the same as
@@ -57,11 +72,19 @@ impl Parser {
     }
 
     fn declaration(&mut self) -> Result<Rc<Stmt>, LoxResult> {
-        let result = if self.matches(&[TokenType::Class]) {
+        let result = if self.check(&TokenType::Class)
+            || self.check(&TokenType::Record)
+            || self.check(&TokenType::Abstract)
+            || self.check_strict()
+        {
             self.class_declaration()
+        } else if self.matches(&[TokenType::Trait]) {
+            self.trait_declaration()
+        } else if self.matches(&[TokenType::Enum]) {
+            self.enum_declaration()
         } else if self.matches(&[TokenType::Fun]) {
             self.fun_declaration("function")
-        } else if self.matches(&[TokenType::Var]) {
+        } else if self.matches(&[TokenType::Var, TokenType::Let, TokenType::Const]) {
             self.var_declaration()
         } else {
             self.statement()
@@ -72,8 +95,38 @@ impl Parser {
         result
     }
 
+    // 'strict' is only a keyword in front of 'class', 'record' or 'abstract'
+    fn check_strict(&self) -> bool {
+        self.check(&TokenType::Identifier)
+            && self.peek().lexeme == "strict"
+            && (self.check_ahead(1, &TokenType::Class)
+                || self.check_ahead(1, &TokenType::Record)
+                || self.check_ahead(1, &TokenType::Abstract))
+    }
+
+    // Classes may be marked 'abstract' and 'strict' in any order. Records
+    // are classes declared with 'record Name(fields)' whose body is optional.
     fn class_declaration(&mut self) -> Result<Rc<Stmt>, LoxResult> {
+        let mut is_abstract = false;
+        let mut strict = false;
+        while !self.matches(&[TokenType::Class, TokenType::Record]) {
+            if self.matches(&[TokenType::Abstract]) {
+                is_abstract = true;
+            } else if self.check_strict() {
+                self.advance();
+                strict = true;
+            } else {
+                self.consume(&TokenType::Class, "Expect 'class' after class modifiers.")?;
+                break;
+            }
+        }
+        let is_record = self.previous().ttype == TokenType::Record;
         let name = self.consume(&TokenType::Identifier, "Expect class name.")?;
+        let record_fields = if is_record {
+            Some(Rc::new(self.record_fields()?))
+        } else {
+            None
+        };
         let superclass = if self.matches(&[TokenType::Less]) {
             self.consume(&TokenType::Identifier, "Expect super-class name.")?;
             Some(Rc::new(Expr::Variable(Rc::new(VariableExpr {
@@ -82,64 +135,288 @@ impl Parser {
         } else {
             None
         };
+        let mut traits = Vec::new();
+        if self.matches(&[TokenType::With]) {
+            loop {
+                let name = self.consume(&TokenType::Identifier, "Expect trait name.")?;
+                traits.push(Rc::new(Expr::Variable(Rc::new(VariableExpr { name }))));
+                if !self.matches(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
 
-        self.consume(&TokenType::LeftBrace, "Expect '{' before class body")?;
         let mut methods = Vec::new();
-        while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
-            methods.push(self.fun_declaration("method")?);
+        let mut static_methods = Vec::new();
+        let mut abstract_methods = Vec::new();
+        let mut fields = Vec::new();
+        // 'record Point(x, y);' has no body
+        if !is_record || !self.matches(&[TokenType::Semicolon]) {
+            self.consume(&TokenType::LeftBrace, "Expect '{' before class body")?;
+            while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
+                if self.matches(&[TokenType::Var]) {
+                    fields.push(self.field_declaration()?);
+                } else if self.matches(&[TokenType::Abstract]) {
+                    abstract_methods.push(self.abstract_method(is_abstract)?);
+                } else if self.matches(&[TokenType::Class, TokenType::Static]) {
+                    // Methods prefixed with 'class' or 'static' belong to the class itself
+                    static_methods.push(self.method_declaration()?);
+                } else {
+                    methods.push(self.method_declaration()?);
+                }
+            }
+            self.consume(&TokenType::RightBrace, "Expect '}' after class body")?;
         }
-
-        self.consume(&TokenType::RightBrace, "Expect '}' after class body")?;
         Ok(Rc::new(Stmt::Class(Rc::new(ClassStmt {
             name,
             superclass,
             methods: Rc::new(methods),
+            static_methods: Rc::new(static_methods),
+            traits: Rc::new(traits),
+            is_abstract,
+            abstract_methods: Rc::new(abstract_methods),
+            fields: Rc::new(fields),
+            strict,
+            record_fields,
+        }))))
+    }
+
+    // The fields of 'record Point(x, y)'
+    fn record_fields(&mut self) -> Result<Vec<Token>, LoxResult> {
+        self.consume(&TokenType::LeftParen, "Expect '(' after record name.")?;
+        let mut fields = Vec::new();
+        if !self.check(&TokenType::RightParen) {
+            loop {
+                fields.push(self.consume(&TokenType::Identifier, "Expect field name.")?);
+                if !self.matches(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+        self.consume(&TokenType::RightParen, "Expect ')' after record fields.")?;
+        Ok(fields)
+    }
+
+    // Instance fields are declared like variables: 'var count = 0;'
+    fn field_declaration(&mut self) -> Result<Rc<Stmt>, LoxResult> {
+        let name = self.member_name("Expect field name.")?;
+        let initializer = if self.matches(&[TokenType::Equal]) {
+            Some(Rc::new(self.expression()?))
+        } else {
+            None
+        };
+        self.consume(&TokenType::Semicolon, "Expect ';' after field declaration")?;
+        Ok(Rc::new(Stmt::Var(Rc::new(VarStmt {
+            name,
+            kind: VarKind::Var,
+            initializer,
+        }))))
+    }
+
+    // An abstract method has a signature but no body: 'abstract area();'
+    fn abstract_method(&mut self, in_abstract_class: bool) -> Result<Token, LoxResult> {
+        let name = self.consume(&TokenType::Identifier, "Expect method name.")?;
+        if !in_abstract_class {
+            self.parse_error(&name, "Only abstract classes can have abstract methods");
+        }
+        self.parameters("method")?;
+        self.consume(&TokenType::Semicolon, "Expect ';' after abstract method")?;
+        Ok(name)
+    }
+
+    // 'enum Shape { Point, Circle(r), Rect(w, h) }'. A trailing comma is allowed.
+    fn enum_declaration(&mut self) -> Result<Rc<Stmt>, LoxResult> {
+        let name = self.consume(&TokenType::Identifier, "Expect enum name.")?;
+        self.consume(&TokenType::LeftBrace, "Expect '{' before enum body")?;
+        let mut variants = Vec::new();
+        while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
+            let name = self.consume(&TokenType::Identifier, "Expect variant name.")?;
+            let mut fields = Vec::new();
+            if self.matches(&[TokenType::LeftParen]) && !self.matches(&[TokenType::RightParen]) {
+                loop {
+                    fields.push(self.consume(&TokenType::Identifier, "Expect field name.")?);
+                    if !self.matches(&[TokenType::Comma]) {
+                        break;
+                    }
+                }
+                self.consume(&TokenType::RightParen, "Expect ')' after variant fields.")?;
+            }
+            variants.push(VariantDecl { name, fields });
+            if !self.matches(&[TokenType::Comma]) {
+                break;
+            }
+        }
+        self.consume(&TokenType::RightBrace, "Expect '}' after enum body")?;
+        Ok(Rc::new(Stmt::Enum(Rc::new(EnumStmt {
+            name,
+            variants: Rc::new(variants),
         }))))
     }
 
+    // A trait holds instance methods only
+    fn trait_declaration(&mut self) -> Result<Rc<Stmt>, LoxResult> {
+        let name = self.consume(&TokenType::Identifier, "Expect trait name.")?;
+        self.consume(&TokenType::LeftBrace, "Expect '{' before trait body")?;
+        let mut methods = Vec::new();
+        while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
+            methods.push(self.method_declaration()?);
+        }
+        self.consume(&TokenType::RightBrace, "Expect '}' after trait body")?;
+        Ok(Rc::new(Stmt::Trait(Rc::new(TraitStmt {
+            name,
+            methods: Rc::new(methods),
+        }))))
+    }
+
+    // The keyword ('var', 'let' or 'const') has already been consumed
     fn var_declaration(&mut self) -> Result<Rc<Stmt>, LoxResult> {
+        let kind = match self.previous().ttype {
+            TokenType::Let => VarKind::Let,
+            TokenType::Const => VarKind::Const,
+            _ => VarKind::Var,
+        };
+        if self.matches(&[TokenType::LeftBracket, TokenType::LeftBrace]) {
+            return self.destructuring_declaration(kind);
+        }
         let name = self.consume(&TokenType::Identifier, "Expect variable name.")?;
         let initializer = if self.matches(&[TokenType::Equal]) {
             Some(Rc::new(self.expression()?))
         } else {
             None
         };
+        if kind == VarKind::Const && initializer.is_none() {
+            self.parse_error(&name, "A constant must be initialized.");
+        }
         self.consume(
             &TokenType::Semicolon,
             "Expect ',' after variable declaration",
         )?;
-        Ok(Rc::new(Stmt::Var(Rc::new(VarStmt { name, initializer }))))
+        Ok(Rc::new(Stmt::Var(Rc::new(VarStmt {
+            name,
+            kind,
+            initializer,
+        }))))
+    }
+
+    // 'var [a, b] = list;' or 'var {x, y} = instance;'
+    fn destructuring_declaration(&mut self, kind: VarKind) -> Result<Rc<Stmt>, LoxResult> {
+        let bracket = self.previous();
+        let (shape, closing) = if bracket.ttype == TokenType::LeftBracket {
+            (DestructureKind::List, TokenType::RightBracket)
+        } else {
+            (DestructureKind::Fields, TokenType::RightBrace)
+        };
+        let mut names = vec![self.consume(&TokenType::Identifier, "Expect variable name.")?];
+        while self.matches(&[TokenType::Comma]) {
+            names.push(self.consume(&TokenType::Identifier, "Expect variable name.")?);
+        }
+        self.consume(&closing, "Expect closing bracket after names.")?;
+        self.consume(&TokenType::Equal, "Expect '=' after names to destructure.")?;
+        let initializer = Rc::new(self.expression()?);
+        self.consume(
+            &TokenType::Semicolon,
+            "Expect ';' after variable declaration",
+        )?;
+        Ok(Rc::new(Stmt::Destructure(Rc::new(DestructureStmt {
+            bracket,
+            shape,
+            kind,
+            names: Rc::new(names),
+            initializer,
+        }))))
     }
 
     fn fun_declaration(&mut self, kind: &str) -> Result<Rc<Stmt>, LoxResult> {
-        let mut params = Vec::new();
         let name = self.consume(&TokenType::Identifier, &format!("Expect '{}' name.", kind))?;
+        let params = self.parameters(kind)?;
+        self.function_body(name, FunctionKind::Function, params, kind)
+    }
+
+    // Methods can also be getters which have no parameter list at all
+    // ('area { ... }') or setters written as 'area=(value) { ... }'
+    fn method_declaration(&mut self) -> Result<Rc<Stmt>, LoxResult> {
+        let name = self.member_name("Expect 'method' name.")?;
+        if self.check(&TokenType::LeftBrace) {
+            let params = Parameters::default();
+            return self.function_body(name, FunctionKind::Getter, params, "getter");
+        }
+        if self.matches(&[TokenType::Equal]) {
+            let params = self.parameters("setter")?;
+            return self.function_body(name, FunctionKind::Setter, params, "setter");
+        }
+        let params = self.parameters("method")?;
+        self.function_body(name, FunctionKind::Function, params, "method")
+    }
+
+    // Properties and methods may be private: '#name'
+    fn member_name(&mut self, message: &str) -> Result<Token, LoxResult> {
+        if self.matches(&[TokenType::PrivateName]) {
+            Ok(self.previous())
+        } else {
+            self.consume(&TokenType::Identifier, message)
+        }
+    }
+
+    // Parameters with a default have to come after those without one and
+    // the rest parameter has to be the last
+    fn parameters(&mut self, kind: &str) -> Result<Parameters, LoxResult> {
+        let mut params = Parameters::default();
         self.consume(
             &TokenType::LeftParen,
             &format!("Expect '(' after '{}' name.", kind),
         )?;
 
         if !self.check(&TokenType::RightParen) {
-            params.push(self.consume(&TokenType::Identifier, "Expect parameter name")?);
-            while self.matches(&[TokenType::Comma]) {
-                if params.len() >= 255 {
+            loop {
+                if params.names.len() >= 255 {
                     self.parse_error(&self.peek(), "Can't have more than 255 parameters");
-                } else {
-                    params.push(self.consume(&TokenType::Identifier, "Expect parameter name")?);
+                }
+                if self.matches(&[TokenType::Ellipsis]) {
+                    params.rest =
+                        Some(self.consume(&TokenType::Identifier, "Expect rest parameter name")?);
+                    if self.check(&TokenType::Comma) {
+                        self.parse_error(&self.peek(), "The rest parameter must be the last one");
+                    }
+                    break;
+                }
+                let name = self.consume(&TokenType::Identifier, "Expect parameter name")?;
+                if self.matches(&[TokenType::Equal]) {
+                    params.defaults.push(Rc::new(self.expression()?));
+                } else if !params.defaults.is_empty() {
+                    self.parse_error(
+                        &name,
+                        "A parameter without a default can't follow one with a default",
+                    );
+                }
+                params.names.push(name);
+                if !self.matches(&[TokenType::Comma]) {
+                    break;
                 }
             }
         }
         self.consume(&TokenType::RightParen, "Expect ')' after parameters")?;
+        Ok(params)
+    }
 
+    fn function_body(
+        &mut self,
+        name: Token,
+        kind: FunctionKind,
+        params: Parameters,
+        kind_name: &str,
+    ) -> Result<Rc<Stmt>, LoxResult> {
         // Parse function body
         self.consume(
             &TokenType::LeftBrace,
-            &format!("Expect '{{' before '{}' body", kind),
+            &format!("Expect '{{' before '{}' body", kind_name),
         )?;
         let body = self.block()?;
         Ok(Rc::new(Stmt::Function(Rc::new(FunctionStmt {
             name,
-            params: Rc::new(params),
+            kind,
+            params: Rc::new(params.names),
+            defaults: Rc::new(params.defaults),
+            rest: params.rest,
             body: Rc::new(body),
         }))))
     }
@@ -151,6 +428,9 @@ impl Parser {
         if self.matches(&[TokenType::If]) {
             return Ok(Rc::new(self.if_statement()?));
         }
+        if self.matches(&[TokenType::Match]) {
+            return self.match_statement();
+        }
         if self.matches(&[TokenType::Print]) {
             return Ok(Rc::new(self.print_statement()?));
         }
@@ -188,6 +468,122 @@ impl Parser {
         })))
     }
 
+    // match (subject) { case pattern, ... if guard => body ... default => body }
+    fn match_statement(&mut self) -> Result<Rc<Stmt>, LoxResult> {
+        let keyword = self.previous();
+        self.consume(&TokenType::LeftParen, "Expect '(' after 'match'.")?;
+        let subject = Rc::new(self.expression()?);
+        self.consume(&TokenType::RightParen, "Expect ')' after match value.")?;
+        self.consume(&TokenType::LeftBrace, "Expect '{' before match cases.")?;
+
+        let mut cases = Vec::new();
+        let mut default = None;
+        while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
+            if self.matches(&[TokenType::Default]) {
+                if default.is_some() {
+                    self.parse_error(&self.previous(), "A match can only have one default.");
+                }
+                self.consume(&TokenType::FatArrow, "Expect '=>' after 'default'.")?;
+                default = Some(self.statement()?);
+                continue;
+            }
+            self.consume(&TokenType::Case, "Expect 'case' or 'default'.")?;
+            let mut patterns = vec![self.pattern()?];
+            while self.matches(&[TokenType::Comma]) {
+                patterns.push(self.pattern()?);
+            }
+            let guard = if self.matches(&[TokenType::If]) {
+                Some(Rc::new(self.expression()?))
+            } else {
+                None
+            };
+            self.consume(&TokenType::FatArrow, "Expect '=>' after case pattern.")?;
+            let body = self.statement()?;
+            cases.push(MatchCase {
+                patterns,
+                guard,
+                body,
+            });
+        }
+        self.consume(&TokenType::RightBrace, "Expect '}' after match cases.")?;
+        Ok(Rc::new(Stmt::Match(Rc::new(MatchStmt {
+            keyword,
+            subject,
+            cases: Rc::new(cases),
+            default,
+        }))))
+    }
+
+    fn pattern(&mut self) -> Result<Pattern, LoxResult> {
+        if self.matches(&[TokenType::Number, TokenType::StringLiteral]) {
+            return Ok(Pattern::Literal(
+                self.previous().literal.unwrap_or(Object::Nil),
+            ));
+        }
+        if self.matches(&[TokenType::True]) {
+            return Ok(Pattern::Literal(Object::Bool(true)));
+        }
+        if self.matches(&[TokenType::False]) {
+            return Ok(Pattern::Literal(Object::Bool(false)));
+        }
+        if self.matches(&[TokenType::Nil]) {
+            return Ok(Pattern::Literal(Object::Nil));
+        }
+        if self.matches(&[TokenType::Minus]) {
+            let number = self.consume(&TokenType::Number, "Expect number after '-'.")?;
+            return match number.literal.as_ref().and_then(numeric::negate) {
+                Some(value) => Ok(Pattern::Literal(value)),
+                None => Err(self.parse_error(&number, "Expect number after '-'.")),
+            };
+        }
+        let name = self.consume(&TokenType::Identifier, "Expect pattern.")?;
+        if name.lexeme == "_" {
+            return Ok(Pattern::Wildcard);
+        }
+        if self.matches(&[TokenType::Dot]) {
+            let variant = self.consume(&TokenType::Identifier, "Expect variant name after '.'.")?;
+            let fields = if self.matches(&[TokenType::LeftParen]) {
+                self.field_patterns()?
+            } else {
+                Vec::new()
+            };
+            return Ok(Pattern::Variant {
+                enumeration: Rc::new(Expr::Variable(Rc::new(VariableExpr { name }))),
+                name: variant,
+                fields,
+            });
+        }
+        if !self.matches(&[TokenType::LeftParen]) {
+            return Ok(Pattern::Binding(name));
+        }
+        Ok(Pattern::Instance {
+            class: Rc::new(Expr::Variable(Rc::new(VariableExpr { name: name.clone() }))),
+            name,
+            fields: self.field_patterns()?,
+        })
+    }
+
+    // 'x, y: 0)' after the opening parenthesis of an instance or variant pattern
+    fn field_patterns(&mut self) -> Result<Vec<(Token, Pattern)>, LoxResult> {
+        let mut fields = Vec::new();
+        if !self.check(&TokenType::RightParen) {
+            loop {
+                let field = self.consume(&TokenType::Identifier, "Expect field name.")?;
+                let pattern = if self.matches(&[TokenType::Colon]) {
+                    self.pattern()?
+                } else {
+                    Pattern::Binding(field.clone())
+                };
+                fields.push((field, pattern));
+                if !self.matches(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+        self.consume(&TokenType::RightParen, "Expect ')' after field patterns.")?;
+        Ok(fields)
+    }
+
     fn print_statement(&mut self) -> Result<Stmt, LoxResult> {
         let value = self.expression()?;
         self.consume(&TokenType::Semicolon, "Expect ';' after value.")?;
@@ -200,7 +596,17 @@ impl Parser {
         let keyword = self.previous();
         let mut value = None;
         if !self.check(&TokenType::Semicolon) {
-            value = Some(Rc::new(self.expression()?));
+            let expr = self.expression()?;
+            // 'return a, b;' returns the tuple '(a, b)'
+            value = Some(Rc::new(if self.check(&TokenType::Comma) {
+                let mut elements = vec![Rc::new(expr)];
+                while self.matches(&[TokenType::Comma]) {
+                    elements.push(Rc::new(self.expression()?));
+                }
+                Expr::Tuple(Rc::new(TupleExpr { elements }))
+            } else {
+                expr
+            }));
         }
         self.consume(&TokenType::Semicolon, "Expect ';' after return statement.")?;
         Ok(Stmt::Return(Rc::new(ReturnStmt { keyword, value })))
@@ -226,10 +632,16 @@ impl Parser {
 
     fn for_statement(&mut self) -> Result<Rc<Stmt>, LoxResult> {
         self.consume(&TokenType::LeftParen, "Expect '(' after 'for'.")?;
+        if self.check(&TokenType::Var)
+            && self.check_ahead(1, &TokenType::Identifier)
+            && self.check_ahead(2, &TokenType::In)
+        {
+            return self.for_in_statement();
+        }
         // Parse optional 'initializer'
         let initializer = if self.matches(&[TokenType::Semicolon]) {
             None
-        } else if self.matches(&[TokenType::Var]) {
+        } else if self.matches(&[TokenType::Var, TokenType::Let, TokenType::Const]) {
             Some(self.var_declaration()?)
         } else {
             Some(self.expression_statement()?)
@@ -289,14 +701,70 @@ impl Parser {
         Ok(body)
     }
 
+    // for (var name in iterable) body
+    fn for_in_statement(&mut self) -> Result<Rc<Stmt>, LoxResult> {
+        self.consume(&TokenType::Var, "Expect 'var' in for loop.")?;
+        let name = self.consume(&TokenType::Identifier, "Expect variable name.")?;
+        self.consume(&TokenType::In, "Expect 'in' after variable name.")?;
+        let iterable = Rc::new(self.expression()?);
+        self.consume(&TokenType::RightParen, "Expect ')' after for loop.")?;
+        let body = self.statement()?;
+        Ok(Rc::new(Stmt::ForIn(Rc::new(ForInStmt {
+            name,
+            iterable,
+            body,
+        }))))
+    }
+
     fn expression_statement(&mut self) -> Result<Rc<Stmt>, LoxResult> {
         let expr = self.expression()?;
+        if self.check(&TokenType::Comma) {
+            return self.multiple_assignment(expr);
+        }
         self.consume(&TokenType::Semicolon, "Expect ';' after value.")?;
         Ok(Rc::new(Stmt::Expression(Rc::new(ExpressionStmt {
             expression: Rc::new(expr),
         }))))
     }
 
+    // 'a, b = b, a;' assigns the values after evaluating all of them. A
+    // single value on the right is a list holding a value for each target.
+    fn multiple_assignment(&mut self, first: Expr) -> Result<Rc<Stmt>, LoxResult> {
+        let mut targets = vec![Rc::new(first)];
+        while self.matches(&[TokenType::Comma]) {
+            targets.push(Rc::new(self.logical_or()?));
+        }
+        let equals = self.consume(&TokenType::Equal, "Expect '=' after assignment targets.")?;
+        for target in targets.iter() {
+            if !matches!(
+                target.deref(),
+                Expr::Variable(_) | Expr::Get(_) | Expr::Index(_)
+            ) {
+                self.parse_error(&equals, "Invalid assignment target.");
+            }
+        }
+        let mut values = vec![Rc::new(self.expression()?)];
+        while self.matches(&[TokenType::Comma]) {
+            values.push(Rc::new(self.expression()?));
+        }
+        if values.len() != 1 && values.len() != targets.len() {
+            self.parse_error(
+                &equals,
+                &format!(
+                    "Expected {} values to assign but got {}.",
+                    targets.len(),
+                    values.len()
+                ),
+            );
+        }
+        self.consume(&TokenType::Semicolon, "Expect ';' after value.")?;
+        Ok(Rc::new(Stmt::MultiAssign(Rc::new(MultiAssignStmt {
+            targets: Rc::new(targets),
+            equals,
+            values: Rc::new(values),
+        }))))
+    }
+
     fn block(&mut self) -> Result<Vec<Rc<Stmt>>, LoxResult> {
         let mut stmts = Vec::new();
         while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
@@ -311,6 +779,9 @@ impl Parser {
     }
 
     fn assignment(&mut self) -> Result<Expr, LoxResult> {
+        if self.matches(&[TokenType::Yield]) {
+            return self.yield_expr();
+        }
         let expr = self.logical_or()?;
         if self.matches(&[TokenType::Equal]) {
             let equals = self.previous();
@@ -328,6 +799,14 @@ impl Parser {
                     name: get_expr.name.clone(),
                     value: Rc::new(value),
                 })));
+            } else if let Expr::Index(index_expr) = expr {
+                // Likewise, assigning to 'a[i]' becomes a 'SetIndex' expression
+                return Ok(Expr::SetIndex(Rc::new(SetIndexExpr {
+                    object: index_expr.object.clone(),
+                    bracket: index_expr.bracket.clone(),
+                    index: index_expr.index.clone(),
+                    value: Rc::new(value),
+                })));
             }
             // Report but do not throw the error because the parser
             // does not need to panic and synchronize
@@ -336,6 +815,20 @@ impl Parser {
         Ok(expr)
     }
 
+    // 'yield' binds loosest of all. The value is optional: a bare 'yield'
+    // produces nil.
+    fn yield_expr(&mut self) -> Result<Expr, LoxResult> {
+        let keyword = self.previous();
+        let value = match self.peek().ttype {
+            TokenType::Semicolon
+            | TokenType::RightParen
+            | TokenType::RightBracket
+            | TokenType::Comma => None,
+            _ => Some(Rc::new(self.assignment()?)),
+        };
+        Ok(Expr::Yield(Rc::new(YieldExpr { keyword, value })))
+    }
+
     fn logical_or(&mut self) -> Result<Expr, LoxResult> {
         let mut expr = self.logical_and()?;
         while self.matches(&[TokenType::Or]) {
@@ -390,17 +883,19 @@ impl Parser {
         Ok(expr)
     }
 
+    // 'in' tests membership and has the precedence of the comparisons
     fn comparison(&mut self) -> Result<Expr, LoxResult> {
-        let mut expr = self.term()?;
+        let mut expr = self.range()?;
         let compare_operators = [
             TokenType::Greater,
             TokenType::GreaterEqual,
             TokenType::Less,
             TokenType::LessEqual,
+            TokenType::In,
         ];
         while self.matches(&compare_operators) {
             let operator = self.previous();
-            let right = self.term()?;
+            let right = self.range()?;
             expr = Expr::Binary(Rc::new(BinaryExpr {
                 left: Rc::new(expr),
                 operator,
@@ -410,6 +905,29 @@ impl Parser {
         Ok(expr)
     }
 
+    // 'a..b' or 'a..=b' followed by an optional 'step s'. 'step' is only a
+    // keyword in this position.
+    fn range(&mut self) -> Result<Expr, LoxResult> {
+        let expr = self.term()?;
+        if !self.matches(&[TokenType::DotDot, TokenType::DotDotEqual]) {
+            return Ok(expr);
+        }
+        let operator = self.previous();
+        let end = self.term()?;
+        let step = if self.check(&TokenType::Identifier) && self.peek().lexeme == "step" {
+            self.advance();
+            Some(Rc::new(self.term()?))
+        } else {
+            None
+        };
+        Ok(Expr::Range(Rc::new(RangeExpr {
+            start: Rc::new(expr),
+            operator,
+            end: Rc::new(end),
+            step,
+        })))
+    }
+
     // In order of precedence, first addition and subtraction
     fn term(&mut self) -> Result<Expr, LoxResult> {
         let mut expr = self.factor()?;
@@ -461,6 +979,8 @@ impl Parser {
                 expr = self.finish_call(Rc::new(expr))?;
             } else if self.matches(&[TokenType::Dot]) {
                 expr = self.finish_get_expr(Rc::new(expr))?;
+            } else if self.matches(&[TokenType::LeftBracket]) {
+                expr = self.finish_index_expr(Rc::new(expr))?;
             } else {
                 break;
             }
@@ -471,14 +991,15 @@ impl Parser {
     // Process function call arguments and consume the closing parenthesis
     fn finish_call(&mut self, callee: Rc<Expr>) -> Result<Expr, LoxResult> {
         let mut arguments = Vec::new();
+        let mut named = Vec::new();
         // If there are arguments to the function
         if !self.check(&TokenType::RightParen) {
-            arguments.push(Rc::new(self.expression()?));
+            self.argument(&mut arguments, &mut named)?;
             while self.matches(&[TokenType::Comma]) {
-                if arguments.len() >= 255 {
+                if arguments.len() + named.len() >= 255 {
                     self.parse_error(&self.peek(), "Can't have more than 255 arguments");
                 } else {
-                    arguments.push(Rc::new(self.expression()?));
+                    self.argument(&mut arguments, &mut named)?;
                 }
             }
         }
@@ -488,14 +1009,87 @@ impl Parser {
             callee,
             paren,
             arguments,
+            named,
         })))
     }
 
+    // A positional argument or a named one ('name: value'). Named arguments
+    // come last.
+    fn argument(
+        &mut self,
+        arguments: &mut Vec<Rc<Expr>>,
+        named: &mut Vec<NamedArgument>,
+    ) -> Result<(), LoxResult> {
+        if self.check(&TokenType::Identifier) && self.check_ahead(1, &TokenType::Colon) {
+            let name = self.advance();
+            self.advance();
+            let value = Rc::new(self.expression()?);
+            named.push(NamedArgument { name, value });
+        } else {
+            if !named.is_empty() {
+                self.parse_error(
+                    &self.peek(),
+                    "Positional arguments must come before named ones",
+                );
+            }
+            arguments.push(Rc::new(self.spreadable()?));
+        }
+        Ok(())
+    }
+
+    // An argument or a list element, which can be an iterable spread with '...'
+    fn spreadable(&mut self) -> Result<Expr, LoxResult> {
+        if self.matches(&[TokenType::Ellipsis]) {
+            let ellipsis = self.previous();
+            let value = Rc::new(self.expression()?);
+            return Ok(Expr::Spread(Rc::new(SpreadExpr { ellipsis, value })));
+        }
+        self.expression()
+    }
+
     // Process get expressions
     fn finish_get_expr(&mut self, object: Rc<Expr>) -> Result<Expr, LoxResult> {
-        let name = self.consume(&TokenType::Identifier, "Expect property name after '.'")?;
+        let name = self.member_name("Expect property name after '.'")?;
         Ok(Expr::Get(Rc::new(GetExpr { name, object })))
     }
+
+    // Process index expressions and consume the closing bracket
+    fn finish_index_expr(&mut self, object: Rc<Expr>) -> Result<Expr, LoxResult> {
+        let index = self.expression()?;
+        let bracket = self.consume(&TokenType::RightBracket, "Expect ']' after index")?;
+        Ok(Expr::Index(Rc::new(IndexExpr {
+            object,
+            bracket,
+            index: Rc::new(index),
+        })))
+    }
+    // Process the elements of a list literal and consume the closing bracket
+    fn finish_list_expr(&mut self) -> Result<Expr, LoxResult> {
+        let mut elements = Vec::new();
+        if !self.check(&TokenType::RightBracket) {
+            elements.push(Rc::new(self.spreadable()?));
+            while self.matches(&[TokenType::Comma]) {
+                elements.push(Rc::new(self.spreadable()?));
+            }
+        }
+        self.consume(&TokenType::RightBracket, "Expect ']' after list elements")?;
+        Ok(Expr::List(Rc::new(ListExpr { elements })))
+    }
+
+    // The first element and its comma have been consumed. A trailing comma
+    // is allowed so that '(1,)' is a tuple of one.
+    fn finish_tuple_expr(&mut self, first: Expr) -> Result<Expr, LoxResult> {
+        let mut elements = vec![Rc::new(first)];
+        while !self.check(&TokenType::RightParen) {
+            elements.push(Rc::new(self.spreadable()?));
+            if !self.matches(&[TokenType::Comma]) {
+                break;
+            }
+        }
+        self.consume(&TokenType::RightParen, "Expect ')' after tuple elements")?;
+        Ok(Expr::Tuple(Rc::new(TupleExpr { elements })))
+    }
+
     // Reached highest level of precedence after crawling up the
     // precedence hierarchy. Most of the primary rules are terminals.
     fn primary(&mut self) -> Result<Expr, LoxResult> {
@@ -538,8 +1132,19 @@ impl Parser {
                 name: self.previous(),
             })));
         }
+        if self.matches(&[TokenType::LeftBracket]) {
+            return self.finish_list_expr();
+        }
         if self.matches(&[TokenType::LeftParen]) {
-            let expr = self.expression()?;
+            // A comma turns the parenthesized expression into a tuple
+            if self.matches(&[TokenType::RightParen]) {
+                let elements = Vec::new();
+                return Ok(Expr::Tuple(Rc::new(TupleExpr { elements })));
+            }
+            let expr = self.spreadable()?;
+            if self.matches(&[TokenType::Comma]) {
+                return self.finish_tuple_expr(expr);
+            }
             self.consume(&TokenType::RightParen, "Expect `)` after expression")?;
             return Ok(Expr::Grouping(Rc::new(GroupingExpr {
                 expression: Rc::new(expr),
@@ -559,12 +1164,19 @@ impl Parser {
                 return;
             }
             match self.peek().ttype {
-                TokenType::Class
+                TokenType::Abstract
+                | TokenType::Class
+                | TokenType::Trait
+                | TokenType::Enum
+                | TokenType::Record
                 | TokenType::Fun
                 | TokenType::Var
+                | TokenType::Let
+                | TokenType::Const
                 | TokenType::For
                 | TokenType::If
                 | TokenType::While
+                | TokenType::Match
                 | TokenType::Print
                 | TokenType::Return => {
                     return;
@@ -615,6 +1227,15 @@ impl Parser {
         &self.peek().ttype == ttype
     }
 
+    // Returns true if the token 'distance' tokens past the current one is of
+    // the given type, without consuming anything
+    fn check_ahead(&self, distance: usize, ttype: &TokenType) -> bool {
+        match self.tokens.get(self.current + distance) {
+            Some(token) => &token.ttype == ttype,
+            None => false,
+        }
+    }
+
     // Consumes the current token and return it
     fn advance(&mut self) -> Token {
         if !self.is_at_end() {
@@ -0,0 +1,61 @@
+use crate::expr::Expr;
+use crate::object::*;
+use crate::stmt::Stmt;
+use crate::token::*;
+use std::rc::Rc;
+
+// Patterns of the cases of a 'match' statement
+#[derive(Clone)]
+pub enum Pattern {
+    // '_' matches any value without binding it
+    Wildcard,
+    // A number, string, boolean or nil compared with '=='
+    Literal(Object),
+    // A name matches any value and binds it
+    Binding(Token),
+    // 'Point(x, y: 0)' matches instances of the class (or a subclass) whose
+    // fields match. A field without a pattern binds a variable of its name.
+    Instance {
+        class: Rc<Expr>,
+        name: Token,
+        fields: Vec<(Token, Pattern)>,
+    },
+    // 'Color.Red' matches that enum value and 'Shape.Circle(r)' the values
+    // of the variant whose data match, like the fields of an instance
+    Variant {
+        enumeration: Rc<Expr>,
+        name: Token,
+        fields: Vec<(Token, Pattern)>,
+    },
+}
+
+impl Pattern {
+    // Names bound by the pattern in the order they appear
+    pub fn bindings(&self) -> Vec<Token> {
+        match self {
+            Pattern::Binding(name) => vec![name.clone()],
+            Pattern::Instance { fields, .. } | Pattern::Variant { fields, .. } => fields
+                .iter()
+                .flat_map(|(_, pattern)| pattern.bindings())
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+// 'var [a, b] = list;' binds list elements and 'var {x, y} = point;'
+// binds properties of an instance
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DestructureKind {
+    List,
+    Fields,
+}
+
+// 'case p1, p2 if guard => body'. The case matches if any of its
+// patterns does and the guard, evaluated with the bindings, is true.
+#[derive(Clone)]
+pub struct MatchCase {
+    pub patterns: Vec<Pattern>,
+    pub guard: Option<Rc<Expr>>,
+    pub body: Rc<Stmt>,
+}
@@ -1,10 +1,13 @@
+use crate::environment::VarKind;
 use crate::error::*;
 use crate::expr::*;
+use crate::functions_lox::FunctionKind;
 use crate::interpreter::*;
+use crate::pattern::Pattern;
 use crate::stmt::*;
 use crate::token::*;
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::ops::Deref;
 use std::rc::Rc;
 
@@ -12,10 +15,37 @@ pub struct Resolver<'a> {
     interpreter: &'a Interpreter,
     // Two RefCells needed to make both vector and hashmap mutable
     scopes: RefCell<Vec<RefCell<HashMap<String, bool>>>>,
+    // Names declared with 'const' in each of the scopes
+    constants: RefCell<Vec<HashSet<String>>>,
     in_loop: RefCell<bool>,
     had_error: RefCell<bool>,
     current_function: RefCell<FunctionType>,
     current_class: RefCell<ClassType>,
+    // Method names of the traits declared so far in each scope, the global
+    // one first, to report conflicts
+    traits: Declarations,
+    // Unimplemented abstract methods of the classes declared so far
+    abstract_methods: Declarations,
+    // Variant names of the enums declared so far, to catch misspelled variants
+    enums: Declarations,
+    // Private members of the classes being resolved, innermost last
+    privates: RefCell<Vec<PrivateScope>>,
+    // The only 'yield' expression allowed at this point. See 'resolve_yield_site'
+    yield_site: RefCell<Option<Rc<Expr>>>,
+}
+
+// Names a declaration lists, by the name it declares. A name only refers to
+// the declaration if nothing redeclared or assigned it since.
+type Declarations = RefCell<Vec<HashMap<String, Vec<String>>>>;
+
+// Private names a class declares (by assigning them through 'this' or with
+// a private method) and those it reads, which must be among the former
+struct PrivateScope {
+    owner: String,
+    // The id the interpreter keys the private members on
+    id: usize,
+    declared: HashSet<String>,
+    used: Vec<Token>,
 }
 
 #[derive(PartialEq)]
@@ -23,6 +53,7 @@ enum FunctionType {
     None,
     Function,
     Method,
+    Initializer,
 }
 
 #[derive(PartialEq)]
@@ -30,6 +61,16 @@ enum ClassType {
     None,
     Class,
     Subclass,
+    Trait,
+}
+
+// Setters are keyed as 'name=' like in the interpreter's method tables
+fn method_key(method: &FunctionStmt) -> String {
+    if method.kind == FunctionKind::Setter {
+        format!("{}=", method.name.lexeme)
+    } else {
+        method.name.lexeme.clone()
+    }
 }
 
 impl<'a> Resolver<'a> {
@@ -37,10 +78,16 @@ impl<'a> Resolver<'a> {
         Self {
             interpreter,
             scopes: RefCell::new(Vec::new()),
+            constants: RefCell::new(Vec::new()),
             had_error: RefCell::new(false),
             in_loop: RefCell::new(false),
             current_function: RefCell::new(FunctionType::None),
             current_class: RefCell::new(ClassType::None),
+            traits: RefCell::new(vec![HashMap::new()]),
+            abstract_methods: RefCell::new(vec![HashMap::new()]),
+            enums: RefCell::new(vec![HashMap::new()]),
+            privates: RefCell::new(Vec::new()),
+            yield_site: RefCell::new(None),
         }
     }
     pub fn resolve(&self, stmts: &Rc<Vec<Rc<Stmt>>>) -> Result<(), LoxResult> {
@@ -55,13 +102,35 @@ impl<'a> Resolver<'a> {
     fn resolve_expr(&self, expr: Rc<Expr>) -> Result<(), LoxResult> {
         expr.accept(expr.clone(), self)
     }
+    // A generator can only suspend at a 'yield' that is a statement of its
+    // own, the initializer of a variable or the value of an assignment to a
+    // variable. A 'yield' anywhere else inside 'expr' is reported.
+    fn resolve_yield_site(&self, expr: Rc<Expr>) -> Result<(), LoxResult> {
+        let site = match expr.deref() {
+            Expr::Assign(assign) => assign.value.clone(),
+            _ => expr.clone(),
+        };
+        let enclosing_site = self.yield_site.replace(Some(site));
+        let result = self.resolve_expr(expr);
+        self.yield_site.replace(enclosing_site);
+        result
+    }
     fn begin_scope(&self) {
-        self.scopes.borrow_mut().push(RefCell::new(HashMap::new()))
+        self.scopes.borrow_mut().push(RefCell::new(HashMap::new()));
+        self.constants.borrow_mut().push(HashSet::new());
+        self.traits.borrow_mut().push(HashMap::new());
+        self.abstract_methods.borrow_mut().push(HashMap::new());
+        self.enums.borrow_mut().push(HashMap::new());
     }
     fn end_scope(&self) {
         self.scopes.borrow_mut().pop();
+        self.constants.borrow_mut().pop();
+        self.traits.borrow_mut().pop();
+        self.abstract_methods.borrow_mut().pop();
+        self.enums.borrow_mut().pop();
     }
     fn declare(&self, name: &Token) {
+        self.forget_declaration(self.scopes.borrow().len(), name);
         if !self.scopes.borrow().is_empty() {
             // Add variable to the innermost scope so it shadows outer ones if any
             // Mark it as false (not ready yet). Value associated with a key in the
@@ -99,6 +168,233 @@ impl<'a> Resolver<'a> {
         }
     }
 
+    fn mark_constant(&self, name: &Token) {
+        if let Some(constants) = self.constants.borrow_mut().last_mut() {
+            constants.insert(name.lexeme.clone());
+        }
+    }
+
+    // Index of the scope 'name' refers to in the declaration tables, where
+    // the global scope comes first
+    fn declaration_scope(&self, name: &Token) -> usize {
+        let scopes = self.scopes.borrow();
+        scopes
+            .iter()
+            .rposition(|scope| scope.borrow().contains_key(&name.lexeme))
+            .map_or(0, |index| index + 1)
+    }
+
+    // What the declaration 'name' refers to lists, if it is known here
+    fn declared(&self, table: &Declarations, name: &Token) -> Option<Vec<String>> {
+        let scope = self.declaration_scope(name);
+        table.borrow()[scope].get(&name.lexeme).cloned()
+    }
+
+    fn record_declaration(&self, table: &Declarations, name: &Token, names: Vec<String>) {
+        if let Some(declarations) = table.borrow_mut().last_mut() {
+            declarations.insert(name.lexeme.clone(), names);
+        }
+    }
+
+    fn forget_declaration(&self, scope: usize, name: &Token) {
+        self.traits.borrow_mut()[scope].remove(&name.lexeme);
+        self.abstract_methods.borrow_mut()[scope].remove(&name.lexeme);
+        self.enums.borrow_mut()[scope].remove(&name.lexeme);
+    }
+
+    // Only local constants are known here. Assigning to a global constant
+    // is an error at runtime.
+    fn check_assignable(&self, name: &Token) {
+        let scopes = self.scopes.borrow();
+        let constants = self.constants.borrow();
+        for (scope, constants) in scopes.iter().rev().zip(constants.iter().rev()) {
+            if scope.borrow().contains_key(&name.lexeme) {
+                if constants.contains(&name.lexeme) {
+                    self.resolve_error(name, "Can't assign to a constant");
+                }
+                return;
+            }
+        }
+    }
+
+    fn resolve_method(
+        &self,
+        base: Rc<Stmt>,
+        owner: &Token,
+        method: &Rc<Stmt>,
+        is_static: bool,
+    ) -> Result<(), LoxResult> {
+        if let Stmt::Function(method) = method.deref() {
+            let declaration = if method.name.lexeme == "init" && !is_static {
+                FunctionType::Initializer
+            } else {
+                FunctionType::Method
+            };
+            match method.kind {
+                FunctionKind::Getter if !method.params.is_empty() => {
+                    self.resolve_error(&method.name, "A getter can't have parameters")
+                }
+                FunctionKind::Setter if method.params.len() != 1 || method.rest.is_some() => {
+                    self.resolve_error(&method.name, "A setter must have exactly one parameter")
+                }
+                _ => {}
+            }
+            self.resolve_function(base, method, declaration)
+        } else {
+            Err(LoxResult::error_runtime(owner, "failed to resolve method"))
+        }
+    }
+
+    // Two traits of a class must not provide the same method unless the
+    // class overrides it. Traits declared out of sight are checked at runtime.
+    fn check_trait_conflicts(&self, stmt: &ClassStmt) {
+        let own: HashSet<String> = stmt
+            .methods
+            .iter()
+            .filter_map(|method| match method.deref() {
+                Stmt::Function(method) => Some(method_key(method)),
+                _ => None,
+            })
+            .collect();
+        let mut provided: HashMap<String, String> = HashMap::new();
+        for tr in stmt.traits.iter() {
+            let Expr::Variable(v) = tr.deref() else {
+                continue;
+            };
+            let Some(names) = self.declared(&self.traits, &v.name) else {
+                continue;
+            };
+            for name in names.into_iter().filter(|name| !own.contains(name)) {
+                match provided.get(&name) {
+                    Some(other) if other != &v.name.lexeme => self.resolve_error(
+                        &v.name,
+                        &format!(
+                            "Method '{}' is defined by both traits '{}' and '{}'",
+                            name.trim_end_matches('='),
+                            other,
+                            v.name.lexeme
+                        ),
+                    ),
+                    _ => {
+                        provided.insert(name, v.name.lexeme.clone());
+                    }
+                }
+            }
+        }
+    }
+
+    // A class that isn't abstract must implement the abstract methods it
+    // inherits. Only superclasses declared before the class are checked here,
+    // the rest is left to instantiation.
+    fn check_abstract_methods(&self, stmt: &ClassStmt) {
+        let mut missing = match stmt.superclass.as_deref() {
+            Some(Expr::Variable(v)) => self
+                .declared(&self.abstract_methods, &v.name)
+                .unwrap_or_default(),
+            _ => Vec::new(),
+        };
+        let included: Vec<String> = stmt
+            .traits
+            .iter()
+            .filter_map(|tr| match tr.deref() {
+                Expr::Variable(v) => self.declared(&self.traits, &v.name),
+                _ => None,
+            })
+            .flatten()
+            .collect();
+        missing.retain(|name| {
+            !included.contains(name)
+                && !stmt.methods.iter().any(|method| match method.deref() {
+                    Stmt::Function(method) => &method_key(method) == name,
+                    _ => false,
+                })
+        });
+        if !stmt.is_abstract && !missing.is_empty() {
+            self.resolve_error(
+                &stmt.name,
+                &format!(
+                    "Class '{}' must implement the abstract methods: {}",
+                    stmt.name.lexeme,
+                    missing.join(", ")
+                ),
+            );
+        }
+        missing.extend(stmt.abstract_methods.iter().map(|name| name.lexeme.clone()));
+        missing.sort();
+        missing.dedup();
+        self.record_declaration(&self.abstract_methods, &stmt.name, missing);
+    }
+
+    // Private methods and fields declared in the body count as declared
+    fn begin_privates(
+        &self,
+        owner: &Token,
+        declaration: &Rc<Stmt>,
+        members: &[&Rc<Vec<Rc<Stmt>>>],
+    ) {
+        let declared = members
+            .iter()
+            .flat_map(|members| members.iter())
+            .filter_map(|member| match member.deref() {
+                Stmt::Function(method) if method.name.ttype == TokenType::PrivateName => {
+                    Some(method.name.lexeme.clone())
+                }
+                Stmt::Var(field) if field.name.ttype == TokenType::PrivateName => {
+                    Some(field.name.lexeme.clone())
+                }
+                _ => None,
+            })
+            .collect();
+        self.privates.borrow_mut().push(PrivateScope {
+            owner: owner.lexeme.clone(),
+            id: self.interpreter.private_owner(declaration),
+            declared,
+            used: Vec::new(),
+        });
+    }
+
+    fn end_privates(&self) {
+        let scope = self.privates.borrow_mut().pop().unwrap();
+        for name in scope.used {
+            if !scope.declared.contains(&name.lexeme) {
+                self.resolve_error(
+                    &name,
+                    &format!(
+                        "Undefined private member '{}' in '{}'",
+                        name.lexeme, scope.owner
+                    ),
+                );
+            }
+        }
+    }
+
+    // Private members are only reachable through 'this' inside the class
+    // that declares them
+    fn resolve_member(&self, base: Rc<Expr>, object: &Rc<Expr>, name: &Token, is_set: bool) {
+        if name.ttype != TokenType::PrivateName {
+            return;
+        }
+        if !matches!(object.deref(), Expr::This(_)) {
+            self.resolve_error(
+                name,
+                &format!(
+                    "Private member '{}' can only be accessed through 'this'",
+                    name.lexeme
+                ),
+            );
+        }
+        let mut privates = self.privates.borrow_mut();
+        let Some(scope) = privates.last_mut() else {
+            return;
+        };
+        self.interpreter.resolve_private(base, scope.id);
+        if is_set {
+            scope.declared.insert(name.lexeme.clone());
+        } else {
+            scope.used.push(name.clone());
+        }
+    }
+
     // Unlike variable, define functions eagerly so that a function
     // can recursively refer to itself.
     fn resolve_function(
@@ -109,16 +405,65 @@ impl<'a> Resolver<'a> {
     ) -> Result<(), LoxResult> {
         let enclosing_function = self.current_function.replace(ftype);
         self.begin_scope();
-        for param in function.params.deref() {
+        // A default can refer to the parameters before it
+        let first_default = function.params.len() - function.defaults.len();
+        for (i, param) in function.params.iter().enumerate() {
+            if i >= first_default {
+                self.resolve_expr(function.defaults[i - first_default].clone())?;
+            }
             self.declare(param);
             self.define(param);
         }
+        if let Some(rest) = &function.rest {
+            self.declare(rest);
+            self.define(rest);
+        }
         self.resolve(&function.body)?;
         self.end_scope();
         self.current_function.replace(enclosing_function);
         Ok(())
     }
 
+    // Class names in patterns are looked up where the match is
+    fn resolve_pattern(&self, pattern: &Pattern) -> Result<(), LoxResult> {
+        match pattern {
+            Pattern::Instance { class, fields, .. } => {
+                self.resolve_expr(class.clone())?;
+                for (_, pattern) in fields {
+                    self.resolve_pattern(pattern)?;
+                }
+            }
+            Pattern::Variant {
+                enumeration,
+                name,
+                fields,
+            } => {
+                self.check_variant(enumeration, name);
+                self.resolve_expr(enumeration.clone())?;
+                for (_, pattern) in fields {
+                    self.resolve_pattern(pattern)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    // 'Color.Purple' where 'Color' is an enum declared without that variant
+    fn check_variant(&self, enumeration: &Rc<Expr>, name: &Token) {
+        let Expr::Variable(v) = enumeration.deref() else {
+            return;
+        };
+        if let Some(variants) = self.declared(&self.enums, &v.name) {
+            if !variants.contains(&name.lexeme) {
+                self.resolve_error(
+                    name,
+                    &format!("Enum '{}' has no variant '{}'", v.name.lexeme, name.lexeme),
+                );
+            }
+        }
+    }
+
     pub fn resolve_error(&self, token: &Token, message: &str) {
         self.had_error.replace(true);
         LoxResult::error_runtime(token, message);
@@ -141,6 +486,24 @@ impl<'a> StmtVisitor<()> for Resolver<'a> {
 
         self.declare(&stmt.name);
         self.define(&stmt.name);
+        let mut names: Vec<&String> = Vec::new();
+        for field in stmt.record_fields.iter().flat_map(|fields| fields.iter()) {
+            if names.contains(&&field.lexeme) {
+                self.resolve_error(
+                    field,
+                    &format!(
+                        "Field '{}' is already declared in record '{}'",
+                        field.lexeme, stmt.name.lexeme
+                    ),
+                );
+            }
+            names.push(&field.lexeme);
+        }
+        self.begin_privates(
+            &stmt.name,
+            &base,
+            &[&stmt.methods, &stmt.static_methods, &stmt.fields],
+        );
 
         // If found a superclass, resolve the expression as it is possible
         // that a superclass name refers to a local variable.
@@ -153,7 +516,13 @@ impl<'a> StmtVisitor<()> for Resolver<'a> {
                 }
             }
             self.resolve_expr(superclass.clone())?;
-
+        }
+        for tr in stmt.traits.iter() {
+            self.resolve_expr(tr.clone())?;
+        }
+        self.check_trait_conflicts(stmt);
+        self.check_abstract_methods(stmt);
+        if stmt.superclass.is_some() {
             // scope for environment that contains 'super'. begin_scope() is conditional
             // because there is no need to create a scope for the environment holding
             // 'super' if the current class has no superclass.
@@ -177,27 +546,98 @@ impl<'a> StmtVisitor<()> for Resolver<'a> {
             .unwrap()
             .borrow_mut()
             .insert("this".to_string(), true);
-        for method in stmt.methods.deref() {
-            let declaration = FunctionType::Method;
-            if let Stmt::Function(method) = method.deref() {
-                self.resolve_function(base.clone(), method, declaration)?;
-            } else {
-                return Err(LoxResult::error_runtime(
-                    &stmt.name,
-                    "failed to resolve method",
-                ));
+        // Field initializers see 'this' but are not inside a function
+        for field in stmt.fields.iter() {
+            if let Stmt::Var(field) = field.deref() {
+                if let Some(initializer) = &field.initializer {
+                    self.resolve_expr(initializer.clone())?;
+                }
             }
         }
+        // Static methods are resolved like instance methods. 'this' inside
+        // them refers to the class instead of an instance.
+        let static_methods = stmt.static_methods.iter().map(|method| (method, true));
+        for (method, is_static) in stmt
+            .methods
+            .iter()
+            .map(|m| (m, false))
+            .chain(static_methods)
+        {
+            self.resolve_method(base.clone(), &stmt.name, method, is_static)?;
+        }
         self.end_scope();
         // End scope for the environment enclosing 'super' keyword
         if stmt.superclass.is_some() {
             self.end_scope();
         }
+        self.end_privates();
+        self.current_class.replace(enclosing_class);
+        Ok(())
+    }
+    fn visit_enum_stmt(&self, _: Rc<Stmt>, stmt: &EnumStmt) -> Result<(), LoxResult> {
+        self.declare(&stmt.name);
+        self.define(&stmt.name);
+        let mut names: Vec<String> = Vec::new();
+        for variant in stmt.variants.iter() {
+            if names.contains(&variant.name.lexeme) {
+                self.resolve_error(
+                    &variant.name,
+                    &format!(
+                        "Variant '{}' is already declared in enum '{}'",
+                        variant.name.lexeme, stmt.name.lexeme
+                    ),
+                );
+            }
+            names.push(variant.name.lexeme.clone());
+        }
+        self.record_declaration(&self.enums, &stmt.name, names);
+        Ok(())
+    }
+    // Trait methods are resolved like the methods of a class without a superclass
+    fn visit_trait_stmt(&self, base: Rc<Stmt>, stmt: &TraitStmt) -> Result<(), LoxResult> {
+        let enclosing_class = self.current_class.replace(ClassType::Trait);
+        self.declare(&stmt.name);
+        self.define(&stmt.name);
+        self.begin_privates(&stmt.name, &base, &[&stmt.methods]);
+
+        self.begin_scope();
+        self.scopes
+            .borrow()
+            .last()
+            .unwrap()
+            .borrow_mut()
+            .insert("this".to_string(), true);
+        let mut names = Vec::new();
+        for method in stmt.methods.iter() {
+            self.resolve_method(base.clone(), &stmt.name, method, false)?;
+            // Private methods of different traits never clash
+            match method.deref() {
+                Stmt::Function(method) if method.name.ttype != TokenType::PrivateName => {
+                    names.push(method_key(method))
+                }
+                _ => {}
+            }
+        }
+        self.end_scope();
+        self.end_privates();
+        self.record_declaration(&self.traits, &stmt.name, names);
         self.current_class.replace(enclosing_class);
         Ok(())
     }
     fn visit_expression_stmt(&self, _: Rc<Stmt>, stmt: &ExpressionStmt) -> Result<(), LoxResult> {
-        self.resolve_expr(stmt.expression.clone())
+        self.resolve_yield_site(stmt.expression.clone())
+    }
+    // The loop variable lives in a scope of its own around the body
+    fn visit_forin_stmt(&self, _: Rc<Stmt>, stmt: &ForInStmt) -> Result<(), LoxResult> {
+        self.resolve_expr(stmt.iterable.clone())?;
+        let nesting_prev = self.in_loop.replace(true);
+        self.begin_scope();
+        self.declare(&stmt.name);
+        self.define(&stmt.name);
+        self.resolve_stmt(stmt.body.clone())?;
+        self.end_scope();
+        self.in_loop.replace(nesting_prev);
+        Ok(())
     }
     // Functions both bind names and introduce a scope.
     // The name of the fn itself is bound in the surrounding scope where it is declared.
@@ -216,6 +656,81 @@ impl<'a> StmtVisitor<()> for Resolver<'a> {
         }
         Ok(())
     }
+    fn visit_destructure_stmt(&self, _: Rc<Stmt>, stmt: &DestructureStmt) -> Result<(), LoxResult> {
+        self.resolve_expr(stmt.initializer.clone())?;
+        for name in stmt.names.iter() {
+            self.declare(name);
+            self.define(name);
+            if stmt.kind == VarKind::Const {
+                self.mark_constant(name);
+            }
+        }
+        Ok(())
+    }
+    fn visit_multiassign_stmt(&self, _: Rc<Stmt>, stmt: &MultiAssignStmt) -> Result<(), LoxResult> {
+        for value in stmt.values.iter() {
+            self.resolve_expr(value.clone())?;
+        }
+        for target in stmt.targets.iter() {
+            match target.deref() {
+                Expr::Variable(variable) => {
+                    self.check_assignable(&variable.name);
+                    let scope = self.declaration_scope(&variable.name);
+                    self.forget_declaration(scope, &variable.name);
+                    self.resolve_local(target.clone(), &variable.name);
+                }
+                Expr::Get(get) => {
+                    self.resolve_member(target.clone(), &get.object, &get.name, true);
+                    self.resolve_expr(get.object.clone())?;
+                }
+                Expr::Index(index) => {
+                    self.resolve_expr(index.object.clone())?;
+                    self.resolve_expr(index.index.clone())?;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+    // Each case is a scope of its own holding the names its patterns bind.
+    // Every alternative of a case has to bind the same names.
+    fn visit_match_stmt(&self, _: Rc<Stmt>, stmt: &MatchStmt) -> Result<(), LoxResult> {
+        self.resolve_expr(stmt.subject.clone())?;
+        for case in stmt.cases.iter() {
+            let names = |pattern: &Pattern| {
+                let mut names: Vec<String> = pattern
+                    .bindings()
+                    .into_iter()
+                    .map(|name| name.lexeme)
+                    .collect();
+                names.sort();
+                names
+            };
+            for pattern in case.patterns.iter() {
+                self.resolve_pattern(pattern)?;
+                if names(pattern) != names(&case.patterns[0]) {
+                    self.resolve_error(
+                        &stmt.keyword,
+                        "All patterns of a case must bind the same names",
+                    );
+                }
+            }
+            self.begin_scope();
+            for name in case.patterns[0].bindings() {
+                self.declare(&name);
+                self.define(&name);
+            }
+            if let Some(guard) = case.guard.clone() {
+                self.resolve_expr(guard)?;
+            }
+            self.resolve_stmt(case.body.clone())?;
+            self.end_scope();
+        }
+        if let Some(default) = stmt.default.clone() {
+            self.resolve_stmt(default)?;
+        }
+        Ok(())
+    }
     fn visit_print_stmt(&self, _: Rc<Stmt>, stmt: &PrintStmt) -> Result<(), LoxResult> {
         self.resolve_expr(stmt.expression.clone())
     }
@@ -231,9 +746,12 @@ impl<'a> StmtVisitor<()> for Resolver<'a> {
     fn visit_var_stmt(&self, _: Rc<Stmt>, stmt: &VarStmt) -> Result<(), LoxResult> {
         self.declare(&stmt.name);
         if let Some(init) = stmt.initializer.clone() {
-            self.resolve_expr(init)?;
+            self.resolve_yield_site(init)?;
         }
         self.define(&stmt.name);
+        if stmt.kind == VarKind::Const {
+            self.mark_constant(&stmt.name);
+        }
         Ok(())
     }
     fn visit_while_stmt(&self, _: Rc<Stmt>, stmt: &WhileStmt) -> Result<(), LoxResult> {
@@ -257,6 +775,8 @@ impl<'a> ExprVisitor<()> for Resolver<'a> {
     // to resolve the variable that is being assigned to.
     fn visit_assign_expr(&self, base: Rc<Expr>, expr: &AssignExpr) -> Result<(), LoxResult> {
         self.resolve_expr(expr.value.clone())?;
+        self.check_assignable(&expr.name);
+        self.forget_declaration(self.declaration_scope(&expr.name), &expr.name);
         self.resolve_local(base, &expr.name);
         Ok(())
     }
@@ -272,30 +792,69 @@ impl<'a> ExprVisitor<()> for Resolver<'a> {
         for arg in expr.arguments.clone() {
             self.resolve_expr(arg)?;
         }
+        for arg in expr.named.iter() {
+            self.resolve_expr(arg.value.clone())?;
+        }
         Ok(())
     }
     // property dispatch is dynamic since the property name is not resolved here
     // i.e. only the object is resolved not the token after '.'
-    fn visit_get_expr(&self, _: Rc<Expr>, expr: &GetExpr) -> Result<(), LoxResult> {
+    fn visit_get_expr(&self, base: Rc<Expr>, expr: &GetExpr) -> Result<(), LoxResult> {
+        self.resolve_member(base, &expr.object, &expr.name, false);
+        self.check_variant(&expr.object, &expr.name);
         self.resolve_expr(expr.object.clone())
     }
     fn visit_grouping_expr(&self, _: Rc<Expr>, expr: &GroupingExpr) -> Result<(), LoxResult> {
         self.resolve_expr(expr.expression.clone())
     }
+    fn visit_index_expr(&self, _: Rc<Expr>, expr: &IndexExpr) -> Result<(), LoxResult> {
+        self.resolve_expr(expr.object.clone())?;
+        self.resolve_expr(expr.index.clone())
+    }
+    fn visit_list_expr(&self, _: Rc<Expr>, expr: &ListExpr) -> Result<(), LoxResult> {
+        for element in expr.elements.iter() {
+            self.resolve_expr(element.clone())?;
+        }
+        Ok(())
+    }
     fn visit_logical_expr(&self, _: Rc<Expr>, expr: &LogicalExpr) -> Result<(), LoxResult> {
         self.resolve_expr(expr.left.clone())?;
         self.resolve_expr(expr.right.clone())
     }
-    fn visit_set_expr(&self, _base: Rc<Expr>, expr: &SetExpr) -> Result<(), LoxResult> {
+    fn visit_set_expr(&self, base: Rc<Expr>, expr: &SetExpr) -> Result<(), LoxResult> {
+        self.resolve_member(base, &expr.object, &expr.name, true);
         self.resolve_expr(expr.value.clone())?;
         self.resolve_expr(expr.object.clone())
     }
+    fn visit_setindex_expr(&self, _base: Rc<Expr>, expr: &SetIndexExpr) -> Result<(), LoxResult> {
+        self.resolve_expr(expr.value.clone())?;
+        self.resolve_expr(expr.object.clone())?;
+        self.resolve_expr(expr.index.clone())
+    }
+    fn visit_range_expr(&self, _: Rc<Expr>, expr: &RangeExpr) -> Result<(), LoxResult> {
+        self.resolve_expr(expr.start.clone())?;
+        self.resolve_expr(expr.end.clone())?;
+        if let Some(step) = expr.step.clone() {
+            self.resolve_expr(step)?;
+        }
+        Ok(())
+    }
+    fn visit_tuple_expr(&self, _: Rc<Expr>, expr: &TupleExpr) -> Result<(), LoxResult> {
+        for element in expr.elements.iter() {
+            self.resolve_expr(element.clone())?;
+        }
+        Ok(())
+    }
+    fn visit_spread_expr(&self, _: Rc<Expr>, expr: &SpreadExpr) -> Result<(), LoxResult> {
+        self.resolve_expr(expr.value.clone())
+    }
     fn visit_super_expr(&self, base: Rc<Expr>, expr: &SuperExpr) -> Result<(), LoxResult> {
         match *self.current_class.borrow() {
             ClassType::None => {
                 self.resolve_error(&expr.keyword, "Can't use 'super' outside of a class.")
             }
             ClassType::Subclass => {}
+            ClassType::Trait => self.resolve_error(&expr.keyword, "Can't use 'super' in a trait."),
             _ => self.resolve_error(
                 &expr.keyword,
                 "Can't use 'super' in a class that has no superclass.",
@@ -336,4 +895,25 @@ impl<'a> ExprVisitor<()> for Resolver<'a> {
             Ok(())
         }
     }
+    fn visit_yield_expr(&self, base: Rc<Expr>, expr: &YieldExpr) -> Result<(), LoxResult> {
+        match *self.current_function.borrow() {
+            FunctionType::None => {
+                self.resolve_error(&expr.keyword, "Can't yield outside of a function")
+            }
+            FunctionType::Initializer => {
+                self.resolve_error(&expr.keyword, "Can't yield from an initializer")
+            }
+            _ => {}
+        }
+        if self.yield_site.borrow().as_ref() != Some(&base) {
+            self.resolve_error(
+                &expr.keyword,
+                "'yield' can only be a statement, a variable initializer or an assigned value",
+            );
+        }
+        if let Some(value) = expr.value.clone() {
+            self.resolve_expr(value)?;
+        }
+        Ok(())
+    }
 }
@@ -1,4 +1,5 @@
 use crate::error::*;
+use crate::numeric;
 use crate::object::*;
 use crate::token::*;
 use lazy_static::lazy_static;
@@ -7,22 +8,35 @@ use std::collections::HashMap;
 lazy_static! {
     static ref KEYWORDS: HashMap<String, TokenType> = {
         let mut m = HashMap::new();
+        m.insert("abstract".into(), TokenType::Abstract);
         m.insert("and".into(), TokenType::And);
+        m.insert("case".into(), TokenType::Case);
         m.insert("class".into(), TokenType::Class);
+        m.insert("default".into(), TokenType::Default);
+        m.insert("const".into(), TokenType::Const);
         m.insert("else".into(), TokenType::Else);
+        m.insert("enum".into(), TokenType::Enum);
         m.insert("false".into(), TokenType::False);
         m.insert("for".into(), TokenType::For);
         m.insert("fun".into(), TokenType::Fun);
         m.insert("if".into(), TokenType::If);
+        m.insert("in".into(), TokenType::In);
+        m.insert("let".into(), TokenType::Let);
+        m.insert("match".into(), TokenType::Match);
         m.insert("nil".into(), TokenType::Nil);
         m.insert("or".into(), TokenType::Or);
         m.insert("print".into(), TokenType::Print);
+        m.insert("record".into(), TokenType::Record);
         m.insert("return".into(), TokenType::Return);
+        m.insert("static".into(), TokenType::Static);
         m.insert("super".into(), TokenType::Super);
         m.insert("this".into(), TokenType::This);
+        m.insert("trait".into(), TokenType::Trait);
         m.insert("true".into(), TokenType::True);
         m.insert("var".into(), TokenType::Var);
         m.insert("while".into(), TokenType::While);
+        m.insert("with".into(), TokenType::With);
+        m.insert("yield".into(), TokenType::Yield);
         m.insert("break".into(), TokenType::Break);
         m
     };
@@ -61,14 +75,33 @@ impl Scanner {
             ')' => self.add_token(TokenType::RightParen, None),
             '{' => self.add_token(TokenType::LeftBrace, None),
             '}' => self.add_token(TokenType::RightBrace, None),
+            '[' => self.add_token(TokenType::LeftBracket, None),
+            ']' => self.add_token(TokenType::RightBracket, None),
+            ':' => self.add_token(TokenType::Colon, None),
             ',' => self.add_token(TokenType::Comma, None),
-            '.' => self.add_token(TokenType::Dot, None),
+            '.' => {
+                if self.peek() == '.' && self.peek_next() == '.' {
+                    self.advance();
+                    self.advance();
+                    self.add_token(TokenType::Ellipsis, None)
+                } else if self.matches('.') {
+                    self.add_token_twin('=', TokenType::DotDotEqual, TokenType::DotDot)
+                } else {
+                    self.add_token(TokenType::Dot, None)
+                }
+            }
             '-' => self.add_token(TokenType::Minus, None),
             '+' => self.add_token(TokenType::Plus, None),
             ';' => self.add_token(TokenType::Semicolon, None),
             '*' => self.add_token(TokenType::Star, None),
             '!' => self.add_token_twin('=', TokenType::BangEqual, TokenType::Bang),
-            '=' => self.add_token_twin('=', TokenType::EqualEqual, TokenType::Equal),
+            '=' => {
+                if self.matches('>') {
+                    self.add_token(TokenType::FatArrow, None)
+                } else {
+                    self.add_token_twin('=', TokenType::EqualEqual, TokenType::Equal)
+                }
+            }
             '<' => self.add_token_twin('=', TokenType::LessEqual, TokenType::Less),
             '>' => self.add_token_twin('=', TokenType::GreaterEqual, TokenType::Greater),
             '/' => self.handle_slash()?,
@@ -78,6 +111,7 @@ impl Scanner {
                 self.col = 0;
             }
             '"' => self.handle_string()?,
+            '#' => self.handle_private_name()?,
             _ => self.handle_longer_lexemes(c)?,
         }
         Ok(())
@@ -202,7 +236,7 @@ impl Scanner {
     }
 
     fn handle_longer_lexemes(&mut self, c: char) -> Result<(), LoxResult> {
-        if c.is_digit(10) {
+        if c.is_ascii_digit() {
             self.handle_number()
         } else if Self::is_alphabetic(c) {
             self.handle_identifier()
@@ -217,23 +251,29 @@ impl Scanner {
     }
 
     fn handle_number(&mut self) {
-        while self.peek().is_digit(10) {
+        while self.peek().is_ascii_digit() {
             self.advance();
         }
 
         // Look for a fractional part
-        if self.peek() == '.' && self.peek_next().is_digit(10) {
+        let is_float = self.peek() == '.' && self.peek_next().is_ascii_digit();
+        if is_float {
             // Consume the "."
             self.advance();
         }
 
-        while self.peek().is_digit(10) {
+        while self.peek().is_ascii_digit() {
             self.advance();
         }
 
+        // Literals without a fractional part are integers
         let s: String = self.source[self.start..self.current].iter().collect();
-        let val: f64 = s.parse().unwrap();
-        self.add_token(TokenType::Number, Some(Object::Number(val)))
+        let val = if is_float {
+            Object::Number(s.parse().unwrap())
+        } else {
+            numeric::parse_int(&s).unwrap()
+        };
+        self.add_token(TokenType::Number, Some(val))
     }
 
     fn handle_identifier(&mut self) {
@@ -251,6 +291,22 @@ impl Scanner {
         self.add_token(ttype, literal)
     }
 
+    // Private member names are written '#name'
+    fn handle_private_name(&mut self) -> Result<(), LoxResult> {
+        if !Self::is_alphabetic(self.peek()) {
+            return Err(LoxResult::error(
+                self.line,
+                self.col,
+                "Expect a name after '#'",
+            ));
+        }
+        while Self::is_alphanumeric(self.peek()) {
+            self.advance();
+        }
+        self.add_token(TokenType::PrivateName, None);
+        Ok(())
+    }
+
     fn matches(&mut self, expected: char) -> bool {
         if self.is_at_end() {
             return false;
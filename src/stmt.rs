@@ -1,8 +1,12 @@
 // This is an autogenerated file. Do not edit manually. Use gen-ast package.
 // Use gen-ast package to generate this file.
 
+use crate::environment::VarKind;
 use crate::error::*;
 use crate::expr::Expr;
+use crate::functions_lox::FunctionKind;
+use crate::lox_enum::VariantDecl;
+use crate::pattern::{DestructureKind, MatchCase};
 use crate::token::Token;
 use std::hash::Hash;
 use std::hash::Hasher;
@@ -11,11 +15,17 @@ use std::rc::Rc;
 pub enum Stmt {
     Block(Rc<BlockStmt>),
     Class(Rc<ClassStmt>),
+    Destructure(Rc<DestructureStmt>),
+    Enum(Rc<EnumStmt>),
     Expression(Rc<ExpressionStmt>),
+    ForIn(Rc<ForInStmt>),
     Function(Rc<FunctionStmt>),
     If(Rc<IfStmt>),
+    Match(Rc<MatchStmt>),
+    MultiAssign(Rc<MultiAssignStmt>),
     Print(Rc<PrintStmt>),
     Return(Rc<ReturnStmt>),
+    Trait(Rc<TraitStmt>),
     Var(Rc<VarStmt>),
     While(Rc<WhileStmt>),
     Break(Rc<BreakStmt>),
@@ -26,11 +36,17 @@ impl PartialEq for Stmt {
         match (self, other) {
             (Stmt::Block(a), Stmt::Block(b)) => Rc::ptr_eq(a, b),
             (Stmt::Class(a), Stmt::Class(b)) => Rc::ptr_eq(a, b),
+            (Stmt::Destructure(a), Stmt::Destructure(b)) => Rc::ptr_eq(a, b),
+            (Stmt::Enum(a), Stmt::Enum(b)) => Rc::ptr_eq(a, b),
             (Stmt::Expression(a), Stmt::Expression(b)) => Rc::ptr_eq(a, b),
+            (Stmt::ForIn(a), Stmt::ForIn(b)) => Rc::ptr_eq(a, b),
             (Stmt::Function(a), Stmt::Function(b)) => Rc::ptr_eq(a, b),
             (Stmt::If(a), Stmt::If(b)) => Rc::ptr_eq(a, b),
+            (Stmt::Match(a), Stmt::Match(b)) => Rc::ptr_eq(a, b),
+            (Stmt::MultiAssign(a), Stmt::MultiAssign(b)) => Rc::ptr_eq(a, b),
             (Stmt::Print(a), Stmt::Print(b)) => Rc::ptr_eq(a, b),
             (Stmt::Return(a), Stmt::Return(b)) => Rc::ptr_eq(a, b),
+            (Stmt::Trait(a), Stmt::Trait(b)) => Rc::ptr_eq(a, b),
             (Stmt::Var(a), Stmt::Var(b)) => Rc::ptr_eq(a, b),
             (Stmt::While(a), Stmt::While(b)) => Rc::ptr_eq(a, b),
             (Stmt::Break(a), Stmt::Break(b)) => Rc::ptr_eq(a, b),
@@ -50,21 +66,39 @@ impl Hash for Stmt {
             Stmt::Class(a) => {
                 hasher.write_usize(Rc::as_ptr(a) as usize);
             }
+            Stmt::Destructure(a) => {
+                hasher.write_usize(Rc::as_ptr(a) as usize);
+            }
+            Stmt::Enum(a) => {
+                hasher.write_usize(Rc::as_ptr(a) as usize);
+            }
             Stmt::Expression(a) => {
                 hasher.write_usize(Rc::as_ptr(a) as usize);
             }
+            Stmt::ForIn(a) => {
+                hasher.write_usize(Rc::as_ptr(a) as usize);
+            }
             Stmt::Function(a) => {
                 hasher.write_usize(Rc::as_ptr(a) as usize);
             }
             Stmt::If(a) => {
                 hasher.write_usize(Rc::as_ptr(a) as usize);
             }
+            Stmt::Match(a) => {
+                hasher.write_usize(Rc::as_ptr(a) as usize);
+            }
+            Stmt::MultiAssign(a) => {
+                hasher.write_usize(Rc::as_ptr(a) as usize);
+            }
             Stmt::Print(a) => {
                 hasher.write_usize(Rc::as_ptr(a) as usize);
             }
             Stmt::Return(a) => {
                 hasher.write_usize(Rc::as_ptr(a) as usize);
             }
+            Stmt::Trait(a) => {
+                hasher.write_usize(Rc::as_ptr(a) as usize);
+            }
             Stmt::Var(a) => {
                 hasher.write_usize(Rc::as_ptr(a) as usize);
             }
@@ -83,11 +117,17 @@ impl Stmt {
         match self {
             Stmt::Block(v) => visitor.visit_block_stmt(base, v),
             Stmt::Class(v) => visitor.visit_class_stmt(base, v),
+            Stmt::Destructure(v) => visitor.visit_destructure_stmt(base, v),
+            Stmt::Enum(v) => visitor.visit_enum_stmt(base, v),
             Stmt::Expression(v) => visitor.visit_expression_stmt(base, v),
+            Stmt::ForIn(v) => visitor.visit_forin_stmt(base, v),
             Stmt::Function(v) => visitor.visit_function_stmt(base, v),
             Stmt::If(v) => visitor.visit_if_stmt(base, v),
+            Stmt::Match(v) => visitor.visit_match_stmt(base, v),
+            Stmt::MultiAssign(v) => visitor.visit_multiassign_stmt(base, v),
             Stmt::Print(v) => visitor.visit_print_stmt(base, v),
             Stmt::Return(v) => visitor.visit_return_stmt(base, v),
+            Stmt::Trait(v) => visitor.visit_trait_stmt(base, v),
             Stmt::Var(v) => visitor.visit_var_stmt(base, v),
             Stmt::While(v) => visitor.visit_while_stmt(base, v),
             Stmt::Break(v) => visitor.visit_break_stmt(base, v),
@@ -105,6 +145,28 @@ pub struct ClassStmt {
     pub name: Token,
     pub superclass: Option<Rc<Expr>>,
     pub methods: Rc<Vec<Rc<Stmt>>>,
+    pub static_methods: Rc<Vec<Rc<Stmt>>>,
+    pub traits: Rc<Vec<Rc<Expr>>>,
+    pub is_abstract: bool,
+    pub abstract_methods: Rc<Vec<Token>>,
+    pub fields: Rc<Vec<Rc<Stmt>>>,
+    pub strict: bool,
+    pub record_fields: Option<Rc<Vec<Token>>>,
+}
+
+#[derive(Clone)]
+pub struct DestructureStmt {
+    pub bracket: Token,
+    pub shape: DestructureKind,
+    pub kind: VarKind,
+    pub names: Rc<Vec<Token>>,
+    pub initializer: Rc<Expr>,
+}
+
+#[derive(Clone)]
+pub struct EnumStmt {
+    pub name: Token,
+    pub variants: Rc<Vec<VariantDecl>>,
 }
 
 #[derive(Clone)]
@@ -112,10 +174,20 @@ pub struct ExpressionStmt {
     pub expression: Rc<Expr>,
 }
 
+#[derive(Clone)]
+pub struct ForInStmt {
+    pub name: Token,
+    pub iterable: Rc<Expr>,
+    pub body: Rc<Stmt>,
+}
+
 #[derive(Clone)]
 pub struct FunctionStmt {
     pub name: Token,
+    pub kind: FunctionKind,
     pub params: Rc<Vec<Token>>,
+    pub defaults: Rc<Vec<Rc<Expr>>>,
+    pub rest: Option<Token>,
     pub body: Rc<Vec<Rc<Stmt>>>,
 }
 
@@ -126,6 +198,21 @@ pub struct IfStmt {
     pub else_branch: Option<Rc<Stmt>>,
 }
 
+#[derive(Clone)]
+pub struct MatchStmt {
+    pub keyword: Token,
+    pub subject: Rc<Expr>,
+    pub cases: Rc<Vec<MatchCase>>,
+    pub default: Option<Rc<Stmt>>,
+}
+
+#[derive(Clone)]
+pub struct MultiAssignStmt {
+    pub targets: Rc<Vec<Rc<Expr>>>,
+    pub equals: Token,
+    pub values: Rc<Vec<Rc<Expr>>>,
+}
+
 #[derive(Clone)]
 pub struct PrintStmt {
     pub expression: Rc<Expr>,
@@ -137,9 +224,16 @@ pub struct ReturnStmt {
     pub value: Option<Rc<Expr>>,
 }
 
+#[derive(Clone)]
+pub struct TraitStmt {
+    pub name: Token,
+    pub methods: Rc<Vec<Rc<Stmt>>>,
+}
+
 #[derive(Clone)]
 pub struct VarStmt {
     pub name: Token,
+    pub kind: VarKind,
     pub initializer: Option<Rc<Expr>>,
 }
 
@@ -157,11 +251,17 @@ pub struct BreakStmt {
 pub trait StmtVisitor<T> {
     fn visit_block_stmt(&self, base: Rc<Stmt>, stmt: &BlockStmt) -> Result<T, LoxResult>;
     fn visit_class_stmt(&self, base: Rc<Stmt>, stmt: &ClassStmt) -> Result<T, LoxResult>;
+    fn visit_destructure_stmt(&self, base: Rc<Stmt>, stmt: &DestructureStmt) -> Result<T, LoxResult>;
+    fn visit_enum_stmt(&self, base: Rc<Stmt>, stmt: &EnumStmt) -> Result<T, LoxResult>;
     fn visit_expression_stmt(&self, base: Rc<Stmt>, stmt: &ExpressionStmt) -> Result<T, LoxResult>;
+    fn visit_forin_stmt(&self, base: Rc<Stmt>, stmt: &ForInStmt) -> Result<T, LoxResult>;
     fn visit_function_stmt(&self, base: Rc<Stmt>, stmt: &FunctionStmt) -> Result<T, LoxResult>;
     fn visit_if_stmt(&self, base: Rc<Stmt>, stmt: &IfStmt) -> Result<T, LoxResult>;
+    fn visit_match_stmt(&self, base: Rc<Stmt>, stmt: &MatchStmt) -> Result<T, LoxResult>;
+    fn visit_multiassign_stmt(&self, base: Rc<Stmt>, stmt: &MultiAssignStmt) -> Result<T, LoxResult>;
     fn visit_print_stmt(&self, base: Rc<Stmt>, stmt: &PrintStmt) -> Result<T, LoxResult>;
     fn visit_return_stmt(&self, base: Rc<Stmt>, stmt: &ReturnStmt) -> Result<T, LoxResult>;
+    fn visit_trait_stmt(&self, base: Rc<Stmt>, stmt: &TraitStmt) -> Result<T, LoxResult>;
     fn visit_var_stmt(&self, base: Rc<Stmt>, stmt: &VarStmt) -> Result<T, LoxResult>;
     fn visit_while_stmt(&self, base: Rc<Stmt>, stmt: &WhileStmt) -> Result<T, LoxResult>;
     fn visit_break_stmt(&self, base: Rc<Stmt>, stmt: &BreakStmt) -> Result<T, LoxResult>;
@@ -8,8 +8,14 @@ pub enum TokenType {
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
+    Colon,
     Comma,
     Dot,
+    DotDot,
+    DotDotEqual,
+    Ellipsis,
     Minus,
     Plus,
     Semicolon,
@@ -21,6 +27,7 @@ pub enum TokenType {
     BangEqual,
     Equal,
     EqualEqual,
+    FatArrow,
     Greater,
     GreaterEqual,
     Less,
@@ -28,26 +35,40 @@ pub enum TokenType {
 
     // Literals.
     Identifier,
+    PrivateName,
     StringLiteral,
     Number,
 
     // Keywords.
+    Abstract,
     And,
+    Case,
     Class,
+    Const,
+    Default,
     Else,
+    Enum,
     False,
     Fun,
     For,
     If,
+    In,
+    Let,
+    Match,
     Nil,
     Or,
     Print,
+    Record,
     Return,
+    Static,
     Super,
     This,
+    Trait,
     True,
     Var,
     While,
+    With,
+    Yield,
     Break,
     Eof,
 }